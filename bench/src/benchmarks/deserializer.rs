@@ -121,9 +121,29 @@ struct UnitPayload {
     carried: String,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct SensorPayload {
+    temperature: f64,
+    humidity: f64,
+    pressure: f64,
+    voltage: f64,
+    coordinates: CoordinatesPayload,
+    timestamp: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+struct CoordinatesPayload {
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+}
+
 pub mod criterion_benches {
     use super::*;
     use criterion::{BenchmarkId, Criterion, Throughput};
+    use serde::de::IgnoredAny;
     use std::hint::black_box;
 
     fn deserializer(c: &mut Criterion) {
@@ -138,6 +158,58 @@ pub mod criterion_benches {
                 black_box(game);
             });
         });
+
+        let sensors = include_bytes!("../../../assets/corpus/sensors.txt");
+        group.throughput(Throughput::Bytes(sensors.len() as u64));
+        group.bench_function(BenchmarkId::from_parameter("sensors"), |b| {
+            b.iter(|| {
+                let mut deserializer = phpserz::PhpDeserializer::new(sensors.as_slice());
+                let sensors: BTreeMap<String, SensorPayload> =
+                    Deserialize::deserialize(&mut deserializer)
+                        .expect("to deserialize sensors payload");
+                black_box(sensors);
+            });
+        });
+        group.finish();
+
+        let mut group = c.benchmark_group("deserializer-value");
+        for (name, data) in [("awbw", awbw.as_slice()), ("sensors", sensors.as_slice())] {
+            group.throughput(Throughput::Bytes(data.len() as u64));
+            group.bench_function(BenchmarkId::from_parameter(name), |b| {
+                b.iter(|| {
+                    let mut deserializer = phpserz::PhpDeserializer::new(data);
+                    let value: phpserz::PhpValue =
+                        Deserialize::deserialize(&mut deserializer).expect("to deserialize value");
+                    black_box(value);
+                });
+            });
+
+            let mut arena = bumpalo::Bump::new();
+            group.bench_function(BenchmarkId::new("arena", name), |b| {
+                b.iter(|| {
+                    arena.reset();
+                    let mut parser = phpserz::PhpParser::new(data);
+                    let value = parser
+                        .read_arena_value(&arena)
+                        .expect("to read arena value");
+                    black_box(value);
+                });
+            });
+        }
+        group.finish();
+
+        let mut group = c.benchmark_group("deserializer-ignored");
+        for (name, data) in [("awbw", awbw.as_slice()), ("sensors", sensors.as_slice())] {
+            group.throughput(Throughput::Bytes(data.len() as u64));
+            group.bench_function(BenchmarkId::from_parameter(name), |b| {
+                b.iter(|| {
+                    let mut deserializer = phpserz::PhpDeserializer::new(data);
+                    let ignored: IgnoredAny =
+                        Deserialize::deserialize(&mut deserializer).expect("to skip payload");
+                    black_box(ignored);
+                });
+            });
+        }
         group.finish();
     }
 
@@ -148,6 +220,7 @@ pub mod criterion_benches {
 pub mod gungraun_benches {
     use super::*;
     use gungraun::{library_benchmark, library_benchmark_group};
+    use serde::de::IgnoredAny;
 
     #[library_benchmark]
     #[bench::game_awbw(include_bytes!("../../../assets/corpus/awbw.txt").as_slice())]
@@ -156,5 +229,36 @@ pub mod gungraun_benches {
         Deserialize::deserialize(&mut deserializer).expect("to deserialize game payload")
     }
 
-    library_benchmark_group!(name = deserializer_benches, benchmarks = [deserialize_game,]);
+    #[library_benchmark]
+    #[bench::sensors(include_bytes!("../../../assets/corpus/sensors.txt").as_slice())]
+    fn deserialize_sensors(data: &[u8]) -> BTreeMap<String, SensorPayload> {
+        let mut deserializer = phpserz::PhpDeserializer::new(data);
+        Deserialize::deserialize(&mut deserializer).expect("to deserialize sensors payload")
+    }
+
+    #[library_benchmark]
+    #[bench::awbw(include_bytes!("../../../assets/corpus/awbw.txt").as_slice())]
+    #[bench::sensors(include_bytes!("../../../assets/corpus/sensors.txt").as_slice())]
+    fn deserialize_value(data: &[u8]) -> phpserz::PhpValue {
+        let mut deserializer = phpserz::PhpDeserializer::new(data);
+        Deserialize::deserialize(&mut deserializer).expect("to deserialize value")
+    }
+
+    #[library_benchmark]
+    #[bench::awbw(include_bytes!("../../../assets/corpus/awbw.txt").as_slice())]
+    #[bench::sensors(include_bytes!("../../../assets/corpus/sensors.txt").as_slice())]
+    fn deserialize_ignored(data: &[u8]) -> IgnoredAny {
+        let mut deserializer = phpserz::PhpDeserializer::new(data);
+        Deserialize::deserialize(&mut deserializer).expect("to skip payload")
+    }
+
+    library_benchmark_group!(
+        name = deserializer_benches,
+        benchmarks = [
+            deserialize_game,
+            deserialize_sensors,
+            deserialize_value,
+            deserialize_ignored,
+        ]
+    );
 }
@@ -32,7 +32,43 @@ pub mod criterion_benches {
         group.finish();
     }
 
-    criterion::criterion_group!(parser_benches, parser);
+    fn strings(c: &mut Criterion) {
+        let sensors = include_bytes!("../../../assets/corpus/sensors.txt");
+        let mut group = c.benchmark_group("strings");
+        group.throughput(Throughput::Bytes(sensors.len() as u64));
+        group.bench_function(BenchmarkId::new("next_token", "sensors"), |b| {
+            b.iter(|| {
+                let mut parser = phpserz::PhpParser::new(sensors.as_slice());
+                let mut len = 0;
+                while let Ok(Some(token)) = parser.next_token() {
+                    if let phpserz::PhpToken::String(s) = token {
+                        len += s.as_bytes().len();
+                    }
+                }
+                black_box(len);
+            });
+        });
+        group.bench_function(BenchmarkId::new("next_string_bytes", "sensors"), |b| {
+            b.iter(|| {
+                let mut parser = phpserz::PhpParser::new(sensors.as_slice());
+                let mut len = 0;
+                loop {
+                    match parser.next_string_bytes() {
+                        Ok(Some(bytes)) => len += bytes.len(),
+                        Ok(None) => match parser.next_token() {
+                            Ok(Some(_)) => {}
+                            _ => break,
+                        },
+                        Err(_) => break,
+                    }
+                }
+                black_box(len);
+            });
+        });
+        group.finish();
+    }
+
+    criterion::criterion_group!(parser_benches, parser, strings);
 }
 
 #[cfg(not(target_family = "wasm"))]
@@ -51,5 +87,26 @@ pub mod gungraun_benches {
         count
     }
 
-    library_benchmark_group!(name = parser_benches, benchmarks = [parse_tokens,]);
+    #[library_benchmark]
+    #[bench::sensors(include_bytes!("../../../assets/corpus/sensors.txt").as_slice())]
+    fn scan_strings(data: &[u8]) -> usize {
+        let mut parser = phpserz::PhpParser::new(data);
+        let mut len = 0;
+        loop {
+            match parser.next_string_bytes() {
+                Ok(Some(bytes)) => len += bytes.len(),
+                Ok(None) => match parser.next_token() {
+                    Ok(Some(_)) => {}
+                    _ => break,
+                },
+                Err(_) => break,
+            }
+        }
+        len
+    }
+
+    library_benchmark_group!(
+        name = parser_benches,
+        benchmarks = [parse_tokens, scan_strings]
+    );
 }
@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use serde::Deserialize;
+
+fuzz_target!(|data: &[u8]| {
+    let mut deserializer = phpserz::PhpDeserializer::new(data);
+    let _ = serde::de::IgnoredAny::deserialize(&mut deserializer);
+});
@@ -0,0 +1,278 @@
+use crate::errors::{Error, ErrorKind};
+use crate::parser::{PhpParser, PhpToken};
+use bumpalo::Bump;
+use bumpalo::collections::Vec as BumpVec;
+
+/// The key of an entry in a [`PhpArenaValue::Array`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PhpArenaKey<'a> {
+    /// An integer key.
+    Int(i64),
+
+    /// A byte string key, borrowed from the input.
+    Str(&'a [u8]),
+}
+
+/// A dynamically typed PHP value whose strings borrow from the input and
+/// whose containers are allocated in a [`bumpalo`] arena.
+///
+/// Compared to [`PhpValue`](crate::PhpValue), reading a value performs no
+/// per-string heap allocations, and freeing it is a matter of resetting the
+/// arena. This suits services that parse many small payloads, like session
+/// blobs. Read one with [`PhpParser::read_arena_value`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PhpArenaValue<'a> {
+    /// The null value.
+    Null,
+
+    /// A boolean.
+    Bool(bool),
+
+    /// An integer.
+    Int(i64),
+
+    /// A float.
+    Float(f64),
+
+    /// A byte string.
+    String(&'a [u8]),
+
+    /// An ordered array of key value pairs.
+    Array(&'a [(PhpArenaKey<'a>, PhpArenaValue<'a>)]),
+
+    /// An object with its class name and properties. Property names are kept
+    /// as serialized, including any visibility prefix.
+    Object {
+        class: &'a [u8],
+        properties: &'a [(&'a [u8], PhpArenaValue<'a>)],
+    },
+
+    /// A custom serialized object with its class name and opaque payload.
+    CustomObject { class: &'a [u8], payload: &'a [u8] },
+
+    /// An enum case with its class name.
+    Enum { class: &'a [u8], case: &'a [u8] },
+}
+
+impl<'a> PhpParser<'a> {
+    /// Read the next complete value, allocating its containers in the arena.
+    ///
+    /// References have no [`PhpArenaValue`] representation and are reported
+    /// with [`ErrorKind::UnsupportedReference`].
+    ///
+    /// ```rust
+    /// use bumpalo::Bump;
+    /// use phpserz::{PhpArenaKey, PhpArenaValue, PhpParser};
+    ///
+    /// let arena = Bump::new();
+    /// let mut parser = PhpParser::new(b"a:2:{i:0;b:1;s:3:\"foo\";s:3:\"bar\";}");
+    /// let value = parser.read_arena_value(&arena).unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     PhpArenaValue::Array(&[
+    ///         (PhpArenaKey::Int(0), PhpArenaValue::Bool(true)),
+    ///         (PhpArenaKey::Str(b"foo"), PhpArenaValue::String(b"bar")),
+    ///     ])
+    /// );
+    /// ```
+    pub fn read_arena_value(&mut self, arena: &'a Bump) -> Result<PhpArenaValue<'a>, Error> {
+        enum Frame<'a> {
+            Array(BumpVec<'a, (PhpArenaKey<'a>, PhpArenaValue<'a>)>),
+            Object {
+                class: &'a [u8],
+                properties: BumpVec<'a, (&'a [u8], PhpArenaValue<'a>)>,
+            },
+        }
+
+        // Open containers, innermost last, along with the key of the entry
+        // being read once it has been seen.
+        let mut stack: Vec<(Frame<'a>, Option<PhpArenaKey<'a>>)> = Vec::new();
+        loop {
+            let (position, found) = self.upcoming();
+            let token = self.read_token()?;
+
+            if let Some((_, key @ None)) = stack.last_mut() {
+                *key = match token {
+                    PhpToken::Integer(i) => Some(PhpArenaKey::Int(i)),
+                    PhpToken::String(s) => Some(PhpArenaKey::Str(s.as_bytes())),
+                    PhpToken::End => None,
+                    _ => {
+                        let kind = token.kind();
+                        return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+                    }
+                };
+
+                if key.is_some() {
+                    continue;
+                }
+            }
+
+            let value = match token {
+                PhpToken::End => match stack.pop() {
+                    Some((Frame::Array(entries), None)) => {
+                        PhpArenaValue::Array(entries.into_bump_slice())
+                    }
+                    Some((Frame::Object { class, properties }, None)) => PhpArenaValue::Object {
+                        class,
+                        properties: properties.into_bump_slice(),
+                    },
+                    Some((_, Some(_))) | None => {
+                        return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+                    }
+                },
+                PhpToken::Array { elements } => {
+                    let entries = BumpVec::with_capacity_in(elements.min(1024) as usize, arena);
+                    stack.push((Frame::Array(entries), None));
+                    continue;
+                }
+                PhpToken::Object { class, properties } => {
+                    let properties =
+                        BumpVec::with_capacity_in(properties.min(1024) as usize, arena);
+                    let class = class.as_bytes();
+                    stack.push((Frame::Object { class, properties }, None));
+                    continue;
+                }
+                PhpToken::Null => PhpArenaValue::Null,
+                PhpToken::Boolean(b) => PhpArenaValue::Bool(b),
+                PhpToken::Integer(i) => PhpArenaValue::Int(i),
+                PhpToken::Float(f) => PhpArenaValue::Float(f),
+                PhpToken::String(s) => PhpArenaValue::String(s.as_bytes()),
+                PhpToken::CustomObject { class, payload } => PhpArenaValue::CustomObject {
+                    class: class.as_bytes(),
+                    payload: payload.as_bytes(),
+                },
+                PhpToken::Enum { class, case } => PhpArenaValue::Enum {
+                    class: class.as_bytes(),
+                    case: case.as_bytes(),
+                },
+                PhpToken::Reference { id, .. } => {
+                    return Err(Error::from(ErrorKind::UnsupportedReference {
+                        index: id.index(),
+                        position,
+                    }));
+                }
+            };
+
+            let Some((frame, key)) = stack.last_mut() else {
+                return Ok(value);
+            };
+
+            match (frame, key.take()) {
+                (Frame::Array(entries), Some(key)) => entries.push((key, value)),
+                (Frame::Object { properties, .. }, Some(PhpArenaKey::Str(name))) => {
+                    properties.push((name, value))
+                }
+                (Frame::Object { properties, .. }, Some(PhpArenaKey::Int(i))) => {
+                    let name = bumpalo::format!(in arena, "{}", i).into_bump_str();
+                    properties.push((name.as_bytes(), value))
+                }
+                (_, None) => unreachable!("values in containers follow a key"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PhpTokenKind;
+
+    #[test]
+    fn test_read_arena_value_nested() {
+        let arena = Bump::new();
+        let input =
+            b"a:2:{i:0;O:3:\"Foo\":2:{s:6:\"\0*\0bar\";d:1.5;i:3;N;}s:1:\"c\";C:3:\"Baz\":2:{xy}}";
+        let value = PhpParser::new(input).read_arena_value(&arena).unwrap();
+        assert_eq!(
+            value,
+            PhpArenaValue::Array(&[
+                (
+                    PhpArenaKey::Int(0),
+                    PhpArenaValue::Object {
+                        class: b"Foo",
+                        properties: &[
+                            (b"\0*\0bar", PhpArenaValue::Float(1.5)),
+                            (b"3", PhpArenaValue::Null),
+                        ]
+                    }
+                ),
+                (
+                    PhpArenaKey::Str(b"c"),
+                    PhpArenaValue::CustomObject {
+                        class: b"Baz",
+                        payload: b"xy"
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_arena_value_borrows_input() {
+        let arena = Bump::new();
+        let input = b"s:5:\"hello\";";
+        let PhpArenaValue::String(s) = PhpParser::new(input).read_arena_value(&arena).unwrap()
+        else {
+            panic!("expected a string");
+        };
+        assert_eq!(s.as_ptr(), input[5..].as_ptr());
+    }
+
+    #[test]
+    fn test_read_arena_value_reference() {
+        let arena = Bump::new();
+        let error = PhpParser::new(b"a:2:{i:0;i:1;i:1;r:2;}")
+            .read_arena_value(&arena)
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 17
+            }
+        ));
+    }
+
+    #[test]
+    fn test_read_arena_value_large_declared_counts() {
+        // Every level declares far more entries than it holds, which the
+        // trailing padding makes plausible.
+        let depth = 32;
+        let mut input = "a:50000:{i:0;".repeat(depth).into_bytes();
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        input.extend(std::iter::repeat_n(b' ', 50_000 * 6));
+
+        let arena = Bump::new();
+        PhpParser::new(&input).read_arena_value(&arena).unwrap();
+        assert!(
+            arena.allocated_bytes() < 4 << 20,
+            "{}",
+            arena.allocated_bytes()
+        );
+    }
+
+    #[test]
+    fn test_read_arena_value_invalid_key() {
+        let arena = Bump::new();
+        let error = PhpParser::new(b"a:1:{N;i:1;}")
+            .read_arena_value(&arena)
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::InvalidKey {
+                kind: PhpTokenKind::Null,
+                position: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_read_arena_value_eof() {
+        let arena = Bump::new();
+        let error = PhpParser::new(b"a:1:{i:0;a:0:{}")
+            .read_arena_value(&arena)
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Eof));
+    }
+}
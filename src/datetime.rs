@@ -0,0 +1,139 @@
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, Visitor};
+use std::fmt;
+
+/// The state of a serialized PHP `DateTime` or `DateTimeImmutable` object.
+///
+/// PHP serializes these objects as three properties: the local date and time
+/// with microsecond precision, the kind of timezone, and the timezone itself.
+///
+/// ```plain,ignore
+/// O:8:"DateTime":3:{s:4:"date";s:26:"2023-01-01 12:00:00.000000";s:13:"timezone_type";i:3;s:8:"timezone";s:3:"UTC";}
+/// ```
+///
+/// The fields are exposed as is so they can be handed to a date library such
+/// as `chrono` or `jiff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PhpDateTime {
+    /// The local date and time, formatted as `YYYY-MM-DD HH:MM:SS.uuuuuu`.
+    pub date: String,
+
+    /// How [`PhpDateTime::timezone`] is expressed: `1` for a UTC offset like
+    /// `+02:00`, `2` for an abbreviation like `EST`, and `3` for an identifier
+    /// like `Europe/Amsterdam`.
+    pub timezone_type: i64,
+
+    /// The timezone of [`PhpDateTime::date`].
+    pub timezone: String,
+}
+
+impl<'de> Deserialize<'de> for PhpDateTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        const FIELDS: &[&str] = &["date", "timezone_type", "timezone"];
+        deserializer.deserialize_struct("DateTime", FIELDS, PhpDateTimeVisitor)
+    }
+}
+
+struct PhpDateTimeVisitor;
+
+impl<'de> Visitor<'de> for PhpDateTimeVisitor {
+    type Value = PhpDateTime;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a PHP DateTime object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut date = None;
+        let mut timezone_type = None;
+        let mut timezone = None;
+
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "date" => date = Some(map.next_value()?),
+                "timezone_type" => timezone_type = Some(map.next_value()?),
+                "timezone" => timezone = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(PhpDateTime {
+            date: date.ok_or_else(|| de::Error::missing_field("date"))?,
+            timezone_type: timezone_type
+                .ok_or_else(|| de::Error::missing_field("timezone_type"))?,
+            timezone: timezone.ok_or_else(|| de::Error::missing_field("timezone"))?,
+        })
+    }
+}
+
+/// Deserialize a PHP `DateTime` object into its date string and timezone.
+///
+/// Meant to be used with `#[serde(deserialize_with)]`:
+///
+/// ```rust
+/// use phpserz::{PhpDateTime, PhpDeserializer};
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Event {
+///     #[serde(deserialize_with = "phpserz::deserialize_datetime")]
+///     created: PhpDateTime,
+/// }
+///
+/// let input = b"a:1:{s:7:\"created\";O:8:\"DateTime\":3:{s:4:\"date\";s:26:\"2023-01-01 12:00:00.000000\";s:13:\"timezone_type\";i:3;s:8:\"timezone\";s:3:\"UTC\";}}";
+/// let event = Event::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+/// assert_eq!(event.created.date, "2023-01-01 12:00:00.000000");
+/// assert_eq!(event.created.timezone, "UTC");
+/// ```
+pub fn deserialize_datetime<'de, D>(deserializer: D) -> Result<PhpDateTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    PhpDateTime::deserialize(deserializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, PhpDeserializer};
+
+    #[test]
+    fn test_deserialize_datetime() {
+        let input = b"O:8:\"DateTime\":3:{s:4:\"date\";s:26:\"2023-01-01 12:00:00.000000\";s:13:\"timezone_type\";i:3;s:8:\"timezone\";s:3:\"UTC\";}";
+        let result = deserialize_datetime(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(
+            result,
+            PhpDateTime {
+                date: "2023-01-01 12:00:00.000000".to_string(),
+                timezone_type: 3,
+                timezone: "UTC".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_datetime_immutable_offset() {
+        let input = b"O:17:\"DateTimeImmutable\":3:{s:4:\"date\";s:26:\"2024-06-30 08:15:00.250000\";s:13:\"timezone_type\";i:1;s:8:\"timezone\";s:6:\"+02:00\";}";
+        let result = deserialize_datetime(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(result.date, "2024-06-30 08:15:00.250000");
+        assert_eq!(result.timezone_type, 1);
+        assert_eq!(result.timezone, "+02:00");
+    }
+
+    #[test]
+    fn test_deserialize_datetime_missing_field() {
+        let input = b"O:8:\"DateTime\":1:{s:4:\"date\";s:26:\"2023-01-01 12:00:00.000000\";}";
+        let error = deserialize_datetime(&mut PhpDeserializer::new(input)).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. } if message == "missing field `timezone_type`"
+        ));
+    }
+}
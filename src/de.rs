@@ -1,21 +1,208 @@
 use crate::errors::{Error, ErrorKind};
-use crate::parser::{PhpParser, PhpToken, PhpTokenKind};
+use crate::parser::{DEFAULT_MAX_DEPTH, PhpCheckpoint, PhpParser, PhpToken, PhpTokenKind};
+use crate::value::canonical_int;
 use serde::Deserializer;
-use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess};
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, MapAccess, SeqAccess};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// When the next token stands in for another value, like a reference that is
+/// being resolved, deserialize that value instead.
+macro_rules! substitute {
+    ($de:ident.$method:ident($($arg:expr),*)) => {
+        if let Some(mut target) = $de.substitute()? {
+            let result = (&mut target).$method($($arg),*);
+            $de.references = target.references.take();
+            $de.total_bytes = target.total_bytes;
+            $de.reference_bytes = target.reference_bytes;
+            return result;
+        }
+    };
+}
+
+/// How a struct is deserialized from an object or array that holds the same
+/// property more than once.
+///
+/// PHP never serializes duplicate properties, but crafted input can, and a
+/// field that is resolved differently by different consumers of the same
+/// payload can be abused. Property names are compared after demangling, so
+/// `s:4:"name";` and `s:10:"\0Foo\0name";` are duplicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateFields {
+    /// Hand every entry to the struct, which fails on a duplicate field as
+    /// serde derived structs do.
+    #[default]
+    Error,
+
+    /// Keep the first occurrence of a property and skip the rest.
+    First,
+
+    /// Keep the last occurrence of a property and skip the earlier ones, like
+    /// PHP does when it unserializes an object.
+    Last,
+}
 
 /// A deserializer for PHP serialized data.
+///
+/// `&mut PhpDeserializer` implements [`Deserializer`], so besides
+/// [`Deserialize`](serde::Deserialize) types it accepts any
+/// [`DeserializeSeed`]. Seeds carry runtime state into deserialization, like
+/// a string interner or a class registry:
+///
+/// ```rust
+/// use phpserz::PhpDeserializer;
+/// use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
+/// use std::collections::HashSet;
+/// use std::fmt;
+///
+/// /// Deserializes a list of strings, interning each one into the pool.
+/// struct Interned<'a>(&'a mut HashSet<String>);
+///
+/// impl<'de> DeserializeSeed<'de> for Interned<'_> {
+///     type Value = usize;
+///
+///     fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+///         deserializer.deserialize_seq(self)
+///     }
+/// }
+///
+/// impl<'de> Visitor<'de> for Interned<'_> {
+///     type Value = usize;
+///
+///     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+///         formatter.write_str("a list of strings")
+///     }
+///
+///     fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+///         let mut count = 0;
+///         while let Some(s) = seq.next_element::<String>()? {
+///             self.0.insert(s);
+///             count += 1;
+///         }
+///         Ok(count)
+///     }
+/// }
+///
+/// let mut pool = HashSet::new();
+/// let input = b"a:3:{i:0;s:1:\"a\";i:1;s:1:\"b\";i:2;s:1:\"a\";}";
+/// let mut deserializer = PhpDeserializer::new(input);
+/// let count = Interned(&mut pool).deserialize(&mut deserializer).unwrap();
+/// assert_eq!(count, 3);
+/// assert_eq!(pool.len(), 2);
+/// ```
 #[derive(Debug)]
 pub struct PhpDeserializer<'de> {
     parser: PhpParser<'de>,
+    float_to_int: bool,
+    lenient_bools: bool,
+    max_depth: usize,
+    depth: usize,
+    max_total_bytes: usize,
+    total_bytes: usize,
+    coerce_numeric_keys: bool,
+    in_key: bool,
+    ignore_sequence_keys: bool,
+    positional_structs: bool,
+    duplicate_fields: DuplicateFields,
+    closures_as_null: bool,
+    empty_string_as_none: bool,
+    resolve_references: bool,
+    max_reference_bytes: usize,
+    reference_bytes: usize,
+    references: Option<ReferenceTargets<'de>>,
+
+    // Where deserialization started, which is where reference targets are
+    // looked for from.
+    origin: PhpCheckpoint,
+    allowed_classes: Option<ClassFilter>,
+
+    // Where the containers skipped while looking for duplicate fields end,
+    // by where they start.
+    spans: BTreeMap<usize, PhpCheckpoint>,
+}
+
+type ClassPredicate = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// The predicate given to [`PhpDeserializer::allow_class`].
+#[derive(Clone)]
+struct ClassFilter(Arc<ClassPredicate>);
+
+impl std::fmt::Debug for ClassFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ClassFilter")
+    }
+}
+
+/// The default for [`PhpDeserializer::max_reference_bytes`].
+const DEFAULT_MAX_REFERENCE_BYTES: usize = 4 * 1024 * 1024;
+
+/// The byte ranges of the values that references can point to, indexed by
+/// reference number minus one, less the values seen before deserialization
+/// started. Built incrementally as references are found.
+#[derive(Debug)]
+struct ReferenceTargets<'de> {
+    parser: PhpParser<'de>,
+    base: u64,
+
+    // Containers that have not ended yet, innermost last.
+    open: Vec<usize>,
+    ranges: Vec<Range<usize>>,
+}
+
+impl<'de> ReferenceTargets<'de> {
+    fn new(parser: &PhpParser<'de>, origin: PhpCheckpoint) -> Self {
+        let mut parser = parser.rewind();
+        parser.restore(origin);
+        ReferenceTargets {
+            base: parser.value_count(),
+            parser,
+            open: Vec::new(),
+            ranges: Vec::new(),
+        }
+    }
+
+    /// The range of the value with the given reference id, if it has been
+    /// recorded.
+    fn get(&self, id: u64) -> Option<&Range<usize>> {
+        let index = id.checked_sub(self.base + 1)?;
+        self.ranges.get(usize::try_from(index).ok()?)
+    }
+
+    /// Record the values that start before the position. Containers that are
+    /// still open at that point end at `usize::MAX`.
+    fn advance_to(&mut self, position: usize) -> Result<(), Error> {
+        while self.parser.position() < position {
+            let start = self.parser.position();
+            let Some((token, id)) = self.parser.next_token_with_ref_id()? else {
+                break;
+            };
+
+            match token {
+                PhpToken::End => {
+                    if let Some(index) = self.open.pop() {
+                        self.ranges[index].end = self.parser.position();
+                    }
+                }
+                PhpToken::Array { .. } | PhpToken::Object { .. } if id.is_some() => {
+                    self.open.push(self.ranges.len());
+                    self.ranges.push(start..usize::MAX);
+                }
+                _ if id.is_some() => self.ranges.push(start..self.parser.position()),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<'de> PhpDeserializer<'de> {
     /// Create a new deserializer from a slice of bytes.
     #[must_use]
     pub const fn new(data: &'de [u8]) -> Self {
-        PhpDeserializer {
-            parser: PhpParser::new(data),
-        }
+        Self::from_parser(PhpParser::new(data))
     }
 
     /// Create a new deserializer from an existing parser.
@@ -24,7 +211,362 @@ impl<'de> PhpDeserializer<'de> {
     /// and want to deserialize the remaining part.
     #[must_use]
     pub const fn from_parser(parser: PhpParser<'de>) -> Self {
-        PhpDeserializer { parser }
+        let origin = parser.checkpoint();
+        PhpDeserializer {
+            parser,
+            float_to_int: false,
+            lenient_bools: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            depth: 0,
+            max_total_bytes: usize::MAX,
+            total_bytes: 0,
+            coerce_numeric_keys: true,
+            in_key: false,
+            ignore_sequence_keys: false,
+            positional_structs: false,
+            duplicate_fields: DuplicateFields::Error,
+            closures_as_null: false,
+            empty_string_as_none: false,
+            resolve_references: false,
+            max_reference_bytes: DEFAULT_MAX_REFERENCE_BYTES,
+            reference_bytes: 0,
+            references: None,
+            origin,
+            allowed_classes: None,
+            spans: BTreeMap::new(),
+        }
+    }
+
+    /// Allow integer fields to be deserialized from floats that hold an exact
+    /// integer value, like `d:42;` (default: `false`).
+    ///
+    /// Floats with a fractional part or outside of the `i64` range are
+    /// rejected. This is opt-in as the coercion can hide data issues.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let mut deserializer = PhpDeserializer::new(b"d:42;").float_to_int(true);
+    /// assert_eq!(i64::deserialize(&mut deserializer).unwrap(), 42);
+    /// ```
+    #[must_use]
+    pub const fn float_to_int(mut self, enabled: bool) -> Self {
+        self.float_to_int = enabled;
+        self
+    }
+
+    /// Allow booleans to be deserialized from integers and strings that PHP
+    /// code commonly uses in their place (default: `false`).
+    ///
+    /// Besides `b:0;` and `b:1;`, the integers `i:0;` and `i:1;` and the
+    /// strings `"0"`, `"1"`, `"false"`, and `"true"` are accepted. Any other
+    /// value is rejected as usual.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let mut deserializer = PhpDeserializer::new(b"i:1;").lenient_bools(true);
+    /// assert!(bool::deserialize(&mut deserializer).unwrap());
+    ///
+    /// let mut deserializer = PhpDeserializer::new(b"i:1;");
+    /// assert!(bool::deserialize(&mut deserializer).is_err());
+    /// ```
+    #[must_use]
+    pub const fn lenient_bools(mut self, enabled: bool) -> Self {
+        self.lenient_bools = enabled;
+        self
+    }
+
+    /// Set how deeply arrays and objects may be nested before deserialization
+    /// fails with [`ErrorKind::DepthLimitExceeded`] (default: `128`).
+    ///
+    /// Deserialization recurses for each level of nesting, so the limit
+    /// protects against stack overflows from untrusted input.
+    ///
+    /// ```rust
+    /// use phpserz::{ErrorKind, PhpDeserializer};
+    /// use serde::Deserialize;
+    /// let mut deserializer = PhpDeserializer::new(b"a:1:{i:0;a:1:{i:0;a:0:{}}}").max_depth(2);
+    /// let error = Vec::<Vec<Vec<i64>>>::deserialize(&mut deserializer).unwrap_err();
+    /// assert!(matches!(error.kind(), ErrorKind::DepthLimitExceeded { .. }));
+    /// ```
+    #[must_use]
+    pub const fn max_depth(mut self, limit: usize) -> Self {
+        self.max_depth = limit;
+        self
+    }
+
+    /// Set how many bytes deserialization may materialize before failing
+    /// with [`ErrorKind::ResourceLimit`] (default: unlimited).
+    ///
+    /// The total is an estimate of what the deserialized value allocates:
+    /// every string counts its length, and every array or object counts one
+    /// byte per declared entry. Each resolved reference counts its target
+    /// again, so the limit also caps payloads that are small but expand
+    /// through [`PhpDeserializer::resolve_references`]. Pair it with
+    /// [`PhpDeserializer::max_depth`] to bound the work done on untrusted
+    /// input. Values that are read only to be ignored count as well.
+    ///
+    /// ```rust
+    /// use phpserz::{ErrorKind, PhpDeserializer};
+    /// use serde::Deserialize;
+    /// let input = b"a:2:{i:0;s:5:\"hello\";i:1;s:5:\"world\";}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).max_total_bytes(12);
+    /// assert_eq!(Vec::<String>::deserialize(&mut deserializer).unwrap().len(), 2);
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).max_total_bytes(11);
+    /// let error = Vec::<String>::deserialize(&mut deserializer).unwrap_err();
+    /// assert!(matches!(error.kind(), ErrorKind::ResourceLimit { limit: 11, .. }));
+    /// ```
+    #[must_use]
+    pub const fn max_total_bytes(mut self, limit: usize) -> Self {
+        self.max_total_bytes = limit;
+        self
+    }
+
+    /// Treat map keys that are canonical integer strings, like `"5"`, as the
+    /// integer they spell (default: `true`).
+    ///
+    /// This matches PHP, which stores such keys as integers, so a key like
+    /// `s:1:"5";` can be read into an integer key type. Keys read into string
+    /// types keep their original text either way. When disabled, these keys
+    /// are only ever strings.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// use std::collections::HashMap;
+    /// let input = b"a:1:{s:1:\"5\";b:1;}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input);
+    /// let map = HashMap::<i64, bool>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(map[&5], true);
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).coerce_numeric_keys(false);
+    /// assert!(HashMap::<i64, bool>::deserialize(&mut deserializer).is_err());
+    /// ```
+    #[must_use]
+    pub const fn coerce_numeric_keys(mut self, enabled: bool) -> Self {
+        self.coerce_numeric_keys = enabled;
+        self
+    }
+
+    /// Deserialize sequences from the values of an array in serialized order,
+    /// whatever their keys (default: `false`).
+    ///
+    /// By default a sequence like a `Vec` is only deserialized from a PHP list,
+    /// an array whose keys are `0`, `1`, `2`, and so on. An array with gaps in
+    /// its keys, like `[0 => "a", 2 => "c"]`, or with string keys fails rather
+    /// than silently losing the keys. When keys matter, deserialize into a map
+    /// like `BTreeMap<i64, T>` instead.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let input = b"a:2:{i:0;s:1:\"a\";i:2;s:1:\"c\";}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input);
+    /// assert!(Vec::<String>::deserialize(&mut deserializer).is_err());
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).ignore_sequence_keys(true);
+    /// let values = Vec::<String>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(values, vec!["a", "c"]);
+    /// ```
+    #[must_use]
+    pub const fn ignore_sequence_keys(mut self, enabled: bool) -> Self {
+        self.ignore_sequence_keys = enabled;
+        self
+    }
+
+    /// Deserialize structs from PHP lists by position, the first element
+    /// going to the first field and so on (default: `false`).
+    ///
+    /// This is for PHP code that serializes records as tuples. Arrays with
+    /// string keys and objects are still matched to fields by name.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Debug, Deserialize, PartialEq)]
+    /// struct Person {
+    ///     name: String,
+    ///     age: u32,
+    /// }
+    ///
+    /// let input = b"a:2:{i:0;s:5:\"Alice\";i:1;i:30;}";
+    /// let mut deserializer = PhpDeserializer::new(input).positional_structs(true);
+    /// let person = Person::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(person, Person { name: "Alice".to_string(), age: 30 });
+    /// ```
+    #[must_use]
+    pub const fn positional_structs(mut self, enabled: bool) -> Self {
+        self.positional_structs = enabled;
+        self
+    }
+
+    /// Choose how structs handle a property that appears more than once
+    /// (default: [`DuplicateFields::Error`]).
+    ///
+    /// Only structs are affected: maps receive every entry as is.
+    ///
+    /// ```rust
+    /// use phpserz::{DuplicateFields, PhpDeserializer};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct User {
+    ///     role: String,
+    /// }
+    ///
+    /// let input = b"a:2:{s:4:\"role\";s:4:\"user\";s:4:\"role\";s:5:\"admin\";}";
+    /// let mut deserializer = PhpDeserializer::new(input);
+    /// assert!(User::deserialize(&mut deserializer).is_err());
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).duplicate_fields(DuplicateFields::First);
+    /// assert_eq!(User::deserialize(&mut deserializer).unwrap().role, "user");
+    /// ```
+    #[must_use]
+    pub const fn duplicate_fields(mut self, policy: DuplicateFields) -> Self {
+        self.duplicate_fields = policy;
+        self
+    }
+
+    /// Deserialize the placeholder `O:7:"Closure":0:{}` as null (default:
+    /// `false`).
+    ///
+    /// PHP can't serialize closures, but some payloads carry this empty
+    /// object in their place. By default it fails with
+    /// [`ErrorKind::UnsupportedClass`] wherever it is found, instead of with
+    /// whatever error the expected type would give for an empty object.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let input = b"a:1:{i:0;O:7:\"Closure\":0:{}}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input);
+    /// assert!(Vec::<Option<i64>>::deserialize(&mut deserializer).is_err());
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).closures_as_null(true);
+    /// let values = Vec::<Option<i64>>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(values, vec![None]);
+    /// ```
+    #[must_use]
+    pub const fn closures_as_null(mut self, enabled: bool) -> Self {
+        self.closures_as_null = enabled;
+        self
+    }
+
+    /// Deserialize an empty string, `s:0:"";`, as `None` when an `Option` is
+    /// expected (default: `false`).
+    ///
+    /// Legacy PHP code often stores an empty string rather than null for an
+    /// absent value. This is opt-in as it makes `Some("")` impossible to
+    /// deserialize.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let mut deserializer = PhpDeserializer::new(b"s:0:\"\";").empty_string_as_none(true);
+    /// assert_eq!(Option::<String>::deserialize(&mut deserializer).unwrap(), None);
+    /// ```
+    #[must_use]
+    pub const fn empty_string_as_none(mut self, enabled: bool) -> Self {
+        self.empty_string_as_none = enabled;
+        self
+    }
+
+    /// Resolve references (`r:N;` and `R:N;`) by deserializing the earlier
+    /// value they point to (default: `false`).
+    ///
+    /// Serde has no notion of shared values, so each reference produces an
+    /// independent copy. A reference to a value that encloses it, like an
+    /// object property that points back to the object, can't be represented
+    /// and fails with [`ErrorKind::UnsupportedReference`], as do all
+    /// references when resolution is disabled. Resolving a reference counts
+    /// as one level of nesting towards [`PhpDeserializer::max_depth`].
+    /// References are resolved in a single pass, so one that points at a
+    /// value later in the input fails with [`ErrorKind::ForwardReference`].
+    /// Only values read by this deserializer can be referenced: with
+    /// [`PhpDeserializer::from_parser`], the values the parser read before
+    /// are not looked at again. Resolution is bounded by
+    /// [`PhpDeserializer::max_reference_bytes`].
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// let input = b"a:2:{i:0;s:3:\"foo\";i:1;r:2;}";
+    /// let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+    /// let values = Vec::<String>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(values, vec!["foo", "foo"]);
+    /// ```
+    #[must_use]
+    pub const fn resolve_references(mut self, enabled: bool) -> Self {
+        self.resolve_references = enabled;
+        self
+    }
+
+    /// Set how many bytes of input resolving references may read again
+    /// before failing with [`ErrorKind::ResourceLimit`] (default: 4 MiB).
+    ///
+    /// Each resolved reference deserializes its target again, and targets
+    /// can hold references of their own, so a small payload can expand
+    /// exponentially. Every resolution counts the length of its target in
+    /// the input, including resolutions made while reading another target.
+    ///
+    /// ```rust
+    /// use phpserz::{ErrorKind, PhpDeserializer};
+    /// use serde::Deserialize;
+    /// let input = b"a:3:{i:0;s:3:\"foo\";i:1;r:2;i:2;r:2;}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input)
+    ///     .resolve_references(true)
+    ///     .max_reference_bytes(20);
+    /// assert_eq!(Vec::<String>::deserialize(&mut deserializer).unwrap().len(), 3);
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input)
+    ///     .resolve_references(true)
+    ///     .max_reference_bytes(19);
+    /// let error = Vec::<String>::deserialize(&mut deserializer).unwrap_err();
+    /// assert!(matches!(error.kind(), ErrorKind::ResourceLimit { limit: 19, .. }));
+    /// ```
+    #[must_use]
+    pub const fn max_reference_bytes(mut self, limit: usize) -> Self {
+        self.max_reference_bytes = limit;
+        self
+    }
+
+    /// Only deserialize objects and enum cases whose class name is accepted by
+    /// the predicate (default: all classes are accepted).
+    ///
+    /// Mirrors the `allowed_classes` option of PHP's `unserialize` as a
+    /// defense against object injection. Objects, custom serialized objects,
+    /// and enum cases of a rejected class fail with
+    /// [`ErrorKind::UnsupportedClass`], even when the expected type would
+    /// accept them.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// use serde::Deserialize;
+    /// use std::collections::HashMap;
+    /// let input = b"O:6:\"Person\":1:{s:4:\"name\";s:5:\"Alice\";}";
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).allow_class(|class| class == b"Person");
+    /// let person = HashMap::<String, String>::deserialize(&mut deserializer).unwrap();
+    /// assert_eq!(person["name"], "Alice");
+    ///
+    /// let mut deserializer = PhpDeserializer::new(input).allow_class(|_| false);
+    /// assert!(HashMap::<String, String>::deserialize(&mut deserializer).is_err());
+    /// ```
+    #[must_use]
+    pub fn allow_class(
+        mut self,
+        predicate: impl Fn(&[u8]) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.allowed_classes = Some(ClassFilter(Arc::new(predicate)));
+        self
     }
 
     /// Consume this deserializer and return the underlying parser.
@@ -35,6 +577,433 @@ impl<'de> PhpDeserializer<'de> {
         self.parser
     }
 
+    /// Read the next token as a string and return its bytes borrowed from the
+    /// input.
+    ///
+    /// This is an escape hatch for callers that mix serde with manual
+    /// extraction and want the raw borrow without writing a visitor.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// let mut deserializer = PhpDeserializer::new(b"s:3:\"\xffab\";");
+    /// assert_eq!(deserializer.next_borrowed_bytes().unwrap(), b"\xffab");
+    /// ```
+    pub fn next_borrowed_bytes(&mut self) -> Result<&'de [u8], Error> {
+        if let Some(s) = self.parser.try_read_str() {
+            return Ok(s.as_bytes());
+        }
+
+        let position = self.parser.position();
+        match self.read_token()? {
+            PhpToken::String(s) => Ok(s.as_bytes()),
+            token => Err(Error::from(ErrorKind::Deserialize {
+                message: format!("Expected string but found {token:?}"),
+                position: Some(position),
+            })),
+        }
+    }
+
+    /// Read the next token as a UTF-8 string borrowed from the input.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// let mut deserializer = PhpDeserializer::new(b"s:5:\"hello\";");
+    /// assert_eq!(deserializer.next_borrowed_str().unwrap(), "hello");
+    /// ```
+    pub fn next_borrowed_str(&mut self) -> Result<&'de str, Error> {
+        let bytes = self.next_borrowed_bytes()?;
+        std::str::from_utf8(bytes).map_err(|e| Error::from(ErrorKind::Utf8(e)))
+    }
+
+    /// Read the next value as an array and collect its values, discarding
+    /// the keys.
+    ///
+    /// Unlike deserializing a `Vec<T>`, which accepts any sequence-like
+    /// input, this states that the value is a PHP array: objects are
+    /// rejected, and the keys don't need to be `0..n` in order.
+    ///
+    /// ```rust
+    /// use phpserz::PhpDeserializer;
+    /// let input = b"a:2:{i:3;s:1:\"a\";s:1:\"k\";s:1:\"b\";}";
+    /// let mut deserializer = PhpDeserializer::new(input);
+    /// assert_eq!(deserializer.values::<String>().unwrap(), vec!["a", "b"]);
+    ///
+    /// let mut deserializer = PhpDeserializer::new(b"O:8:\"stdClass\":0:{}");
+    /// assert!(deserializer.values::<String>().is_err());
+    /// ```
+    pub fn values<T: Deserialize<'de>>(&mut self) -> Result<Vec<T>, Error> {
+        substitute!(self.values());
+
+        let position = self.parser.position();
+        let elements = match self.read_token()? {
+            PhpToken::Array { elements } => elements,
+            token => {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: format!("Expected array but found {token:?}"),
+                    position: Some(position),
+                }));
+            }
+        };
+
+        self.nested(elements, |de| {
+            let mut seq = PhpValueSeqAccess {
+                de,
+                remaining: elements,
+                ended: false,
+            };
+
+            let mut values = Vec::with_capacity(elements.min(1024) as usize);
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
+        })
+    }
+
+    /// Skip the next value like [`PhpParser::skip_value`], remembering where
+    /// each container in it ends. Containers already seen are jumped over, so
+    /// nested structs under [`DuplicateFields::Last`] don't walk the same
+    /// bytes again.
+    fn skip_value_spanned(&mut self) -> Result<(), Error> {
+        let mut open = Vec::new();
+        loop {
+            let (position, found) = self.parser.upcoming();
+            if let Some(end) = self.spans.get(&position) {
+                self.parser.restore(*end);
+            } else {
+                match self.parser.read_token()? {
+                    PhpToken::Array { .. } | PhpToken::Object { .. } => open.push(position),
+                    PhpToken::End => {
+                        let start = open
+                            .pop()
+                            .ok_or(ErrorKind::UnexpectedByte { found, position })?;
+                        self.spans.insert(start, self.parser.checkpoint());
+                    }
+                    _ => {}
+                }
+            }
+
+            if open.is_empty() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Count bytes towards [`PhpDeserializer::max_total_bytes`].
+    #[inline]
+    fn charge(&mut self, bytes: usize) -> Result<(), Error> {
+        self.total_bytes = self.total_bytes.saturating_add(bytes);
+        if self.total_bytes > self.max_total_bytes {
+            return Err(Error::from(ErrorKind::ResourceLimit {
+                limit: self.max_total_bytes,
+                position: self.parser.position(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Run `f` one level of nesting deeper, failing if that exceeds the limit,
+    /// and count the `entries` of the container being entered.
+    #[inline]
+    fn nested<T>(
+        &mut self,
+        entries: u32,
+        f: impl FnOnce(&mut Self) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        self.charge(entries as usize)?;
+        if self.depth >= self.max_depth {
+            return Err(Error::from(ErrorKind::DepthLimitExceeded {
+                position: self.parser.position(),
+            }));
+        }
+
+        self.depth += 1;
+        let result = f(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn float_as_int(&self, value: f64) -> Result<i64, Error> {
+        // 2^63 is exactly representable while i64::MAX is not, so compare
+        // against the exclusive upper bound.
+        const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+        if value.fract() == 0.0 && (-LIMIT..LIMIT).contains(&value) {
+            Ok(value as i64)
+        } else {
+            Err(Error::from(ErrorKind::Deserialize {
+                message: format!("Float {value} is not an exact integer"),
+                position: Some(self.parser.position()),
+            }))
+        }
+    }
+
+    /// Consume the reference that is the next token and return a deserializer
+    /// positioned at the value it points to, or an error if references aren't
+    /// being resolved.
+    #[inline]
+    fn referenced(&mut self) -> Result<Option<PhpDeserializer<'de>>, Error> {
+        let (position, _) = self.parser.upcoming();
+        let PhpToken::Reference { id, .. } = self.parser.read_token()? else {
+            unreachable!("peeked a reference");
+        };
+
+        if !self.resolve_references {
+            return Err(Error::from(ErrorKind::UnsupportedReference {
+                index: id.index(),
+                position,
+            }));
+        }
+
+        let mut references = self
+            .references
+            .take()
+            .unwrap_or_else(|| ReferenceTargets::new(&self.parser, self.origin));
+        let advanced = references.advance_to(position);
+        let target = references
+            .get(id.index())
+            .filter(|range| range.end <= position)
+            .cloned();
+        self.references = Some(references);
+        advanced?;
+
+        let Some(target) = target else {
+            return Err(Error::from(ErrorKind::UnsupportedReference {
+                index: id.index(),
+                position,
+            }));
+        };
+
+        if self.depth >= self.max_depth {
+            return Err(Error::from(ErrorKind::DepthLimitExceeded {
+                position: self.parser.position(),
+            }));
+        }
+
+        self.reference_bytes = self.reference_bytes.saturating_add(target.len());
+        if self.reference_bytes > self.max_reference_bytes {
+            return Err(Error::from(ErrorKind::ResourceLimit {
+                limit: self.max_reference_bytes,
+                position: self.parser.position(),
+            }));
+        }
+
+        let parser = self.parser.at(target.start);
+        Ok(Some(self.redirect(parser)))
+    }
+
+    /// When the next token is a `Closure` placeholder object, consume it and
+    /// return a deserializer of null, or an error if closures aren't being
+    /// read as null.
+    #[inline]
+    fn closure(&mut self) -> Result<Option<PhpDeserializer<'de>>, Error> {
+        const CLOSURE: &[u8] = b"O:7:\"Closure\":0:{}";
+        if !self.parser.starts_with(CLOSURE) {
+            return Ok(None);
+        }
+
+        let position = self.parser.position();
+        if !self.closures_as_null {
+            return Err(Error::from(ErrorKind::UnsupportedClass {
+                class: String::from("Closure"),
+                position,
+            }));
+        }
+
+        self.parser.read_token()?;
+        self.parser.read_token()?;
+        Ok(Some(self.redirect(PhpParser::new(b"N;"))))
+    }
+
+    /// Consume the end of an array or object once a sequence visitor is done
+    /// with it, unless the visitor already read past it.
+    fn finish_seq(&mut self, ended: bool, remaining: u32) -> Result<(), Error> {
+        if ended || (remaining == 0 && self.parser.try_read_end()) {
+            return Ok(());
+        }
+
+        Err(self.expected_seq_end())
+    }
+
+    /// The error for a sequence with entries left where its end is expected.
+    fn expected_seq_end(&self) -> Error {
+        Error::from(ErrorKind::Deserialize {
+            message: "Expected end of sequence".to_string(),
+            position: Some(self.parser.position()),
+        })
+    }
+
+    /// Read the next token, failing on an object or enum case of a class
+    /// rejected by [`PhpDeserializer::allow_class`]. Every path that consumes
+    /// a value goes through here, so the class is checked exactly once.
+    #[inline]
+    fn read_token(&mut self) -> Result<PhpToken<'de>, Error> {
+        let Some(ClassFilter(allowed)) = &self.allowed_classes else {
+            return self.parser.read_token();
+        };
+
+        let (position, _) = self.parser.upcoming();
+        match self.parser.read_token()? {
+            PhpToken::Object { class, .. }
+            | PhpToken::CustomObject { class, .. }
+            | PhpToken::Enum { class, .. }
+                if !allowed(class.as_bytes()) =>
+            {
+                Err(Error::from(ErrorKind::UnsupportedClass {
+                    class: String::from_utf8_lossy(class.as_bytes()).into_owned(),
+                    position,
+                }))
+            }
+            token => Ok(token),
+        }
+    }
+
+    /// The value to deserialize in place of the next token, if any.
+    #[inline]
+    fn substitute(&mut self) -> Result<Option<PhpDeserializer<'de>>, Error> {
+        match self.parser.upcoming() {
+            (_, b'r' | b'R') => self.referenced(),
+            (_, b'O') => self.closure(),
+            _ => Ok(None),
+        }
+    }
+
+    /// A deserializer with the same options, one level deeper, that reads
+    /// from `parser`. Hands over the reference targets, which the caller
+    /// takes back once done.
+    fn redirect(&mut self, parser: PhpParser<'de>) -> PhpDeserializer<'de> {
+        PhpDeserializer {
+            parser,
+            float_to_int: self.float_to_int,
+            lenient_bools: self.lenient_bools,
+            max_depth: self.max_depth,
+            depth: self.depth + 1,
+            max_total_bytes: self.max_total_bytes,
+            total_bytes: self.total_bytes,
+            coerce_numeric_keys: self.coerce_numeric_keys,
+            in_key: false,
+            ignore_sequence_keys: self.ignore_sequence_keys,
+            positional_structs: self.positional_structs,
+            duplicate_fields: self.duplicate_fields,
+            closures_as_null: self.closures_as_null,
+            empty_string_as_none: self.empty_string_as_none,
+            resolve_references: self.resolve_references,
+            max_reference_bytes: self.max_reference_bytes,
+            reference_bytes: self.reference_bytes,
+            references: self.references.take(),
+            origin: self.origin,
+            allowed_classes: self.allowed_classes.clone(),
+            spans: BTreeMap::new(),
+        }
+    }
+
+    /// Read the next token as an integer, handing back any token that isn't
+    /// one.
+    fn read_i64(&mut self) -> Result<Result<i64, PhpToken<'de>>, Error> {
+        if let Some(i) = self.parser.try_read_i64() {
+            return Ok(Ok(i));
+        }
+
+        match self.read_scalar_token("integer")? {
+            PhpToken::Integer(i) => Ok(Ok(i)),
+            PhpToken::Float(f) if self.float_to_int => self.float_as_int(f).map(Ok),
+            token => Ok(Err(token)),
+        }
+    }
+
+    /// Read the next token for a scalar type, rejecting an array or object
+    /// with an error that points at it rather than letting the visitor
+    /// report a type mismatch without a position.
+    fn read_scalar_token(&mut self, expected: &str) -> Result<PhpToken<'de>, Error> {
+        let (position, _) = self.parser.upcoming();
+        let found = match self.read_token()? {
+            PhpToken::Array { .. } => "array",
+            PhpToken::Object { .. } => "object",
+            token => return Ok(token),
+        };
+
+        Err(Error::from(ErrorKind::Deserialize {
+            message: format!("Expected {expected}, found {found}"),
+            position: Some(position),
+        }))
+    }
+
+    fn narrow_int<T: TryFrom<i64>>(&self, value: i64) -> Result<T, Error> {
+        T::try_from(value).map_err(|_| {
+            Error::from(ErrorKind::Deserialize {
+                message: format!(
+                    "Integer {value} is out of range for {}",
+                    std::any::type_name::<T>()
+                ),
+                position: Some(self.parser.position()),
+            })
+        })
+    }
+
+    /// Deserialize an object of one of the SPL classes. The custom
+    /// serialization used before PHP 7.4 is handed to the visitor as a
+    /// sequence of its values, read with the same options but without
+    /// resolving references, as their ids continue from the outer payload.
+    #[cfg(feature = "spl")]
+    fn deserialize_spl<V>(
+        &mut self,
+        class: crate::spl::SplClass,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        let position = self.parser.position();
+        let payload = match self.parser.at(position).read_token()? {
+            PhpToken::Object { class: name, .. } | PhpToken::CustomObject { class: name, .. }
+                if !class.accepts(name.as_bytes()) =>
+            {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: format!(
+                        "Expected {}, found class {}",
+                        class.expecting(),
+                        String::from_utf8_lossy(name.as_bytes())
+                    ),
+                    position: Some(position),
+                }));
+            }
+            PhpToken::Object { .. } => return de::Deserializer::deserialize_any(self, visitor),
+            PhpToken::CustomObject { payload, .. } => payload,
+            token => {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: format!("Expected {}, found {token:?}", class.expecting()),
+                    position: Some(position),
+                }));
+            }
+        };
+
+        self.read_token()?;
+        let Some(data) = payload.as_bytes().strip_prefix(b"x:") else {
+            return Err(Error::from(ErrorKind::Deserialize {
+                message: "SPL payload does not start with `x:`".to_string(),
+                position: Some(position),
+            }));
+        };
+
+        let references = self.references.take();
+        let mut inner = self.redirect(PhpParser::new(data));
+        inner.resolve_references = false;
+        let result = visitor
+            .visit_seq(SplPayloadAccess {
+                de: &mut inner,
+                class,
+                index: 0,
+            })
+            .and_then(|value| {
+                inner.parser.expect(b'm')?;
+                inner.parser.expect(b':')?;
+                Ok(value)
+            });
+        self.references = references;
+        self.total_bytes = inner.total_bytes;
+        self.reference_bytes = inner.reference_bytes;
+        result
+    }
+
     fn deserialize_token<V>(&mut self, visitor: V, token: PhpToken<'de>) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
@@ -44,19 +1013,31 @@ impl<'de> PhpDeserializer<'de> {
             PhpToken::Boolean(b) => visitor.visit_bool(b),
             PhpToken::Integer(i) => visitor.visit_i64(i),
             PhpToken::Float(f) => visitor.visit_f64(f),
-            PhpToken::String(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
-            PhpToken::Array { elements } => visitor.visit_map(PhpMapAccess {
-                de: self,
-                remaining: elements,
+            PhpToken::String(s) => match canonical_int(s.as_bytes()) {
+                Some(i) if self.in_key && self.coerce_numeric_keys => visitor.visit_i64(i),
+                _ => {
+                    self.charge(s.as_bytes().len())?;
+                    visitor.visit_borrowed_bytes(s.as_bytes())
+                }
+            },
+            PhpToken::Array { elements } => self.nested(elements, |de| {
+                visitor.visit_map(PhpMapAccess::new(de, elements, DuplicateFields::Error))
             }),
-            PhpToken::Object { properties, .. } => visitor.visit_map(PhpMapAccess {
-                de: self,
-                remaining: properties,
+            PhpToken::Object { properties, .. } => self.nested(properties, |de| {
+                visitor.visit_map(PhpMapAccess::new(de, properties, DuplicateFields::Error))
             }),
             PhpToken::CustomObject { payload, .. } => {
+                self.charge(payload.as_bytes().len())?;
                 visitor.visit_borrowed_bytes(payload.as_bytes())
             }
-            PhpToken::Reference { id, .. } => visitor.visit_i64(id),
+            PhpToken::Enum { case, .. } => {
+                self.charge(case.as_bytes().len())?;
+                visitor.visit_borrowed_bytes(case.as_bytes())
+            }
+            PhpToken::Reference { id, .. } => Err(Error::from(ErrorKind::UnsupportedReference {
+                index: id.index(),
+                position: self.parser.position(),
+            })),
             _ => Err(Error::from(ErrorKind::Deserialize {
                 message: "Unexpected token".to_string(),
                 position: Some(self.parser.position()),
@@ -72,7 +1053,9 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        let token = self.parser.read_token()?;
+        substitute!(self.deserialize_any(visitor));
+
+        let token = self.read_token()?;
         self.deserialize_token(visitor, token)
     }
 
@@ -80,37 +1063,71 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        substitute!(self.deserialize_bool(visitor));
+
+        let token = self.read_scalar_token("boolean")?;
+        if !self.lenient_bools {
+            return self.deserialize_token(visitor, token);
+        }
+
+        match token {
+            PhpToken::Boolean(b) => visitor.visit_bool(b),
+            PhpToken::Integer(0) => visitor.visit_bool(false),
+            PhpToken::Integer(1) => visitor.visit_bool(true),
+            PhpToken::String(s) => match s.as_bytes() {
+                b"0" | b"false" => visitor.visit_bool(false),
+                b"1" | b"true" => visitor.visit_bool(true),
+                _ => self.deserialize_token(visitor, PhpToken::String(s)),
+            },
+            token => self.deserialize_token(visitor, token),
+        }
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        substitute!(self.deserialize_i8(visitor));
+
+        match self.read_i64()? {
+            Ok(i) => visitor.visit_i8(self.narrow_int(i)?),
+            Err(token) => self.deserialize_token(visitor, token),
+        }
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        substitute!(self.deserialize_i16(visitor));
+
+        match self.read_i64()? {
+            Ok(i) => visitor.visit_i16(self.narrow_int(i)?),
+            Err(token) => self.deserialize_token(visitor, token),
+        }
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        substitute!(self.deserialize_i32(visitor));
+
+        match self.read_i64()? {
+            Ok(i) => visitor.visit_i32(self.narrow_int(i)?),
+            Err(token) => self.deserialize_token(visitor, token),
+        }
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        match self.parser.try_read_i64() {
-            Some(i) => visitor.visit_i64(i),
-            None => self.deserialize_any(visitor),
+        substitute!(self.deserialize_i64(visitor));
+
+        match self.read_i64()? {
+            Ok(i) => visitor.visit_i64(i),
+            Err(token) => self.deserialize_token(visitor, token),
         }
     }
 
@@ -153,9 +1170,17 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parser.try_read_f64() {
-            Some(f) => visitor.visit_f64(f),
-            None => self.deserialize_any(visitor),
+        substitute!(self.deserialize_f64(visitor));
+
+        if let Some(f) = self.parser.try_read_f64() {
+            return visitor.visit_f64(f);
+        }
+
+        // PHP serializes whole number floats from integer arithmetic as
+        // integers, so accept those too.
+        match self.read_scalar_token("float")? {
+            PhpToken::Integer(i) => visitor.visit_f64(i as f64),
+            token => self.deserialize_token(visitor, token),
         }
     }
 
@@ -170,12 +1195,17 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_str(visitor));
+
         match self.parser.try_read_str() {
-            Some(s) => visitor.visit_borrowed_str(s.to_str()?),
-            None => match self.parser.read_token()? {
+            Some(s) => {
+                self.charge(s.as_bytes().len())?;
+                visitor.visit_borrowed_str(s.to_str()?)
+            }
+            None => match self.read_scalar_token("string")? {
                 PhpToken::String(s) => {
-                    let str_value = s.to_str()?;
-                    visitor.visit_borrowed_str(str_value)
+                    self.charge(s.as_bytes().len())?;
+                    visitor.visit_borrowed_str(s.to_str()?)
                 }
                 token => self.deserialize_token(visitor, token),
             },
@@ -193,9 +1223,17 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_bytes(visitor));
+
         match self.parser.try_read_str() {
-            Some(s) => visitor.visit_borrowed_bytes(s.as_bytes()),
-            None => self.deserialize_any(visitor),
+            Some(s) => {
+                self.charge(s.as_bytes().len())?;
+                visitor.visit_borrowed_bytes(s.as_bytes())
+            }
+            None => {
+                let token = self.read_scalar_token("string")?;
+                self.deserialize_token(visitor, token)
+            }
         }
     }
 
@@ -210,6 +1248,8 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_option(visitor));
+
         let peeked = self
             .parser
             .peek_token()?
@@ -219,6 +1259,11 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
             return visitor.visit_none();
         }
 
+        if self.empty_string_as_none && self.parser.starts_with(b"s:0:\"\";") {
+            let _ = self.parser.read_token()?;
+            return visitor.visit_none();
+        }
+
         visitor.visit_some(self)
     }
 
@@ -226,7 +1271,10 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        substitute!(self.deserialize_unit(visitor));
+
+        let token = self.read_scalar_token("null")?;
+        self.deserialize_token(visitor, token)
     }
 
     fn deserialize_unit_struct<V>(
@@ -242,12 +1290,19 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_newtype_struct(name, visitor));
+
+        #[cfg(feature = "spl")]
+        if let Some(class) = crate::spl::SplClass::from_name(name) {
+            return self.deserialize_spl(class, visitor);
+        }
+
         visitor.visit_newtype_struct(self)
     }
 
@@ -255,40 +1310,85 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parser.try_read_seq_start() {
-            Some(elements) => visitor.visit_seq(PhpSeqAccess {
-                de: self,
-                remaining: elements,
-                next_index: 0,
-            }),
-            None => match self.parser.read_token()? {
-                PhpToken::Array { elements } => visitor.visit_seq(PhpSeqAccess {
-                    de: self,
-                    remaining: elements,
-                    next_index: 0,
-                }),
-                _ => Err(Error::from(ErrorKind::Deserialize {
-                    message: "Expected array".to_string(),
-                    position: Some(self.parser.position()),
-                })),
+        substitute!(self.deserialize_seq(visitor));
+
+        let elements = match self.parser.try_read_seq_start() {
+            Some(elements) => elements,
+            None => match self.read_token()? {
+                PhpToken::Array { elements } => elements,
+                _ => {
+                    return Err(Error::from(ErrorKind::Deserialize {
+                        message: "Expected array".to_string(),
+                        position: Some(self.parser.position()),
+                    }));
+                }
             },
+        };
+
+        if self.ignore_sequence_keys {
+            return self.nested(elements, |de| {
+                visitor.visit_seq(PhpValueSeqAccess {
+                    de,
+                    remaining: elements,
+                    ended: false,
+                })
+            });
         }
+
+        self.nested(elements, |de| {
+            visitor.visit_seq(PhpSeqAccess {
+                de,
+                remaining: elements,
+                next_index: 0,
+                ended: false,
+            })
+        })
     }
 
     fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_tuple(len, visitor));
+
         match self.parser.next_token()? {
+            // Tuple visitors stop after `len` elements, so the end of the
+            // array is consumed here instead.
             Some(PhpToken::Array { elements }) if (elements as usize) == len => {
-                visitor.visit_seq(PhpSeqAccess {
-                    de: self,
-                    remaining: elements,
-                    next_index: 0,
+                self.nested(elements, |de| {
+                    let mut seq = PhpSeqAccess {
+                        de,
+                        remaining: elements,
+                        next_index: 0,
+                        ended: false,
+                    };
+                    let value = visitor.visit_seq(&mut seq)?;
+                    seq.finish()?;
+                    Ok(value)
                 })
             }
-            Some(PhpToken::Array { .. }) => Err(Error::from(ErrorKind::Deserialize {
-                message: "Array length mismatch".to_string(),
+            Some(PhpToken::Array { elements }) => Err(Error::from(ErrorKind::Deserialize {
+                message: format!("Array length mismatch: expected {len}, array has {elements}"),
+                position: Some(self.parser.position()),
+            })),
+
+            // Fallback for positional data serialized as an object: property
+            // values are read in order and the names are ignored.
+            Some(PhpToken::Object { properties, .. }) if (properties as usize) == len => self
+                .nested(properties, |de| {
+                    let mut seq = PhpValueSeqAccess {
+                        de,
+                        remaining: properties,
+                        ended: false,
+                    };
+                    let value = visitor.visit_seq(&mut seq)?;
+                    seq.finish()?;
+                    Ok(value)
+                }),
+            Some(PhpToken::Object { properties, .. }) => Err(Error::from(ErrorKind::Deserialize {
+                message: format!(
+                    "Object property count mismatch: expected {len}, object has {properties}"
+                ),
                 position: Some(self.parser.position()),
             })),
             _ => Err(Error::from(ErrorKind::Deserialize {
@@ -314,14 +1414,14 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        match self.parser.read_token()? {
-            PhpToken::Array { elements } => visitor.visit_map(PhpMapAccess {
-                de: self,
-                remaining: elements,
+        substitute!(self.deserialize_map(visitor));
+
+        match self.read_token()? {
+            PhpToken::Array { elements } => self.nested(elements, |de| {
+                visitor.visit_map(PhpMapAccess::new(de, elements, DuplicateFields::Error))
             }),
-            PhpToken::Object { properties, .. } => visitor.visit_map(PhpMapAccess {
-                de: self,
-                remaining: properties,
+            PhpToken::Object { properties, .. } => self.nested(properties, |de| {
+                visitor.visit_map(PhpMapAccess::new(de, properties, DuplicateFields::Error))
             }),
             _ => Err(Error::from(ErrorKind::Deserialize {
                 message: "Expected array or object".to_string(),
@@ -332,25 +1432,64 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
+        name: &'static str,
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        substitute!(self.deserialize_struct(name, fields, visitor));
+
+        if self.positional_structs {
+            let checkpoint = self.parser.checkpoint();
+            if let PhpToken::Array { elements } = self.parser.read_token()? {
+                if let Some(PhpTokenKind::Integer) = self.parser.peek_token()? {
+                    return self.nested(elements, |de| {
+                        let mut seq = PhpSeqAccess {
+                            de,
+                            remaining: elements,
+                            next_index: 0,
+                            ended: false,
+                        };
+                        let value = visitor.visit_seq(&mut seq)?;
+                        seq.finish()?;
+                        Ok(value)
+                    });
+                }
+            }
+            self.parser.restore(checkpoint);
+        }
+
+        let (position, _) = self.parser.upcoming();
+        let entries = match self.read_token()? {
+            PhpToken::Array { elements } => elements,
+            PhpToken::Object { properties, .. } => properties,
+            _ => {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: "Expected array or object".to_string(),
+                    position: Some(position),
+                }));
+            }
+        };
+
+        let duplicates = self.duplicate_fields;
+        self.nested(entries, |de| {
+            visitor.visit_map(PhpMapAccess::new(de, entries, duplicates))
+        })
     }
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
-        _variants: &'static [&'static str],
+        name: &'static str,
+        variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Self::Error>
     where
         V: de::Visitor<'de>,
     {
+        substitute!(self.deserialize_enum(name, variants, visitor));
+
         struct StringEnumAccess<'de> {
             variant: &'de str,
         }
@@ -468,11 +1607,11 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
                 }))
             }
 
-            fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+            fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error>
             where
                 V: de::Visitor<'de>,
             {
-                let value = de::Deserializer::deserialize_seq(&mut *self.de, visitor)?;
+                let value = de::Deserializer::deserialize_tuple(&mut *self.de, len, visitor)?;
                 if self.de.parser.try_read_end() {
                     return Ok(value);
                 }
@@ -503,13 +1642,14 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
             }
         }
 
-        match self.parser.read_token()? {
-            PhpToken::String(s) => visitor.visit_enum(StringEnumAccess {
-                variant: s.to_str()?,
-            }),
-            PhpToken::Array { elements: 1 } => visitor.visit_enum(MapEnumAccess { de: self }),
-            PhpToken::Object { properties: 1, .. } => {
-                visitor.visit_enum(MapEnumAccess { de: self })
+        match self.read_token()? {
+            PhpToken::String(s) | PhpToken::Enum { case: s, .. } => {
+                visitor.visit_enum(StringEnumAccess {
+                    variant: s.to_str()?,
+                })
+            }
+            PhpToken::Array { elements: 1 } | PhpToken::Object { properties: 1, .. } => {
+                self.nested(1, |de| visitor.visit_enum(MapEnumAccess { de }))
             }
             PhpToken::Array { .. } | PhpToken::Object { .. } => {
                 Err(Error::from(ErrorKind::Deserialize {
@@ -533,10 +1673,16 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
                 let prop = s.to_property();
                 visitor.visit_borrowed_bytes(prop.as_bytes())
             }
-            None => {
-                let token = self.parser.read_token()?;
-                self.deserialize_token(visitor, token)
-            }
+            None => match self.read_token()? {
+                // PHP treats `1` and `"1"` as the same key, so integers match
+                // identifiers like `#[serde(rename = "1")]`, as when an
+                // integer field is the tag of an internally tagged enum.
+                PhpToken::Integer(n) => visitor.visit_str(format_int(n, &mut [0; 20])),
+                // Strings too long or too close to the end of the input for
+                // the fast path above.
+                PhpToken::String(s) => visitor.visit_borrowed_bytes(s.to_property().as_bytes()),
+                token => self.deserialize_token(visitor, token),
+            },
         }
     }
 
@@ -544,7 +1690,38 @@ impl<'de> Deserializer<'de> for &'_ mut PhpDeserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_any(visitor)
+        // An ignored reference can't corrupt anything, so there is no need to
+        // reject it.
+        match self.read_token()? {
+            PhpToken::Reference { .. } => visitor.visit_unit(),
+            token => self.deserialize_token(visitor, token),
+        }
+    }
+}
+
+/// The values of an SPL object's custom serialization and the separators
+/// between them.
+#[cfg(feature = "spl")]
+struct SplPayloadAccess<'a, 'de: 'a> {
+    de: &'a mut PhpDeserializer<'de>,
+    class: crate::spl::SplClass,
+    index: usize,
+}
+
+#[cfg(feature = "spl")]
+impl<'de> SeqAccess<'de> for SplPayloadAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        let value = seed.deserialize(&mut *self.de)?;
+        if let Some(separator) = self.class.separator(self.index) {
+            self.de.parser.expect(separator)?;
+        }
+        self.index += 1;
+        Ok(Some(value))
     }
 }
 
@@ -552,6 +1729,14 @@ struct PhpSeqAccess<'a, 'de: 'a> {
     de: &'a mut PhpDeserializer<'de>,
     remaining: u32,
     next_index: i64,
+    ended: bool,
+}
+
+impl PhpSeqAccess<'_, '_> {
+    /// Consume the end of the array once the visitor is done with it.
+    fn finish(self) -> Result<(), Error> {
+        self.de.finish_seq(self.ended, self.remaining)
+    }
 }
 
 impl<'de> SeqAccess<'de> for PhpSeqAccess<'_, 'de> {
@@ -563,15 +1748,13 @@ impl<'de> SeqAccess<'de> for PhpSeqAccess<'_, 'de> {
     {
         if self.remaining == 0 {
             if self.de.parser.try_read_end() {
+                self.ended = true;
                 return Ok(None);
             }
             if self.de.parser.peek_token()?.is_none() {
                 return Err(Error::from(ErrorKind::Eof));
             }
-            return Err(Error::from(ErrorKind::Deserialize {
-                message: "Expected end of sequence".to_string(),
-                position: Some(self.de.parser.position()),
-            }));
+            return Err(self.de.expected_seq_end());
         }
 
         let expected_index = self.next_index;
@@ -610,49 +1793,273 @@ impl<'de> SeqAccess<'de> for PhpSeqAccess<'_, 'de> {
         self.next_index += 1;
         seed.deserialize(&mut *self.de).map(Some)
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining as usize)
+    }
 }
 
-struct PhpMapAccess<'a, 'de: 'a> {
+/// Reads the values of an array or object as a sequence, ignoring the keys.
+struct PhpValueSeqAccess<'a, 'de: 'a> {
     de: &'a mut PhpDeserializer<'de>,
     remaining: u32,
+    ended: bool,
 }
 
-impl<'de> MapAccess<'de> for PhpMapAccess<'_, 'de> {
+impl PhpValueSeqAccess<'_, '_> {
+    /// Consume the end of the array or object once the visitor is done with
+    /// it.
+    fn finish(self) -> Result<(), Error> {
+        self.de.finish_seq(self.ended, self.remaining)
+    }
+}
+
+impl<'de> SeqAccess<'de> for PhpValueSeqAccess<'_, 'de> {
     type Error = Error;
 
-    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
     where
-        K: DeserializeSeed<'de>,
+        T: DeserializeSeed<'de>,
     {
         if self.remaining == 0 {
             if self.de.parser.try_read_end() {
+                self.ended = true;
                 return Ok(None);
             }
-            return Err(Error::from(ErrorKind::Deserialize {
-                message: "Expected end of map".to_string(),
-                position: Some(self.de.parser.position()),
-            }));
+            return Err(self.de.expected_seq_end());
+        }
+
+        let (position, _) = self.de.parser.upcoming();
+        match self.de.parser.read_token()? {
+            PhpToken::String(_) | PhpToken::Integer(_) => {}
+            token => {
+                let kind = token.kind();
+                return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+            }
         }
+
         self.remaining -= 1;
         seed.deserialize(&mut *self.de).map(Some)
     }
 
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining as usize)
+    }
+}
+
+struct PhpMapAccess<'a, 'de: 'a> {
+    de: &'a mut PhpDeserializer<'de>,
+    declared: u32,
+    remaining: u32,
+
+    /// How keys that repeat are handled. Only structs track their keys, maps
+    /// pass [`DuplicateFields::Error`] to receive every entry.
+    duplicates: DuplicateFields,
+
+    /// The keys handed out so far under [`DuplicateFields::First`].
+    seen: HashSet<FieldKey<'de>>,
+
+    /// Under [`DuplicateFields::Last`], the keys left to hand out, the next
+    /// one last. Found on the first call by reading ahead over the entries.
+    kept: Option<Vec<PhpCheckpoint>>,
+}
+
+/// A key in the form struct fields are matched by: property names are
+/// demangled and integers equal the names that spell them, as
+/// `deserialize_identifier` sees them.
+#[derive(PartialEq, Eq, Hash)]
+enum FieldKey<'de> {
+    Int(i64),
+    Name(&'de [u8]),
+}
+
+impl<'de> FieldKey<'de> {
+    /// Read a key that has been checked to be an integer or a string.
+    fn read(parser: &mut PhpParser<'de>) -> Result<Self, Error> {
+        match parser.read_token()? {
+            PhpToken::Integer(i) => Ok(FieldKey::Int(i)),
+            PhpToken::String(s) => {
+                let name = s.to_property().as_bytes();
+                Ok(canonical_int(name).map_or(FieldKey::Name(name), FieldKey::Int))
+            }
+            _ => unreachable!("peeked a key"),
+        }
+    }
+}
+
+impl<'a, 'de> PhpMapAccess<'a, 'de> {
+    fn new(de: &'a mut PhpDeserializer<'de>, entries: u32, duplicates: DuplicateFields) -> Self {
+        PhpMapAccess {
+            de,
+            declared: entries,
+            remaining: entries,
+            duplicates,
+            seen: HashSet::new(),
+            kept: None,
+        }
+    }
+
+    /// Check that the next entry has a key PHP can produce, failing on the
+    /// end of the container or a key like null that the key type might
+    /// otherwise accept.
+    fn check_key(&mut self) -> Result<usize, Error> {
+        let (position, next) = self.de.parser.upcoming();
+        if next == b'}' {
+            return Err(Error::from(ErrorKind::CountMismatch {
+                declared: self.declared,
+                found: self.declared - self.remaining,
+                position,
+            }));
+        }
+
+        let kind = self.de.parser.peek_token()?.ok_or(ErrorKind::Eof)?;
+        if !matches!(kind, PhpTokenKind::Integer | PhpTokenKind::String) {
+            return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+        }
+        Ok(position)
+    }
+
+    /// Read ahead over the remaining entries and return the keys to hand out
+    /// under [`DuplicateFields::Last`]: the last entry of each key.
+    fn last_entries(&mut self) -> Result<Vec<PhpCheckpoint>, Error> {
+        let mut entries = Vec::with_capacity(self.remaining.min(1024) as usize);
+        let mut last = HashMap::new();
+        while self.remaining > 0 {
+            self.check_key()?;
+            let checkpoint = self.de.parser.checkpoint();
+            let key = FieldKey::read(&mut self.de.parser)?;
+            self.de.skip_value_spanned()?;
+            last.insert(key, entries.len());
+            entries.push(checkpoint);
+            self.remaining -= 1;
+        }
+
+        let mut kept: Vec<_> = last.into_values().collect();
+        kept.sort_unstable_by(|a, b| b.cmp(a));
+        self.remaining = kept.len() as u32;
+        Ok(kept.into_iter().map(|index| entries[index]).collect())
+    }
+}
+
+impl<'de> MapAccess<'de> for PhpMapAccess<'_, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.duplicates == DuplicateFields::Last {
+            if self.kept.is_none() {
+                self.kept = Some(self.last_entries()?);
+            }
+            if let Some(checkpoint) = self.kept.as_mut().and_then(Vec::pop) {
+                self.de.parser.restore(checkpoint);
+            }
+        }
+
+        loop {
+            if self.remaining == 0 {
+                if self.de.parser.try_read_end() {
+                    return Ok(None);
+                }
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: "Expected end of map".to_string(),
+                    position: Some(self.de.parser.position()),
+                }));
+            }
+
+            let position = self.check_key()?;
+            if self.duplicates == DuplicateFields::First {
+                let checkpoint = self.de.parser.checkpoint();
+                if !self.seen.insert(FieldKey::read(&mut self.de.parser)?) {
+                    self.de.parser.skip_value()?;
+                    self.remaining -= 1;
+                    continue;
+                }
+                self.de.parser.restore(checkpoint);
+            }
+
+            self.remaining -= 1;
+            self.de.in_key = true;
+            let key = seed.deserialize(&mut *self.de);
+            self.de.in_key = false;
+            return key.map(Some).map_err(|e| e.at_position(position));
+        }
+    }
+
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
     where
         V: DeserializeSeed<'de>,
     {
         seed.deserialize(&mut *self.de)
     }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining as usize)
+    }
+}
+
+/// Deserialize a value from a reader of PHP serialized data.
+///
+/// This does not stream: the whole input is buffered in memory before
+/// deserializing, as PHP serialized data can't be delimited without parsing
+/// it and the buffer is dropped before returning, so only owned types can be
+/// produced. Only a single value may be present: anything after it fails with
+/// [`ErrorKind::TrailingData`].
+///
+/// ```rust
+/// let reader = std::io::Cursor::new(b"a:2:{i:0;i:1;i:1;i:2;}");
+/// let values: Vec<i64> = phpserz::from_reader(reader).unwrap();
+/// assert_eq!(values, vec![1, 2]);
+/// ```
+pub fn from_reader<R: Read, T: DeserializeOwned>(mut reader: R) -> Result<T, Error> {
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+
+    let mut deserializer = PhpDeserializer::new(&data);
+    let value = T::deserialize(&mut deserializer)?;
+    let (position, _) = deserializer.parser.upcoming();
+    if position < data.len() {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+
+    Ok(value)
+}
+
+/// Format an integer in decimal into `buf`, which fits `i64::MIN`, without
+/// allocating.
+fn format_int(n: i64, buf: &mut [u8; 20]) -> &str {
+    let mut start = buf.len();
+    let mut rest = n.unsigned_abs();
+    loop {
+        start -= 1;
+        buf[start] = b'0' + (rest % 10) as u8;
+        rest /= 10;
+        if rest == 0 {
+            break;
+        }
+    }
+
+    if n < 0 {
+        start -= 1;
+        buf[start] = b'-';
+    }
+
+    std::str::from_utf8(&buf[start..]).unwrap_or_default()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::PhpBstr;
+    use arrayvec::ArrayVec;
+    use rstest::rstest;
     use serde::{Deserialize, Serialize};
+    use smallvec::SmallVec;
     use std::{
         collections::{BTreeMap, HashMap},
         fmt,
+        io::Write,
         marker::PhantomData,
     };
 
@@ -762,51 +2169,21 @@ mod tests {
     }
 
     #[test]
-    fn test_deserialize_float() {
-        let input = b"d:3.33;";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: f64 = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, 3.33);
-    }
-
-    #[test]
-    fn test_deserialize_float32() {
-        let input = b"d:3.33;";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: f32 = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, 3.33_f32);
-    }
-
-    #[test]
-    fn test_deserialize_string() {
-        let input = b"s:5:\"hello\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: String = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, "hello");
-    }
-
-    #[test]
-    fn test_deserialize_char() {
-        let input = b"s:1:\"x\";";
+    fn test_deserialize_integer_from_float_is_opt_in() {
+        let input = b"d:42;";
         let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: char = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, 'x');
-    }
+        let result: Result<i64, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
 
-    #[test]
-    fn test_deserialize_char_fast_path() {
-        // Trailing serialized data keeps at least 16 bytes available so try_read_str() takes
-        // its short-string fast path for the first token.
-        let input = b"s:4:\"\xF0\x9F\x98\x80\";s:1:\"z\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: char = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, '😀');
+        let mut deserializer = PhpDeserializer::new(&input[..]).float_to_int(true);
+        let result: i64 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 42);
     }
 
     #[test]
-    fn test_deserialize_object() {
-        let input = b"O:6:\"Person\":2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;}";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
+    fn test_deserialize_integer_from_float_in_struct() {
+        let input = b"a:2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";d:30.0;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]).float_to_int(true);
         let result: Person = Deserialize::deserialize(&mut deserializer).unwrap();
         assert_eq!(
             result,
@@ -817,33 +2194,541 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(b"d:42.5;")]
+    #[case(b"d:-0.1;")]
+    #[case(b"d:1.0E+25;")]
+    #[case(b"d:NAN;")]
+    fn test_deserialize_integer_from_inexact_float(#[case] input: &[u8]) {
+        let mut deserializer = PhpDeserializer::new(input).float_to_int(true);
+        let result: Result<i64, _> = Deserialize::deserialize(&mut deserializer);
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. } if message.ends_with("is not an exact integer")
+        ));
+    }
+
+    fn nested_arrays(depth: usize) -> Vec<u8> {
+        let mut input = b"a:1:{i:0;".repeat(depth);
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        input
+    }
+
     #[test]
-    fn test_deserialize_option_some() {
-        let input = b"s:4:\"data\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: Option<String> = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, Some("data".to_string()));
+    fn test_deserialize_depth_limit() {
+        let input = nested_arrays(10_000);
+        let mut deserializer = PhpDeserializer::new(&input);
+        let error = de::IgnoredAny::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::DepthLimitExceeded { position } if *position == 128 * 9 + 5
+        ));
     }
 
     #[test]
-    fn test_deserialize_object_wrapper() {
-        let input = b"O:13:\"ObjectWrapper\":1:{s:5:\"inner\";O:6:\"Person\":2:{s:4:\"name\";s:3:\"Bob\";s:3:\"age\";i:25;}}";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: ObjectWrapper = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(
-            result,
-            ObjectWrapper {
-                inner: Person {
-                    name: "Bob".to_string(),
-                    age: 25
-                }
-            }
-        );
+    fn test_deserialize_total_bytes_limit() {
+        let mut input = b"a:1000:{".to_vec();
+        for i in 0..1000 {
+            write!(input, "i:{i};s:100:\"{}\";", "x".repeat(100)).unwrap();
+        }
+        input.push(b'}');
+
+        let mut deserializer = PhpDeserializer::new(&input).max_total_bytes(101_000);
+        let values = Vec::<String>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(values.len(), 1000);
+
+        let mut deserializer = PhpDeserializer::new(&input).max_total_bytes(50_000);
+        let error = Vec::<String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::ResourceLimit { limit: 50_000, position } if *position < input.len()
+        ));
     }
 
     #[test]
-    fn test_deserialize_assoc_array_to_tuple_vec() {
-        // PHP: array("foo" => "bar", "baz" => "qux")
+    fn test_deserialize_total_bytes_limit_references() {
+        // Every reference is a copy of the 1000 byte string, so the payload
+        // expands far beyond its own size.
+        let mut input = format!("a:100:{{i:0;s:1000:\"{}\";", "x".repeat(1000)).into_bytes();
+        for i in 1..100 {
+            write!(input, "i:{i};r:2;").unwrap();
+        }
+        input.push(b'}');
+
+        let mut deserializer = PhpDeserializer::new(&input)
+            .resolve_references(true)
+            .max_total_bytes(10 * input.len());
+        let error = Vec::<String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::ResourceLimit { .. }));
+
+        let mut deserializer = PhpDeserializer::new(&input)
+            .resolve_references(true)
+            .max_total_bytes(100_100);
+        let values = Vec::<String>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(values.len(), 100);
+    }
+
+    #[test]
+    fn test_deserialize_within_depth_limit() {
+        let input = nested_arrays(128);
+        let mut deserializer = PhpDeserializer::new(&input);
+        de::IgnoredAny::deserialize(&mut deserializer).unwrap();
+        assert_eq!(deserializer.into_parser().position(), input.len());
+    }
+
+    #[test]
+    fn test_deserialize_custom_depth_limit() {
+        let input = nested_arrays(3);
+        let mut deserializer = PhpDeserializer::new(&input).max_depth(3);
+        let value: Vec<Vec<Vec<Option<i64>>>> =
+            Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(value, vec![vec![vec![None]]]);
+
+        let mut deserializer = PhpDeserializer::new(&input).max_depth(2);
+        let result: Result<Vec<Vec<Vec<Option<i64>>>>, _> =
+            Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::DepthLimitExceeded { .. }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_depth_limit_nested_enums() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        enum Tree {
+            Leaf,
+            Node(Box<Tree>),
+        }
+
+        let depth = 1_000;
+        let mut input = b"a:1:{s:4:\"Node\";".repeat(depth);
+        input.extend_from_slice(b"s:4:\"Leaf\";");
+        input.extend(std::iter::repeat_n(b'}', depth));
+
+        let mut deserializer = PhpDeserializer::new(&input);
+        let result: Result<Tree, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::DepthLimitExceeded { .. }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_reference_is_unsupported() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Pair {
+            a: String,
+            b: String,
+        }
+
+        let input = b"a:2:{s:1:\"a\";s:3:\"foo\";s:1:\"b\";R:2;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Result<Pair, _> = Deserialize::deserialize(&mut deserializer);
+        let error = result.unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 31
+            }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_resolve_object_property_reference() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Pair {
+            a: String,
+            b: String,
+        }
+
+        let input = b"O:4:\"Pair\":2:{s:1:\"a\";s:3:\"foo\";s:1:\"b\";r:2;}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let result: Pair = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Pair {
+                a: "foo".to_string(),
+                b: "foo".to_string()
+            }
+        );
+        assert!(deserializer.into_parser().next_token().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_deserialize_resolve_container_and_chained_references() {
+        let input = b"a:3:{i:0;a:1:{i:0;i:5;}i:1;r:2;i:2;r:4;}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let result: Vec<Vec<i64>> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, vec![vec![5], vec![5], vec![5]]);
+
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let value: crate::PhpValue = Deserialize::deserialize(&mut deserializer).unwrap();
+        let crate::PhpValue::Array(entries) = value else {
+            panic!("expected an array");
+        };
+        assert_eq!(entries[0].1, entries[2].1);
+
+        let input = b"a:2:{i:0;N;i:1;r:2;}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let result: Vec<Option<i64>> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, vec![None, None]);
+    }
+
+    #[test]
+    fn test_deserialize_self_referential_object() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Node {
+            name: String,
+            #[serde(rename = "self")]
+            this: Option<Box<Node>>,
+        }
+
+        let input = b"O:4:\"Node\":2:{s:4:\"name\";s:1:\"n\";s:4:\"self\";r:1;}";
+        for resolve in [false, true] {
+            let mut deserializer = PhpDeserializer::new(input).resolve_references(resolve);
+            let result: Result<Node, _> = Deserialize::deserialize(&mut deserializer);
+            assert!(matches!(
+                result.unwrap_err().kind(),
+                ErrorKind::UnsupportedReference {
+                    index: 1,
+                    position: 44
+                }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_deserialize_reference_expansion_limit() {
+        // Each level is an array of two references to the level before it,
+        // so resolving the last level reads the first one 2^(levels - 1)
+        // times.
+        fn expanding(levels: usize) -> String {
+            let mut input = format!("a:{levels}:{{i:0;a:1:{{i:0;s:1:\"x\";}}");
+            let mut previous = 2;
+            for level in 1..levels {
+                input.push_str(&format!(
+                    "i:{level};a:2:{{i:0;r:{previous};i:1;r:{previous};}}"
+                ));
+                previous = 3 * level + 1;
+            }
+            input.push('}');
+            input
+        }
+
+        let input = expanding(8);
+        let mut deserializer = PhpDeserializer::new(input.as_bytes()).resolve_references(true);
+        let value: crate::PhpValue = Deserialize::deserialize(&mut deserializer).unwrap();
+        let crate::PhpValue::Array(levels) = value else {
+            panic!("expected an array");
+        };
+        assert_eq!(levels.len(), 8);
+
+        let input = expanding(24);
+        let mut deserializer = PhpDeserializer::new(input.as_bytes()).resolve_references(true);
+        let result: Result<crate::PhpValue, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::ResourceLimit {
+                limit: DEFAULT_MAX_REFERENCE_BYTES,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_references_before_origin() {
+        let input = b"a:2:{i:0;s:3:\"foo\";i:1;r:2;}";
+        let mut parser = PhpParser::new(input);
+        parser.read_token().unwrap();
+        parser.read_token().unwrap();
+        let mut deserializer = PhpDeserializer::from_parser(parser).resolve_references(true);
+        assert_eq!(String::deserialize(&mut deserializer).unwrap(), "foo");
+
+        let mut parser = deserializer.into_parser();
+        parser.read_token().unwrap();
+        let mut deserializer = PhpDeserializer::from_parser(parser).resolve_references(true);
+        assert!(matches!(
+            String::deserialize(&mut deserializer).unwrap_err().kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 23
+            }
+        ));
+
+        let input = b"a:1:{i:0;a:2:{i:0;s:3:\"foo\";i:1;r:3;}}";
+        let mut parser = PhpParser::new(input);
+        parser.read_token().unwrap();
+        parser.read_token().unwrap();
+        let mut deserializer = PhpDeserializer::from_parser(parser).resolve_references(true);
+        assert_eq!(
+            Vec::<String>::deserialize(&mut deserializer).unwrap(),
+            vec!["foo", "foo"]
+        );
+    }
+
+    #[test]
+    fn test_deserialize_resolve_unknown_reference() {
+        let input = b"a:2:{i:0;i:1;i:1;r:9;}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let result: Result<Vec<i64>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::ForwardReference {
+                index: 9,
+                position: 19
+            }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_ignores_unknown_reference_fields() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Partial {
+            a: String,
+        }
+
+        let input = b"a:2:{s:1:\"a\";s:3:\"foo\";s:1:\"b\";R:2;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Partial = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Partial {
+                a: "foo".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_borrowed_bytes_field() {
+        #[derive(Debug, Deserialize)]
+        struct Blob<'a> {
+            data: &'a [u8],
+            #[serde(borrow)]
+            extra: Option<&'a [u8]>,
+        }
+
+        let input = b"a:2:{s:4:\"data\";s:3:\"\xff\x00\xfe\";s:5:\"extra\";s:2:\"\xc3\x28\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let blob: Blob = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(blob.data, b"\xff\x00\xfe");
+        assert_eq!(blob.extra, Some(&b"\xc3\x28"[..]));
+
+        let range = input.as_ptr_range();
+        assert!(range.contains(&blob.data.as_ptr()));
+        assert!(range.contains(&blob.extra.unwrap().as_ptr()));
+
+        // Strings too long for the fast path borrow as well
+        let long = vec![0xffu8; 150];
+        let mut input = b"a:2:{s:4:\"data\";s:150:\"".to_vec();
+        input.extend_from_slice(&long);
+        input.extend_from_slice(b"\";s:5:\"extra\";N;}");
+        let mut deserializer = PhpDeserializer::new(&input);
+        let blob: Blob = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(blob.data, &long[..]);
+        assert_eq!(blob.extra, None);
+        assert!(input.as_ptr_range().contains(&blob.data.as_ptr()));
+    }
+
+    #[test]
+    fn test_next_borrowed_str() {
+        let input = b"a:2:{i:0;s:5:\"hello\";i:1;s:3:\"\xe2\x82\xac\";}";
+        let mut parser = PhpParser::new(input);
+        assert_eq!(
+            parser.read_token().unwrap(),
+            PhpToken::Array { elements: 2 }
+        );
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(0));
+
+        let mut deserializer = PhpDeserializer::from_parser(parser);
+        assert_eq!(deserializer.next_borrowed_str().unwrap(), "hello");
+        let index: i64 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(deserializer.next_borrowed_str().unwrap(), "\u{20ac}");
+    }
+
+    #[test]
+    fn test_next_borrowed_bytes_non_utf8() {
+        let input = b"s:2:\"\xff\xfe\";";
+        let mut deserializer = PhpDeserializer::new(input);
+        assert_eq!(deserializer.next_borrowed_bytes().unwrap(), b"\xff\xfe");
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = deserializer.next_borrowed_str().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Utf8(_)));
+    }
+
+    #[test]
+    fn test_next_borrowed_bytes_requires_string() {
+        let mut deserializer = PhpDeserializer::new(b"i:5;");
+        let error = deserializer.next_borrowed_bytes().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize {
+                position: Some(0),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_narrow_integers() {
+        let mut deserializer = PhpDeserializer::new(b"i:100;");
+        let result: i8 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 100);
+
+        let mut deserializer = PhpDeserializer::new(b"i:-32768;");
+        let result: i16 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, i16::MIN);
+
+        let mut deserializer = PhpDeserializer::new(b"i:2147483647;");
+        let result: i32 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, i32::MAX);
+    }
+
+    #[rstest]
+    #[case::i8(b"i:300;", "Integer 300 is out of range for i8 at position: 6")]
+    #[case::i8_negative(b"i:-129;", "Integer -129 is out of range for i8 at position: 7")]
+    fn test_deserialize_i8_out_of_range(#[case] input: &[u8], #[case] expected: &str) {
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Result<i8, _> = Deserialize::deserialize(&mut deserializer);
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Deserialization error: {expected}")
+        );
+    }
+
+    #[test]
+    fn test_deserialize_narrow_integers_out_of_range() {
+        let mut deserializer = PhpDeserializer::new(b"i:40000;");
+        let result: Result<i16, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
+
+        let mut deserializer = PhpDeserializer::new(b"i:3000000000;");
+        let result: Result<i32, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
+
+        #[derive(Debug, Deserialize)]
+        struct Small {
+            #[allow(dead_code)]
+            a: i8,
+        }
+
+        let mut deserializer = PhpDeserializer::new(b"a:1:{s:1:\"a\";i:1000;}");
+        let result: Result<Small, _> = Deserialize::deserialize(&mut deserializer);
+        let error = result.unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("Integer 1000 is out of range for i8"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_float() {
+        let input = b"d:3.33;";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: f64 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 3.33);
+    }
+
+    #[test]
+    fn test_deserialize_float32() {
+        let input = b"d:3.33;";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: f32 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 3.33_f32);
+    }
+
+    #[test]
+    fn test_deserialize_string() {
+        let input = b"s:5:\"hello\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: String = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, "hello");
+    }
+
+    #[test]
+    fn test_deserialize_char() {
+        let input = b"s:1:\"x\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: char = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 'x');
+    }
+
+    #[test]
+    fn test_deserialize_char_fast_path() {
+        // Trailing serialized data keeps at least 16 bytes available so try_read_str() takes
+        // its short-string fast path for the first token.
+        let input = b"s:4:\"\xF0\x9F\x98\x80\";s:1:\"z\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: char = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, '😀');
+    }
+
+    #[test]
+    fn test_deserialize_object() {
+        let input = b"O:6:\"Person\":2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Person = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Person {
+                name: "Alice".to_string(),
+                age: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_object_non_utf8_class() {
+        let input = b"O:3:\"P\xffn\":2:{s:4:\"name\";s:5:\"Alice\";s:8:\"\0P\xffn\0age\";i:30;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Person = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Person {
+                name: "Alice".to_string(),
+                age: 30
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_option_some() {
+        let input = b"s:4:\"data\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Option<String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Some("data".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_object_wrapper() {
+        let input = b"O:13:\"ObjectWrapper\":1:{s:5:\"inner\";O:6:\"Person\":2:{s:4:\"name\";s:3:\"Bob\";s:3:\"age\";i:25;}}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: ObjectWrapper = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            ObjectWrapper {
+                inner: Person {
+                    name: "Bob".to_string(),
+                    age: 25
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_assoc_array_to_tuple_vec() {
+        // PHP: array("foo" => "bar", "baz" => "qux")
         let input = b"a:2:{s:3:\"foo\";s:3:\"bar\";s:3:\"baz\";s:3:\"qux\";}";
         let mut deserializer = PhpDeserializer::new(&input[..]);
         let result: Vec<(String, String)> = deserialize_vec_pair(&mut deserializer).unwrap();
@@ -971,36 +2856,355 @@ mod tests {
     }
 
     #[test]
-    fn test_deserialize_seq_explicitly() {
-        #[derive(Debug, PartialEq)]
-        struct ExplicitSeq(Vec<i64>);
+    fn test_deserialize_fixed_size_array() {
+        let input = b"a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: [i64; 3] = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, [1, 2, 3]);
+    }
 
-        impl<'de> Deserialize<'de> for ExplicitSeq {
-            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-            where
-                D: serde::Deserializer<'de>,
-            {
-                struct ExplicitSeqVisitor;
+    #[test]
+    fn test_deserialize_mixed_tuple() {
+        let input = b"a:2:{i:0;s:5:\"hello\";i:1;i:42;}";
+        let result: (String, i64) =
+            Deserialize::deserialize(&mut PhpDeserializer::new(&input[..])).unwrap();
+        assert_eq!(result, ("hello".to_string(), 42));
 
-                impl<'de> de::Visitor<'de> for ExplicitSeqVisitor {
-                    type Value = ExplicitSeq;
+        let input = b"a:5:{i:0;s:2:\"id\";i:1;d:1.5;i:2;b:1;i:3;N;i:4;a:1:{i:0;i:7;}}";
+        let result: (&str, f64, bool, Option<i64>, Vec<u8>) =
+            Deserialize::deserialize(&mut PhpDeserializer::new(&input[..])).unwrap();
+        assert_eq!(result, ("id", 1.5, true, None, vec![7]));
 
-                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("a sequence of integers")
-                    }
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Record {
+            fields: Vec<(String, i64)>,
+        }
 
-                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
-                    where
-                        A: de::SeqAccess<'de>,
-                    {
-                        let mut values = Vec::new();
-                        while let Some(value) = seq.next_element()? {
-                            values.push(value);
-                        }
+        let input = b"a:1:{s:6:\"fields\";a:2:{i:0;a:2:{i:0;s:1:\"a\";i:1;i:1;}i:1;a:2:{i:0;s:1:\"b\";i:1;i:2;}}}";
+        let result: Record =
+            Deserialize::deserialize(&mut PhpDeserializer::new(&input[..])).unwrap();
+        assert_eq!(
+            result.fields,
+            vec![("a".to_string(), 1), ("b".to_string(), 2)]
+        );
 
-                        Ok(ExplicitSeq(values))
-                    }
-                }
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Shape {
+            Tuple(i64, String),
+            Unit,
+        }
+
+        let input = b"a:2:{i:0;a:1:{s:5:\"Tuple\";a:2:{i:0;i:1;i:1;s:1:\"x\";}}i:1;s:4:\"Unit\";}";
+        let result: Vec<Shape> =
+            Deserialize::deserialize(&mut PhpDeserializer::new(&input[..])).unwrap();
+        assert_eq!(result, vec![Shape::Tuple(1, "x".to_string()), Shape::Unit]);
+    }
+
+    #[rstest]
+    #[case(b"a:1:{N;s:1:\"x\";}", PhpTokenKind::Null)]
+    #[case(b"a:1:{b:1;s:1:\"x\";}", PhpTokenKind::Boolean)]
+    #[case(b"a:1:{d:1.5;s:1:\"x\";}", PhpTokenKind::Float)]
+    #[case(b"a:1:{a:0:{}s:1:\"x\";}", PhpTokenKind::Array)]
+    fn test_deserialize_invalid_map_key(#[case] input: &[u8], #[case] expected: PhpTokenKind) {
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = HashMap::<Option<String>, String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::InvalidKey { kind, position: 5 } if *kind == expected),
+            "{error:?}"
+        );
+        assert!(error.is_parse_error());
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = de::IgnoredAny::deserialize(&mut deserializer);
+        assert!(error.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_invalid_map_key_message() {
+        let input = b"a:1:{N;s:1:\"x\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = HashMap::<String, String>::deserialize(&mut deserializer).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Expected an integer or string key, found null at position: 5"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_container_into_scalar() {
+        fn message<'a, T: Deserialize<'a> + std::fmt::Debug>(input: &'a [u8]) -> String {
+            let mut deserializer = PhpDeserializer::new(input);
+            T::deserialize(&mut deserializer).unwrap_err().to_string()
+        }
+
+        let array = b"a:2:{i:0;i:1;i:1;i:2;}";
+        let object = b"O:3:\"Foo\":0:{}";
+        assert_eq!(
+            message::<i64>(array),
+            "Deserialization error: Expected integer, found array at position: 0"
+        );
+        assert_eq!(
+            message::<u8>(object),
+            "Deserialization error: Expected integer, found object at position: 0"
+        );
+        assert_eq!(
+            message::<f64>(array),
+            "Deserialization error: Expected float, found array at position: 0"
+        );
+        assert_eq!(
+            message::<String>(object),
+            "Deserialization error: Expected string, found object at position: 0"
+        );
+        assert_eq!(
+            message::<bool>(array),
+            "Deserialization error: Expected boolean, found array at position: 0"
+        );
+        assert_eq!(
+            message::<()>(array),
+            "Deserialization error: Expected null, found array at position: 0"
+        );
+
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Item {
+            id: i64,
+            name: Option<String>,
+        }
+
+        let input = b"a:2:{s:2:\"id\";i:1;s:4:\"name\";a:1:{i:0;s:1:\"x\";}}";
+        assert_eq!(
+            message::<Item>(input),
+            "Deserialization error: Expected string, found array at position: 29"
+        );
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(7)]
+    #[case(-1)]
+    #[case(1234567890)]
+    #[case(i64::MAX)]
+    #[case(i64::MIN)]
+    fn test_format_int(#[case] n: i64) {
+        assert_eq!(format_int(n, &mut [0; 20]), n.to_string());
+    }
+
+    #[test]
+    fn test_deserialize_integer_tagged_enum() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "kind")]
+        enum Shape {
+            #[serde(rename = "1")]
+            Circle { radius: f64 },
+            #[serde(rename = "2")]
+            Rectangle { width: f64, height: f64 },
+        }
+
+        let input = b"O:5:\"Shape\":2:{s:4:\"kind\";i:1;s:6:\"radius\";d:2;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let shape = Shape::deserialize(&mut deserializer).unwrap();
+        assert_eq!(shape, Shape::Circle { radius: 2.0 });
+
+        // The tag doesn't have to come first, and arrays work the same.
+        let input = b"a:3:{s:5:\"width\";d:1.5;s:4:\"kind\";i:2;s:6:\"height\";i:3;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let shape = Shape::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            shape,
+            Shape::Rectangle {
+                width: 1.5,
+                height: 3.0
+            }
+        );
+
+        let input = b"O:5:\"Shape\":2:{s:4:\"kind\";i:3;s:6:\"radius\";d:2;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let error = Shape::deserialize(&mut deserializer).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::Deserialize { message, .. } if message.starts_with("unknown variant `3`")),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_default_fields() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Settings {
+            name: String,
+            #[serde(default)]
+            retries: u32,
+            #[serde(default = "default_theme")]
+            theme: String,
+        }
+
+        fn default_theme() -> String {
+            "dark".to_string()
+        }
+
+        let expected = Settings {
+            name: "app".to_string(),
+            retries: 0,
+            theme: "dark".to_string(),
+        };
+
+        let input = b"O:8:\"Settings\":1:{s:4:\"name\";s:3:\"app\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        assert_eq!(Settings::deserialize(&mut deserializer).unwrap(), expected);
+
+        for duplicate_fields in [DuplicateFields::First, DuplicateFields::Last] {
+            let mut deserializer =
+                PhpDeserializer::new(&input[..]).duplicate_fields(duplicate_fields);
+            assert_eq!(Settings::deserialize(&mut deserializer).unwrap(), expected);
+        }
+
+        // The end of the object is consumed, so what follows is unaffected.
+        let input = b"a:2:{i:0;O:8:\"Settings\":1:{s:4:\"name\";s:3:\"app\";}i:1;O:8:\"Settings\":2:{s:7:\"retries\";i:3;s:4:\"name\";s:1:\"x\";}}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let all = Vec::<Settings>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            all,
+            vec![
+                expected,
+                Settings {
+                    name: "x".to_string(),
+                    retries: 3,
+                    theme: "dark".to_string(),
+                }
+            ]
+        );
+
+        #[derive(Debug, Default, Deserialize, PartialEq)]
+        #[serde(default)]
+        struct Limits {
+            max: u32,
+            min: u32,
+        }
+
+        let input = b"O:6:\"Limits\":1:{s:3:\"max\";i:9;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let limits = Limits::deserialize(&mut deserializer).unwrap();
+        assert_eq!(limits, Limits { max: 9, min: 0 });
+    }
+
+    #[test]
+    fn test_deserialize_positional_structs() {
+        let input = b"a:2:{i:0;s:5:\"Alice\";i:1;i:30;}";
+        let alice = Person {
+            name: "Alice".to_string(),
+            age: 30,
+        };
+
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        assert!(Person::deserialize(&mut deserializer).is_err());
+
+        let mut deserializer = PhpDeserializer::new(&input[..]).positional_structs(true);
+        let person = Person::deserialize(&mut deserializer).unwrap();
+        assert_eq!(person, alice);
+
+        let input = b"a:2:{i:0;a:2:{i:0;s:5:\"Alice\";i:1;i:30;}i:1;a:2:{s:3:\"age\";i:30;s:4:\"name\";s:5:\"Alice\";}}";
+        let mut deserializer = PhpDeserializer::new(&input[..]).positional_structs(true);
+        let people = Vec::<Person>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(people[0], alice);
+        assert_eq!(people[1], alice);
+        assert_eq!(people.len(), 2);
+    }
+
+    #[rstest]
+    #[case(
+        b"a:1:{i:0;s:5:\"Alice\";}",
+        "invalid length 1, expected struct Person with 2 elements"
+    )]
+    #[case(b"a:3:{i:0;s:5:\"Alice\";i:1;i:30;i:2;N;}", "Expected end of sequence")]
+    #[case(
+        b"a:2:{i:1;s:5:\"Alice\";i:0;i:30;}",
+        "Expected sequence index 0, found integer key 1"
+    )]
+    fn test_deserialize_positional_structs_mismatch(#[case] input: &[u8], #[case] expected: &str) {
+        let mut deserializer = PhpDeserializer::new(input).positional_structs(true);
+        let error = Person::deserialize(&mut deserializer).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::Deserialize { message, .. } if message == expected),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn test_deserialize_tuple_struct_from_object() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Point(i64, i64, String);
+
+        let input = b"O:5:\"Point\":3:{s:1:\"x\";i:1;s:1:\"y\";i:2;s:5:\"label\";s:6:\"origin\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Point = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Point(1, 2, "origin".to_string()));
+
+        let input = b"O:8:\"stdClass\":2:{i:0;i:5;i:1;i:6;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: (i64, i64) = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, (5, 6));
+    }
+
+    #[test]
+    fn test_deserialize_tuple_struct_from_object_count_mismatch() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Point(i64, i64);
+
+        let input = b"O:5:\"Point\":1:{s:1:\"x\";i:1;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Result<Point, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::Deserialize { message, .. }
+                if message == "Object property count mismatch: expected 2, object has 1"
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_fixed_size_array_length_mismatch() {
+        let input = b"a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Result<[i64; 2], _> = Deserialize::deserialize(&mut deserializer);
+        let error = result.unwrap_err();
+
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. }
+                if message == "Array length mismatch: expected 2, array has 3"
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_seq_explicitly() {
+        #[derive(Debug, PartialEq)]
+        struct ExplicitSeq(Vec<i64>);
+
+        impl<'de> Deserialize<'de> for ExplicitSeq {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct ExplicitSeqVisitor;
+
+                impl<'de> de::Visitor<'de> for ExplicitSeqVisitor {
+                    type Value = ExplicitSeq;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("a sequence of integers")
+                    }
+
+                    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                    where
+                        A: de::SeqAccess<'de>,
+                    {
+                        let mut values = Vec::new();
+                        while let Some(value) = seq.next_element()? {
+                            values.push(value);
+                        }
+
+                        Ok(ExplicitSeq(values))
+                    }
+                }
 
                 deserializer.deserialize_seq(ExplicitSeqVisitor)
             }
@@ -1085,6 +3289,115 @@ mod tests {
         assert_eq!(result, expected);
     }
 
+    #[test]
+    fn test_deserialize_arrayvec() {
+        let input = b"a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result = ArrayVec::<i64, 4>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result.as_slice(), [1, 2, 3]);
+
+        let input = b"a:5:{i:0;i:1;i:1;i:2;i:2;i:3;i:3;i:4;i:4;i:5;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = ArrayVec::<i64, 4>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Deserialize { .. }));
+    }
+
+    #[test]
+    fn test_deserialize_smallvec_stays_inline() {
+        let input = b"a:4:{i:0;i:1;i:1;i:2;i:2;i:3;i:3;i:4;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result = SmallVec::<[i64; 4]>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result.as_slice(), [1, 2, 3, 4]);
+        assert!(!result.spilled());
+    }
+
+    #[test]
+    fn test_size_hint() {
+        struct Hint;
+
+        impl<'de> de::Visitor<'de> for Hint {
+            type Value = Option<usize>;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let hint = seq.size_hint();
+                while seq.next_element::<de::IgnoredAny>()?.is_some() {}
+                Ok(hint)
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let hint = map.size_hint();
+                while map
+                    .next_entry::<de::IgnoredAny, de::IgnoredAny>()?
+                    .is_some()
+                {}
+                Ok(hint)
+            }
+        }
+
+        let input = b"a:3:{i:0;N;i:1;N;i:2;N;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        assert_eq!(deserializer.deserialize_seq(Hint).unwrap(), Some(3));
+        let mut deserializer = PhpDeserializer::new(input).ignore_sequence_keys(true);
+        assert_eq!(deserializer.deserialize_seq(Hint).unwrap(), Some(3));
+        let mut deserializer = PhpDeserializer::new(input);
+        assert_eq!(deserializer.deserialize_map(Hint).unwrap(), Some(3));
+
+        let input = b"O:3:\"Foo\":2:{s:1:\"a\";N;s:1:\"b\";N;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        assert_eq!(deserializer.deserialize_tuple(2, Hint).unwrap(), Some(2));
+        let mut deserializer = PhpDeserializer::new(input);
+        assert_eq!(deserializer.deserialize_map(Hint).unwrap(), Some(2));
+    }
+
+    #[rstest]
+    #[case(5)]
+    #[case(150)] // past the fast path for short strings
+    fn test_deserialize_str_borrows(#[case] len: usize) {
+        let contents = "x".repeat(len);
+        let input = format!("s:{len}:\"{contents}\";");
+        let mut deserializer = PhpDeserializer::new(input.as_bytes());
+        let result = <&str>::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, contents);
+        assert!(input.as_bytes().as_ptr_range().contains(&result.as_ptr()));
+    }
+
+    #[test]
+    fn test_deserialize_int_keyed_array_to_hashmap() {
+        // PHP: array(3 => "three", -1 => "minus one", 0 => "zero")
+        let input = b"a:3:{i:3;s:5:\"three\";i:-1;s:9:\"minus one\";i:0;s:4:\"zero\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: HashMap<i64, String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            HashMap::from([
+                (3, "three".to_string()),
+                (-1, "minus one".to_string()),
+                (0, "zero".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_deserialize_mixed_keys_to_uniform_hashmap() {
+        // PHP: array(0 => "a", "x" => "b")
+        let input = b"a:2:{i:0;s:1:\"a\";s:1:\"x\";s:1:\"b\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let error = HashMap::<i64, String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(!error.is_parse_error());
+        assert_eq!(error.position(), Some(17));
+        assert!(error.to_string().contains("invalid type"), "{error}");
+
+        // Integer keys can't be read as strings either
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let error = HashMap::<String, String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(!error.is_parse_error());
+        assert_eq!(error.position(), Some(5));
+    }
+
     #[test]
     fn test_readme() {
         #[derive(Debug, Deserialize, PartialEq)]
@@ -1291,161 +3604,746 @@ mod tests {
                     }
                 }
 
-                deserializer.deserialize_str(ExplicitStrVisitor)
+                deserializer.deserialize_str(ExplicitStrVisitor)
+            }
+        }
+
+        // Invalid UTF-8 sequence
+        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result = ExplicitStr::deserialize(&mut deserializer);
+        assert!(result.is_err(), "Should fail on invalid UTF-8");
+    }
+
+    #[test]
+    fn test_invalid_utf8_string_does_not_consume_next_token() {
+        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";i:7;";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Result<String, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err(), "Should fail on invalid UTF-8");
+
+        let mut parser = deserializer.into_parser();
+        let token = parser.next_token().unwrap().unwrap();
+        assert_eq!(token, PhpToken::Integer(7));
+    }
+
+    #[test]
+    fn test_deserialize_string_comparison_with_bytes() {
+        // Create a struct that explicitly uses deserialize_bytes
+        #[derive(Debug, PartialEq)]
+        struct ExplicitBytes(Vec<u8>);
+
+        impl<'de> Deserialize<'de> for ExplicitBytes {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::Deserializer<'de>,
+            {
+                struct ExplicitBytesVisitor;
+
+                impl de::Visitor<'_> for ExplicitBytesVisitor {
+                    type Value = ExplicitBytes;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str("bytes")
+                    }
+
+                    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+                    where
+                        E: de::Error,
+                    {
+                        Ok(ExplicitBytes(v.to_owned()))
+                    }
+                }
+
+                deserializer.deserialize_bytes(ExplicitBytesVisitor)
+            }
+        }
+
+        // This should work even with invalid UTF-8
+        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result = ExplicitBytes::deserialize(&mut deserializer);
+        assert!(
+            result.is_ok(),
+            "Should succeed on deserialize_bytes with invalid UTF-8"
+        );
+        assert_eq!(result.unwrap(), ExplicitBytes(vec![0xFF, 0xFF, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn test_deserialize_borrowed_str() {
+        let input = b"s:5:\"hello\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: &str = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, "hello");
+    }
+
+    #[test]
+    fn test_deserialize_borrowed_bytes_fallback() {
+        // Test with invalid UTF-8
+        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+
+        let result: &[u8] = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, &[0xFF, 0xFF, 0xFF, 0xFF][..]);
+
+        // Test with valid UTF-8 too
+        let input = b"s:5:\"hello\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: &[u8] = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, b"hello");
+    }
+
+    #[test]
+    fn test_deserialize_enum() {
+        // Test deserializing "N" to CoPower::None
+        let input = b"s:1:\"N\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, CoPower::None);
+
+        // Test deserializing "Y" to CoPower::Power
+        let input = b"s:1:\"Y\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, CoPower::Power);
+
+        // Test deserializing "S" to CoPower::SuperPower
+        let input = b"s:1:\"S\";";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, CoPower::SuperPower);
+    }
+
+    #[test]
+    fn test_deserialize_enum_in_struct() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Character {
+            name: String,
+            power: CoPower,
+        }
+
+        // PHP: array("name" => "Superman", "power" => "S")
+        let input = b"a:2:{s:4:\"name\";s:8:\"Superman\";s:5:\"power\";s:1:\"S\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Character = Deserialize::deserialize(&mut deserializer).unwrap();
+
+        assert_eq!(
+            result,
+            Character {
+                name: "Superman".to_string(),
+                power: CoPower::SuperPower,
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_custom_object_as_bytes() {
+        let input = b"C:5:\"Test2\":6:{foobar}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Result<&[u8], _> = Deserialize::deserialize(&mut deserializer);
+        assert_eq!(result.unwrap(), b"foobar");
+    }
+
+    #[rstest]
+    #[case(b"b:0;", false)]
+    #[case(b"b:1;", true)]
+    #[case(b"i:0;", false)]
+    #[case(b"i:1;", true)]
+    #[case(b"s:1:\"0\";", false)]
+    #[case(b"s:1:\"1\";", true)]
+    #[case(b"s:5:\"false\";", false)]
+    #[case(b"s:4:\"true\";", true)]
+    fn test_deserialize_lenient_bools(#[case] input: &[u8], #[case] expected: bool) {
+        let mut deserializer = PhpDeserializer::new(input).lenient_bools(true);
+        let result: bool = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, expected);
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Result<bool, _> = Deserialize::deserialize(&mut deserializer);
+        assert_eq!(result.is_ok(), input.starts_with(b"b:"));
+    }
+
+    #[rstest]
+    #[case(b"i:2;")]
+    #[case(b"s:3:\"yes\";")]
+    #[case(b"s:0:\"\";")]
+    #[case(b"N;")]
+    fn test_deserialize_lenient_bools_rejects(#[case] input: &[u8]) {
+        let mut deserializer = PhpDeserializer::new(input).lenient_bools(true);
+        let result: Result<bool, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_deserialize_coerce_numeric_keys() {
+        let input = b"a:2:{s:1:\"5\";s:4:\"five\";s:2:\"05\";s:4:\"zero\";}";
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Result<HashMap<i64, String>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err(), "05 is not a canonical integer");
+
+        let input = b"a:1:{s:1:\"5\";s:4:\"five\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: HashMap<i64, String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, HashMap::from([(5, "five".to_string())]));
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: HashMap<String, String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            HashMap::from([("5".to_string(), "five".to_string())])
+        );
+
+        let mut deserializer = PhpDeserializer::new(input).coerce_numeric_keys(false);
+        let result: Result<HashMap<i64, String>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
+
+        let mut deserializer = PhpDeserializer::new(input).coerce_numeric_keys(false);
+        let result: HashMap<String, String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            HashMap::from([("5".to_string(), "five".to_string())])
+        );
+
+        // Values are never coerced.
+        let mut deserializer = PhpDeserializer::new(b"a:1:{i:0;s:1:\"5\";}");
+        let result: Result<Vec<i64>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
+    }
+
+    #[rstest]
+    #[case(DuplicateFields::First, "Alice", 30)]
+    #[case(DuplicateFields::Last, "Mallory", 31)]
+    fn test_deserialize_duplicate_fields(
+        #[case] policy: DuplicateFields,
+        #[case] name: &str,
+        #[case] age: i32,
+    ) {
+        let input = b"O:6:\"Person\":4:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;s:12:\"\0Person\0name\";s:7:\"Mallory\";s:3:\"age\";i:31;}";
+        let mut deserializer = PhpDeserializer::new(input).duplicate_fields(policy);
+        let result: Person = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Person {
+                name: name.to_string(),
+                age
+            }
+        );
+        assert!(deserializer.into_parser().is_done());
+    }
+
+    #[rstest]
+    #[case(DuplicateFields::First, 10)]
+    #[case(DuplicateFields::Last, 20)]
+    fn test_deserialize_duplicate_integer_fields(
+        #[case] policy: DuplicateFields,
+        #[case] one: i64,
+    ) {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Numbered {
+            #[serde(rename = "1")]
+            one: i64,
+        }
+
+        // `i:1;` and `s:1:"1";` name the same field.
+        let input = b"a:2:{i:1;i:10;s:1:\"1\";i:20;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        assert!(Numbered::deserialize(&mut deserializer).is_err());
+
+        let mut deserializer = PhpDeserializer::new(input).duplicate_fields(policy);
+        assert_eq!(
+            Numbered::deserialize(&mut deserializer).unwrap(),
+            Numbered { one }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_duplicate_fields_nested() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Node {
+            name: String,
+            child: Option<Box<Node>>,
+        }
+
+        let input = b"a:3:{s:4:\"name\";s:1:\"a\";s:5:\"child\";a:3:{s:4:\"name\";s:1:\"b\";s:5:\"child\";N;s:4:\"name\";s:1:\"c\";}s:4:\"name\";s:1:\"d\";}i:1;";
+        let mut deserializer = PhpDeserializer::new(input).duplicate_fields(DuplicateFields::Last);
+        assert_eq!(
+            Node::deserialize(&mut deserializer).unwrap(),
+            Node {
+                name: "d".to_string(),
+                child: Some(Box::new(Node {
+                    name: "c".to_string(),
+                    child: None,
+                })),
+            }
+        );
+        let mut parser = deserializer.into_parser();
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(1));
+    }
+
+    #[test]
+    fn test_deserialize_duplicate_fields_error() {
+        let input = b"a:3:{s:4:\"name\";s:5:\"Alice\";s:4:\"name\";a:1:{i:0;N;}s:3:\"age\";i:30;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = Person::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. } if message == "duplicate field `name`"
+        ));
+
+        // Skipped duplicates may be containers
+        let mut deserializer = PhpDeserializer::new(input).duplicate_fields(DuplicateFields::First);
+        let result = Person::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result.name, "Alice");
+
+        // Maps are unaffected
+        let mut deserializer = PhpDeserializer::new(b"a:2:{i:1;i:1;i:1;i:2;}")
+            .duplicate_fields(DuplicateFields::First);
+        let result: Vec<(i64, i64)> = HashMap::<i64, i64>::deserialize(&mut deserializer)
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(result, vec![(1, 2)]);
+    }
+
+    #[test]
+    fn test_deserialize_allowed_classes() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Person {
+            name: String,
+        }
+
+        let person = b"O:6:\"Person\":1:{s:4:\"name\";s:5:\"Alice\";}";
+        let allow_list = |class: &[u8]| [b"Person".as_slice()].contains(&class);
+        let mut deserializer = PhpDeserializer::new(person).allow_class(allow_list);
+        let result = Person::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Person {
+                name: "Alice".to_string()
+            }
+        );
+
+        let evil = b"a:2:{i:0;O:6:\"Person\":1:{s:4:\"name\";s:3:\"Bob\";}i:1;O:9:\"EvilClass\":1:{s:4:\"name\";s:3:\"rm!\";}}";
+        let mut deserializer = PhpDeserializer::new(evil).allow_class(allow_list);
+        let error = Vec::<Person>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 51 } if class == "EvilClass"
+        ));
+
+        let custom = b"C:9:\"EvilClass\":2:{xy}";
+        let mut deserializer = PhpDeserializer::new(custom).allow_class(allow_list);
+        let error = Person::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 0 } if class == "EvilClass"
+        ));
+
+        let mut deserializer = PhpDeserializer::new(evil);
+        assert_eq!(
+            Vec::<Person>::deserialize(&mut deserializer).unwrap().len(),
+            2
+        );
+
+        let case = b"a:1:{i:0;E:11:\"Secret:Case\";}";
+        let mut deserializer = PhpDeserializer::new(case).allow_class(allow_list);
+        let error = Vec::<String>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 9 } if class == "Secret"
+        ));
+
+        let mut deserializer = PhpDeserializer::new(case).allow_class(|class| class == b"Secret");
+        assert_eq!(
+            Vec::<String>::deserialize(&mut deserializer).unwrap(),
+            vec!["Case"]
+        );
+
+        // Values that are skipped over are checked too.
+        let ignored =
+            b"O:6:\"Person\":2:{s:4:\"name\";s:3:\"Bob\";s:3:\"pet\";O:9:\"EvilClass\":0:{}}";
+        let mut deserializer = PhpDeserializer::new(ignored).allow_class(allow_list);
+        let error = Person::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 47 } if class == "EvilClass"
+        ));
+    }
+
+    #[test]
+    fn test_values() {
+        let mut deserializer = PhpDeserializer::new(b"a:3:{i:0;i:1;i:1;i:2;i:2;i:3;}");
+        assert_eq!(deserializer.values::<i64>().unwrap(), vec![1, 2, 3]);
+        assert!(deserializer.into_parser().is_done());
+
+        let input = b"a:2:{i:5;a:1:{s:4:\"name\";s:3:\"Bob\";}s:3:\"key\";a:1:{s:4:\"name\";s:5:\"Alice\";}}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let people = deserializer.values::<HashMap<String, String>>().unwrap();
+        assert_eq!(people[0]["name"], "Bob");
+        assert_eq!(people[1]["name"], "Alice");
+
+        let mut deserializer = PhpDeserializer::new(b"a:0:{}");
+        assert!(deserializer.values::<i64>().unwrap().is_empty());
+
+        let input = b"a:2:{i:0;s:3:\"foo\";i:1;r:2;}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        assert_eq!(deserializer.values::<String>().unwrap(), vec!["foo", "foo"]);
+    }
+
+    #[test]
+    fn test_values_rejects_non_arrays() {
+        let input = b"O:3:\"Foo\":2:{s:1:\"a\";i:1;s:1:\"b\";i:2;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = deserializer.values::<i64>().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, position: Some(0) } if message.starts_with("Expected array")
+        ));
+
+        let mut deserializer = PhpDeserializer::new(input);
+        assert!(Vec::<i64>::deserialize(&mut deserializer).is_err());
+
+        let mut deserializer = PhpDeserializer::new(b"i:1;");
+        assert!(deserializer.values::<i64>().is_err());
+
+        let mut deserializer = PhpDeserializer::new(b"a:1:{i:0;s:1:\"a\";}");
+        assert!(deserializer.values::<i64>().is_err());
+    }
+
+    #[test]
+    fn test_deserialize_newtypes() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Id(i64);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Name(String);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Score(f64);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Player {
+            id: Id,
+            name: Name,
+            scores: Vec<Score>,
+        }
+
+        let mut deserializer = PhpDeserializer::new(b"i:5;");
+        assert_eq!(Id::deserialize(&mut deserializer).unwrap(), Id(5));
+        assert!(deserializer.into_parser().is_done());
+
+        let mut deserializer = PhpDeserializer::new(b"s:5:\"Alice\";");
+        assert_eq!(
+            Name::deserialize(&mut deserializer).unwrap(),
+            Name("Alice".to_string())
+        );
+
+        let mut deserializer = PhpDeserializer::new(b"d:1.5;");
+        assert_eq!(Score::deserialize(&mut deserializer).unwrap(), Score(1.5));
+
+        let input = b"a:3:{s:2:\"id\";i:7;s:4:\"name\";s:3:\"Bob\";s:6:\"scores\";a:2:{i:0;d:0.5;i:1;r:5;}}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        assert_eq!(
+            Player::deserialize(&mut deserializer).unwrap(),
+            Player {
+                id: Id(7),
+                name: Name("Bob".to_string()),
+                scores: vec![Score(0.5), Score(0.5)],
+            }
+        );
+        assert!(deserializer.into_parser().is_done());
+    }
+
+    #[test]
+    fn test_deserialize_mangled_looking_values() {
+        // Only keys are demangled: string values that look like mangled
+        // property names are kept as is.
+        let input = b"s:9:\"\0*\0secret\";";
+        let value = String::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(value, "\0*\0secret");
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Token {
+            value: String,
+            scope: HashMap<String, String>,
+        }
+
+        let input = b"O:5:\"Token\":2:{s:8:\"\0*\0value\";s:12:\"\0Token\0value\";s:5:\"scope\";a:1:{s:7:\"\0*\0name\";s:7:\"\0*\0name\";}}";
+        let token = Token::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(
+            token,
+            Token {
+                value: "\0Token\0value".to_string(),
+                scope: HashMap::from([("\0*\0name".to_string(), "\0*\0name".to_string())]),
+            }
+        );
+    }
+
+    #[test]
+    fn test_deserialize_closure_placeholder() {
+        let input = b"a:2:{s:4:\"name\";s:5:\"Alice\";s:8:\"callback\";O:7:\"Closure\":0:{}}";
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Handler {
+            name: String,
+            callback: Option<String>,
+        }
+
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = Handler::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 43 } if class == "Closure"
+        ));
+
+        let mut deserializer = PhpDeserializer::new(input).closures_as_null(true);
+        let result = Handler::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Handler {
+                name: "Alice".to_string(),
+                callback: None
             }
-        }
+        );
+        assert!(deserializer.into_parser().is_done());
 
-        // Invalid UTF-8 sequence
-        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result = ExplicitStr::deserialize(&mut deserializer);
-        assert!(result.is_err(), "Should fail on invalid UTF-8");
+        let mut deserializer = PhpDeserializer::new(b"O:7:\"Closure\":0:{}").closures_as_null(true);
+        let result: () = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, ());
+
+        // Only the empty placeholder is recognized
+        let input = b"O:7:\"Closure\":1:{s:1:\"a\";i:1;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: HashMap<String, i64> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result["a"], 1);
     }
 
     #[test]
-    fn test_invalid_utf8_string_does_not_consume_next_token() {
-        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";i:7;";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: Result<String, _> = Deserialize::deserialize(&mut deserializer);
-        assert!(result.is_err(), "Should fail on invalid UTF-8");
+    fn test_deserialize_closure_placeholder_untyped() {
+        // Before `closures_as_null` existed, untyped targets accepted the
+        // placeholder as an empty object. It is rejected like anywhere else
+        // now.
+        let input = b"a:1:{i:0;O:7:\"Closure\":0:{}}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error =
+            HashMap::<i64, HashMap<String, i64>>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, position: 9 } if class == "Closure"
+        ));
 
-        let mut parser = deserializer.into_parser();
-        let token = parser.next_token().unwrap().unwrap();
-        assert_eq!(token, PhpToken::Integer(7));
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = crate::PhpValue::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::UnsupportedClass { .. }));
+
+        let mut deserializer = PhpDeserializer::new(input).closures_as_null(true);
+        let result = HashMap::<i64, Option<HashMap<String, i64>>>::deserialize(&mut deserializer);
+        assert_eq!(result.unwrap(), HashMap::from([(0, None)]));
     }
 
     #[test]
-    fn test_deserialize_string_comparison_with_bytes() {
-        // Create a struct that explicitly uses deserialize_bytes
-        #[derive(Debug, PartialEq)]
-        struct ExplicitBytes(Vec<u8>);
+    fn test_deserialize_float_from_integer() {
+        let mut deserializer = PhpDeserializer::new(b"i:42;");
+        let result: f64 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, 42.0);
 
-        impl<'de> Deserialize<'de> for ExplicitBytes {
-            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-            where
-                D: serde::Deserializer<'de>,
-            {
-                struct ExplicitBytesVisitor;
+        let mut deserializer = PhpDeserializer::new(b"i:-7;");
+        let result: f32 = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, -7.0);
 
-                impl de::Visitor<'_> for ExplicitBytesVisitor {
-                    type Value = ExplicitBytes;
+        // Visitors that only accept floats see a float
+        struct OnlyFloat(f64);
+
+        impl<'de> Deserialize<'de> for OnlyFloat {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct OnlyFloatVisitor;
+
+                impl de::Visitor<'_> for OnlyFloatVisitor {
+                    type Value = OnlyFloat;
 
                     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                        formatter.write_str("bytes")
+                        formatter.write_str("a float")
                     }
 
-                    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
-                    where
-                        E: de::Error,
-                    {
-                        Ok(ExplicitBytes(v.to_owned()))
+                    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                        Ok(OnlyFloat(v))
                     }
                 }
 
-                deserializer.deserialize_bytes(ExplicitBytesVisitor)
+                deserializer.deserialize_f64(OnlyFloatVisitor)
             }
         }
 
-        // This should work even with invalid UTF-8
-        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result = ExplicitBytes::deserialize(&mut deserializer);
-        assert!(
-            result.is_ok(),
-            "Should succeed on deserialize_bytes with invalid UTF-8"
+        let mut deserializer = PhpDeserializer::new(b"a:2:{i:0;i:1;i:1;d:2.5;}");
+        let result: Vec<OnlyFloat> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result.iter().map(|f| f.0).collect::<Vec<_>>(),
+            vec![1.0, 2.5]
         );
-        assert_eq!(result.unwrap(), ExplicitBytes(vec![0xFF, 0xFF, 0xFF, 0xFF]));
+
+        let mut deserializer = PhpDeserializer::new(b"s:2:\"42\";");
+        let result: Result<f64, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(result.is_err());
     }
 
     #[test]
-    fn test_deserialize_borrowed_str() {
-        let input = b"s:5:\"hello\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: &str = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, "hello");
+    fn test_deserialize_empty_string_as_none() {
+        let input = b"s:0:\"\";";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Option<String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Some(String::new()));
+
+        let mut deserializer = PhpDeserializer::new(input).empty_string_as_none(true);
+        let result: Option<String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, None);
+
+        // Only options are affected
+        let mut deserializer = PhpDeserializer::new(input).empty_string_as_none(true);
+        let result: String = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, "");
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Contact {
+            email: Option<String>,
+            phone: Option<String>,
+        }
+
+        let input = b"a:2:{s:5:\"email\";s:0:\"\";s:5:\"phone\";s:3:\"555\";}";
+        let mut deserializer = PhpDeserializer::new(input).empty_string_as_none(true);
+        let result: Contact = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Contact {
+                email: None,
+                phone: Some("555".to_string())
+            }
+        );
     }
 
     #[test]
-    fn test_deserialize_borrowed_bytes_fallback() {
-        // Test with invalid UTF-8
-        let input = b"s:4:\"\xFF\xFF\xFF\xFF\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
+    fn test_deserialize_object_fewer_properties_than_declared() {
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Foo {
+            a: i64,
+            b: Option<i64>,
+        }
 
-        let result: &[u8] = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, &[0xFF, 0xFF, 0xFF, 0xFF][..]);
+        let input = b"O:3:\"Foo\":5:{s:1:\"a\";i:1;s:1:\"b\";i:2;s:1:\"c\";i:3;}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = Foo::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::CountMismatch {
+                declared: 5,
+                found: 3,
+                position: 49
+            }
+        ));
 
-        // Test with valid UTF-8 too
-        let input = b"s:5:\"hello\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: &[u8] = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, b"hello");
+        let mut deserializer = PhpDeserializer::new(input);
+        let error = HashMap::<String, i64>::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::CountMismatch {
+                declared: 5,
+                found: 3,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn test_deserialize_enum() {
-        // Test deserializing "N" to CoPower::None
-        let input = b"s:1:\"N\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, CoPower::None);
+    fn test_deserialize_gapped_array() {
+        let input = b"a:3:{i:0;s:1:\"a\";i:2;s:1:\"c\";s:1:\"k\";s:1:\"d\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Result<Vec<String>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::Deserialize { message, .. }
+                if message == "Expected sequence index 1, found integer key 2"
+        ));
 
-        // Test deserializing "Y" to CoPower::Power
-        let input = b"s:1:\"Y\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, CoPower::Power);
+        let mut deserializer = PhpDeserializer::new(input).ignore_sequence_keys(true);
+        let result: Vec<String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, vec!["a", "c", "d"]);
 
-        // Test deserializing "S" to CoPower::SuperPower
-        let input = b"s:1:\"S\";";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: CoPower = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, CoPower::SuperPower);
+        let input = b"a:2:{i:0;s:1:\"a\";i:2;s:1:\"c\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: BTreeMap<i64, String> = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            BTreeMap::from([(0, "a".to_string()), (2, "c".to_string())])
+        );
     }
 
     #[test]
-    fn test_deserialize_enum_in_struct() {
+    fn test_from_reader() {
         #[derive(Debug, Deserialize, PartialEq)]
-        struct Character {
+        struct Person {
             name: String,
-            power: CoPower,
+            age: u8,
         }
 
-        // PHP: array("name" => "Superman", "power" => "S")
-        let input = b"a:2:{s:4:\"name\";s:8:\"Superman\";s:5:\"power\";s:1:\"S\";}";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: Character = Deserialize::deserialize(&mut deserializer).unwrap();
-
+        let input: &[u8] = b"O:6:\"Person\":2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;}";
+        let person: Person = from_reader(input).unwrap();
         assert_eq!(
-            result,
-            Character {
-                name: "Superman".to_string(),
-                power: CoPower::SuperPower,
+            person,
+            Person {
+                name: "Alice".to_string(),
+                age: 30
             }
         );
+
+        let result: Result<i64, _> = from_reader(&b"i:1;i:2;"[..]);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::TrailingData { position: 4 }
+        ));
     }
 
     #[test]
-    fn test_deserialize_custom_object_as_bytes() {
-        let input = b"C:5:\"Test2\":6:{foobar}";
-        let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: Result<&[u8], _> = Deserialize::deserialize(&mut deserializer);
-        assert_eq!(result.unwrap(), b"foobar");
+    fn test_from_reader_io_error() {
+        struct Failing;
+
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("boom"))
+            }
+        }
+
+        let result: Result<i64, _> = from_reader(Failing);
+        assert!(matches!(result.unwrap_err().kind(), ErrorKind::Io(_)));
     }
 
     #[test]
-    fn test_deserialize_lowercase_reference_as_id() {
-        let input = b"r:1;";
+    fn test_deserialize_lowercase_reference_is_unsupported() {
+        let input = b"a:2:{i:0;i:1;i:1;r:2;}";
         let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: i64 = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, 1);
+        let result: Result<Vec<i64>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 17
+            }
+        ));
     }
 
     #[test]
-    fn test_deserialize_uppercase_reference_as_id() {
-        let input = b"R:2;";
+    fn test_deserialize_uppercase_reference_is_unsupported() {
+        let input = b"a:2:{i:0;i:1;i:1;R:2;}";
         let mut deserializer = PhpDeserializer::new(&input[..]);
-        let result: i64 = Deserialize::deserialize(&mut deserializer).unwrap();
-        assert_eq!(result, 2);
+        let result: Result<Vec<i64>, _> = Deserialize::deserialize(&mut deserializer);
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 17
+            }
+        ));
     }
 
     #[test]
@@ -1574,6 +4472,183 @@ mod tests {
         assert_eq!(result, Message::Text("Hello".to_string()));
     }
 
+    #[test]
+    fn test_deserialize_object_empty_class() {
+        let input = b"O:0:\"\":2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Person = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Person {
+                name: "Alice".to_string(),
+                age: 30
+            }
+        );
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Message {
+            Text(String),
+        }
+
+        // The class name plays no part in selecting the variant
+        let input = b"O:0:\"\":1:{s:4:\"Text\";s:5:\"Hello\";}";
+        let mut deserializer = PhpDeserializer::new(&input[..]);
+        let result: Message = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Message::Text("Hello".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_internally_tagged_enum_object_form() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "type")]
+        enum Message {
+            Text { body: String },
+            Ping,
+        }
+
+        let object = b"O:7:\"Message\":2:{s:4:\"type\";s:4:\"Text\";s:4:\"body\";s:2:\"hi\";}";
+        let array = b"a:2:{s:4:\"type\";s:4:\"Text\";s:4:\"body\";s:2:\"hi\";}";
+        for input in [&object[..], &array[..]] {
+            let mut deserializer = PhpDeserializer::new(input);
+            let result: Message = Deserialize::deserialize(&mut deserializer).unwrap();
+            assert_eq!(
+                result,
+                Message::Text {
+                    body: "hi".to_string()
+                }
+            );
+        }
+
+        // The tag doesn't have to come first
+        let input = b"O:7:\"Message\":2:{s:4:\"body\";s:2:\"hi\";s:4:\"type\";s:4:\"Text\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Message = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Message::Text {
+                body: "hi".to_string()
+            }
+        );
+
+        let input = b"O:7:\"Message\":1:{s:4:\"type\";s:4:\"Ping\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Message = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Message::Ping);
+    }
+
+    #[test]
+    fn test_deserialize_adjacently_tagged_enum_object_form() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "t", content = "c")]
+        enum Message {
+            Text(String),
+        }
+
+        let input = b"O:7:\"Message\":2:{s:1:\"t\";s:4:\"Text\";s:1:\"c\";s:2:\"hi\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Message = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Message::Text("hi".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_seed_with_state() {
+        /// Deserializes any value, counting the strings within it.
+        struct StringCounter<'a>(&'a mut usize);
+
+        impl<'de> DeserializeSeed<'de> for StringCounter<'_> {
+            type Value = ();
+
+            fn deserialize<D: serde::Deserializer<'de>>(
+                self,
+                deserializer: D,
+            ) -> Result<(), D::Error> {
+                deserializer.deserialize_any(self)
+            }
+        }
+
+        impl<'de> de::Visitor<'de> for StringCounter<'_> {
+            type Value = ();
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any value")
+            }
+
+            fn visit_bytes<E: de::Error>(self, _v: &[u8]) -> Result<(), E> {
+                *self.0 += 1;
+                Ok(())
+            }
+
+            fn visit_i64<E: de::Error>(self, _v: i64) -> Result<(), E> {
+                Ok(())
+            }
+
+            fn visit_bool<E: de::Error>(self, _v: bool) -> Result<(), E> {
+                Ok(())
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+                while map.next_key_seed(StringCounter(&mut *self.0))?.is_some() {
+                    map.next_value_seed(StringCounter(&mut *self.0))?;
+                }
+                Ok(())
+            }
+        }
+
+        let input =
+            b"a:2:{s:4:\"name\";s:5:\"Alice\";s:4:\"tags\";O:3:\"Foo\":2:{i:0;s:1:\"a\";i:1;b:1;}}";
+        let mut strings = 0;
+        let mut deserializer = PhpDeserializer::new(input);
+        StringCounter(&mut strings)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(strings, 4);
+        assert!(deserializer.into_parser().is_done());
+
+        // The same state carries across deserializers
+        let mut deserializer = PhpDeserializer::new(b"s:1:\"x\";");
+        StringCounter(&mut strings)
+            .deserialize(&mut deserializer)
+            .unwrap();
+        assert_eq!(strings, 5);
+    }
+
+    #[test]
+    fn test_deserialize_php_enum() {
+        #[derive(Debug, Deserialize, PartialEq)]
+        enum Suit {
+            Hearts,
+            Spades,
+        }
+
+        let mut deserializer = PhpDeserializer::new(b"E:11:\"Suit:Hearts\";");
+        let result: Suit = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(result, Suit::Hearts);
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Card {
+            suit: Suit,
+            name: String,
+        }
+
+        let input = b"O:4:\"Card\":2:{s:4:\"suit\";E:21:\"App\\Cards\\Suit:Spades\";s:4:\"name\";E:11:\"Suit:Hearts\";}";
+        let mut deserializer = PhpDeserializer::new(input);
+        let result: Card = Deserialize::deserialize(&mut deserializer).unwrap();
+        assert_eq!(
+            result,
+            Card {
+                suit: Suit::Spades,
+                name: "Hearts".to_string()
+            }
+        );
+
+        let mut deserializer = PhpDeserializer::new(b"E:10:\"Suit:Clubs\";");
+        let error = Suit::deserialize(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. } if message.starts_with("unknown variant `Clubs`")
+        ));
+    }
+
     #[test]
     fn test_from_parser_and_into_parser() {
         // Create a parser with a complex structure
@@ -0,0 +1,116 @@
+use rust_decimal::Decimal;
+use serde::de::{self, Deserializer, Unexpected, Visitor};
+use std::fmt;
+use std::str::FromStr;
+
+/// Deserialize a PHP decimal string, like `s:5:"12.34";`, into a
+/// [`Decimal`] without going through a float.
+///
+/// PHP applications commonly store amounts of money as strings, as produced
+/// by `bcmath` or `number_format`, to avoid float rounding. Integers are
+/// accepted as well. Floats are rejected as they may already have lost
+/// precision.
+///
+/// Meant to be used with `#[serde(deserialize_with)]`:
+///
+/// ```rust
+/// use phpserz::PhpDeserializer;
+/// use rust_decimal::Decimal;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Invoice {
+///     #[serde(deserialize_with = "phpserz::decimal")]
+///     total: Decimal,
+/// }
+///
+/// let input = b"a:1:{s:5:\"total\";s:6:\"199.99\";}";
+/// let invoice = Invoice::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+/// assert_eq!(invoice.total, Decimal::new(19999, 2));
+/// ```
+pub fn decimal<'de, D>(deserializer: D) -> Result<Decimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+struct DecimalVisitor;
+
+impl Visitor<'_> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal string or integer")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Decimal::from_str(v).map_err(|_| de::Error::invalid_value(Unexpected::Str(v), &self))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        match std::str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(de::Error::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Decimal::from(v))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Decimal::from(v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, PhpDeserializer};
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(b"s:5:\"12.34\";", Decimal::new(1234, 2))]
+    #[case(b"s:5:\"-0.50\";", Decimal::new(-50, 2))]
+    #[case(b"s:29:\"79228162514264337593543950335\";", Decimal::MAX)]
+    #[case(b"i:42;", Decimal::from(42))]
+    fn test_deserialize_decimal(#[case] input: &[u8], #[case] expected: Decimal) {
+        let result = decimal(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(result, expected);
+        assert_eq!(result.scale(), expected.scale());
+    }
+
+    #[rstest]
+    #[case(
+        b"s:5:\"12,34\";",
+        "invalid value: string \"12,34\", expected a decimal string or integer"
+    )]
+    #[case(
+        b"s:0:\"\";",
+        "invalid value: string \"\", expected a decimal string or integer"
+    )]
+    #[case(
+        b"d:12.34;",
+        "invalid type: floating point `12.34`, expected a decimal string or integer"
+    )]
+    fn test_deserialize_decimal_malformed(#[case] input: &[u8], #[case] expected: &str) {
+        let error = decimal(&mut PhpDeserializer::new(input)).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::Deserialize { message, .. } if message == expected),
+            "{error:?}"
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use crate::parser::PhpTokenKind;
+
 /// An PHPserz error.
 #[derive(Debug)]
 pub struct Error {
@@ -11,6 +13,126 @@ impl Error {
         &self.kind
     }
 
+    /// Attach a position to a deserialization error that doesn't have one,
+    /// like errors raised through `serde::de::Error::custom`. Errors that
+    /// already have a position are returned unchanged.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "serde")] {
+    /// use phpserz::Error;
+    /// use serde::de::Error as _;
+    /// let error = Error::custom("bad value").at_position(12);
+    /// assert_eq!(error.position(), Some(12));
+    /// # }
+    /// ```
+    #[must_use]
+    pub fn at_position(mut self, position: usize) -> Self {
+        if let ErrorKind::Deserialize {
+            position: slot @ None,
+            ..
+        } = &mut self.kind
+        {
+            *slot = Some(position);
+        }
+        self
+    }
+
+    /// Bundle the error with where it happened in `input`, the data that was
+    /// being read, so it can be reported after the input is gone.
+    ///
+    /// ```rust
+    /// use phpserz::PhpParser;
+    /// let input = b"a:1:{i:0;\ni:x;}".to_vec();
+    /// let mut parser = PhpParser::new(&input).skip_line_breaks(true);
+    /// let error = loop {
+    ///     if let Err(error) = parser.read_token() {
+    ///         break error;
+    ///     }
+    /// };
+    /// let report = error.into_owned_report(&input);
+    /// drop(input);
+    /// assert_eq!((report.line, report.column), (Some(2), Some(3)));
+    /// assert_eq!(report.snippet, b"a:1:{i:0;\ni:x;}");
+    /// ```
+    #[must_use]
+    pub fn into_owned_report(self, input: &[u8]) -> OwnedErrorReport {
+        let Some(position) = self.position() else {
+            return OwnedErrorReport {
+                error: self,
+                line: None,
+                column: None,
+                snippet: Vec::new(),
+                snippet_offset: 0,
+            };
+        };
+
+        let position = position.min(input.len());
+        let before = &input[..position];
+        let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let start = position.saturating_sub(SNIPPET_CONTEXT);
+        let end = position.saturating_add(SNIPPET_CONTEXT).min(input.len());
+        OwnedErrorReport {
+            error: self,
+            line: Some(line),
+            column: Some(position - line_start + 1),
+            snippet: input[start..end].to_vec(),
+            snippet_offset: position - start,
+        }
+    }
+
+    /// Returns true if the error is due to malformed input, as opposed to
+    /// input that doesn't fit the requested type, errors raised by `Serialize`
+    /// and `Deserialize` implementations, or I/O errors.
+    ///
+    /// ```rust
+    /// # #[cfg(feature = "serde")] {
+    /// use phpserz::{Error, PhpParser};
+    /// use serde::de::Error as _;
+    /// assert!(PhpParser::new(b"i:x;").read_token().unwrap_err().is_parse_error());
+    /// assert!(!Error::custom("bad value").is_parse_error());
+    /// # }
+    /// ```
+    #[must_use]
+    pub const fn is_parse_error(&self) -> bool {
+        match &self.kind {
+            ErrorKind::Eof
+            | ErrorKind::MismatchByte { .. }
+            | ErrorKind::UnexpectedByte { .. }
+            | ErrorKind::Empty { .. }
+            | ErrorKind::MissingQuotes { .. }
+            | ErrorKind::StringTooLong { .. }
+            | ErrorKind::InvalidNumber { .. }
+            | ErrorKind::InvalidBoolean { .. }
+            | ErrorKind::TrailingData { .. }
+            | ErrorKind::LengthOverflow { .. }
+            | ErrorKind::IntegerOverflow { .. }
+            | ErrorKind::CountMismatch { .. }
+            | ErrorKind::TruncatedString { .. }
+            | ErrorKind::UnsupportedIgbinaryVersion { .. }
+            | ErrorKind::UnsupportedIgbinaryType { .. }
+            | ErrorKind::InvalidStringId { .. }
+            | ErrorKind::InvalidReference { .. }
+            | ErrorKind::ForwardReference { .. }
+            | ErrorKind::InvalidEnum { .. }
+            | ErrorKind::StringLengthMismatch { .. }
+            | ErrorKind::InvalidKey { .. }
+            | ErrorKind::NotSerialized { .. }
+            | ErrorKind::MissingTerminator { .. } => true,
+            ErrorKind::Utf8(_)
+            | ErrorKind::Deserialize { .. }
+            | ErrorKind::DepthLimitExceeded { .. }
+            | ErrorKind::ResourceLimit { .. }
+            | ErrorKind::UnsupportedReference { .. }
+            | ErrorKind::UnsupportedClass { .. }
+            | ErrorKind::Serialize { .. }
+            | ErrorKind::Io(_) => false,
+        }
+    }
+
     /// Get the position of the error.
     #[must_use]
     pub const fn position(&self) -> Option<usize> {
@@ -21,9 +143,28 @@ impl Error {
             | ErrorKind::MissingQuotes { position }
             | ErrorKind::StringTooLong { position }
             | ErrorKind::InvalidNumber { position }
-            | ErrorKind::Overflow { position } => Some(*position),
+            | ErrorKind::InvalidBoolean { position }
+            | ErrorKind::TrailingData { position }
+            | ErrorKind::LengthOverflow { position }
+            | ErrorKind::IntegerOverflow { position }
+            | ErrorKind::CountMismatch { position, .. }
+            | ErrorKind::DepthLimitExceeded { position }
+            | ErrorKind::ResourceLimit { position, .. }
+            | ErrorKind::UnsupportedReference { position, .. }
+            | ErrorKind::TruncatedString { position, .. }
+            | ErrorKind::UnsupportedIgbinaryType { position, .. }
+            | ErrorKind::InvalidStringId { position, .. }
+            | ErrorKind::UnsupportedClass { position, .. }
+            | ErrorKind::InvalidReference { position, .. }
+            | ErrorKind::ForwardReference { position, .. }
+            | ErrorKind::InvalidEnum { position }
+            | ErrorKind::StringLengthMismatch { position, .. }
+            | ErrorKind::InvalidKey { position, .. }
+            | ErrorKind::NotSerialized { position }
+            | ErrorKind::MissingTerminator { position } => Some(*position),
             ErrorKind::Deserialize { position, .. } => *position,
             ErrorKind::Eof
+            | ErrorKind::UnsupportedIgbinaryVersion { .. }
             | ErrorKind::Utf8(_)
             | ErrorKind::Serialize { .. }
             | ErrorKind::Io(_) => None,
@@ -31,6 +172,44 @@ impl Error {
     }
 }
 
+/// How many bytes on either side of an error's position are kept in an
+/// [`OwnedErrorReport`].
+const SNIPPET_CONTEXT: usize = 16;
+
+/// An error along with the input surrounding it, as created by
+/// [`Error::into_owned_report`].
+///
+/// The line and column are only known for errors with a position.
+#[derive(Debug)]
+pub struct OwnedErrorReport {
+    /// The error being reported.
+    pub error: Error,
+
+    /// The 1-based line of the error's position, where lines are separated
+    /// by `\n`.
+    pub line: Option<usize>,
+
+    /// The 1-based column of the error's position, counted in bytes.
+    pub column: Option<usize>,
+
+    /// Up to 16 bytes of input on either side of the error's position.
+    pub snippet: Vec<u8>,
+
+    /// The offset of the error's position within the snippet.
+    pub snippet_offset: usize,
+}
+
+impl std::fmt::Display for OwnedErrorReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.error)?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            let snippet = String::from_utf8_lossy(&self.snippet);
+            write!(f, " (line {line}, column {column}) near {snippet:?}")?;
+        }
+        Ok(())
+    }
+}
+
 /// The kind of error that can occur when working with PHP serialized data.
 #[derive(Debug)]
 #[non_exhaustive]
@@ -62,7 +241,118 @@ pub enum ErrorKind {
     InvalidNumber {
         position: usize,
     },
-    Overflow {
+    /// A boolean value has more than the single `0` or `1` digit.
+    InvalidBoolean {
+        position: usize,
+    },
+    /// Input remains after a complete value.
+    TrailingData {
+        position: usize,
+    },
+    /// A length or count (string length, array elements, object properties)
+    /// exceeds `u32::MAX`.
+    LengthOverflow {
+        position: usize,
+    },
+    /// An integer value does not fit in an `i64`.
+    IntegerOverflow {
+        position: usize,
+    },
+    /// The declared number of entries in an array or object disagrees with
+    /// its contents. When detected before the contents are read, `found` is
+    /// the most entries the remaining input could hold.
+    CountMismatch {
+        declared: u32,
+        found: u32,
+        position: usize,
+    },
+    /// Arrays and objects are nested deeper than the configured limit.
+    DepthLimitExceeded {
+        position: usize,
+    },
+    /// Deserializing exceeded a configured resource limit: either
+    /// [`PhpDeserializer::max_total_bytes`](crate::PhpDeserializer::max_total_bytes),
+    /// which counts materialized string bytes and container entries, or
+    /// [`PhpDeserializer::max_reference_bytes`](crate::PhpDeserializer::max_reference_bytes),
+    /// which counts input read again while resolving references. `limit` is
+    /// the value of whichever was exceeded.
+    ResourceLimit {
+        limit: usize,
+        position: usize,
+    },
+    /// A reference (`r:N;` or `R:N;`) was found where a value was expected,
+    /// and the deserializer is not resolving references.
+    UnsupportedReference {
+        index: u64,
+        position: usize,
+    },
+    /// A reference to a value id below 1, the first id PHP assigns.
+    InvalidReference {
+        index: i64,
+        position: usize,
+    },
+    /// A reference to a value that hasn't been parsed yet. PHP only ever
+    /// references values that precede the reference, so the input is
+    /// resolved in a single pass and forward references are rejected.
+    ForwardReference {
+        index: u64,
+        position: usize,
+    },
+    /// A string or custom object payload declares more bytes than remain in
+    /// the input.
+    TruncatedString {
+        declared: u32,
+        position: usize,
+    },
+    /// The declared length of an object's class name disagrees with the
+    /// position of its closing quote. The position points at the length.
+    StringLengthMismatch {
+        declared: u32,
+        found: u32,
+        position: usize,
+    },
+    /// An array key or property name is neither an integer nor a string.
+    InvalidKey {
+        kind: PhpTokenKind,
+        position: usize,
+    },
+    /// The contents of a string expected to hold PHP serialized data, like
+    /// the outer string of double serialized data, don't start with a value.
+    /// The position points at the contents.
+    NotSerialized {
+        position: usize,
+    },
+    /// A top level scalar ends right where its final `;` is expected, like
+    /// `i:42`. Only reported as a warning by
+    /// [`PhpParser::parse_lenient`](crate::PhpParser::parse_lenient), which
+    /// keeps the value. The position points at the end of the input.
+    MissingTerminator {
+        position: usize,
+    },
+    /// The igbinary header declares a format version other than 1 or 2.
+    UnsupportedIgbinaryVersion {
+        version: u32,
+    },
+    /// An igbinary type byte that is unknown or not supported by the reader.
+    UnsupportedIgbinaryType {
+        found: u8,
+        position: usize,
+    },
+    /// An igbinary string back-reference to a string that has not been seen.
+    InvalidStringId {
+        id: u32,
+        position: usize,
+    },
+    /// An enum value (`E:`) lacks the colon between its class and case.
+    InvalidEnum {
+        position: usize,
+    },
+    /// An object of a class that can't be represented, like the `Closure`
+    /// placeholder, or that was rejected by
+    /// [`PhpDeserializer::allow_class`](crate::PhpDeserializer::allow_class),
+    /// was found where a value was expected.
+    UnsupportedClass {
+        class: String,
         position: usize,
     },
     /// An error produced while serializing a value to PHP serialized data.
@@ -83,7 +373,26 @@ impl std::error::Error for Error {
             | ErrorKind::Deserialize { .. }
             | ErrorKind::StringTooLong { .. }
             | ErrorKind::InvalidNumber { .. }
-            | ErrorKind::Overflow { .. }
+            | ErrorKind::InvalidBoolean { .. }
+            | ErrorKind::TrailingData { .. }
+            | ErrorKind::LengthOverflow { .. }
+            | ErrorKind::IntegerOverflow { .. }
+            | ErrorKind::CountMismatch { .. }
+            | ErrorKind::DepthLimitExceeded { .. }
+            | ErrorKind::ResourceLimit { .. }
+            | ErrorKind::UnsupportedReference { .. }
+            | ErrorKind::TruncatedString { .. }
+            | ErrorKind::UnsupportedIgbinaryVersion { .. }
+            | ErrorKind::UnsupportedIgbinaryType { .. }
+            | ErrorKind::InvalidStringId { .. }
+            | ErrorKind::UnsupportedClass { .. }
+            | ErrorKind::InvalidReference { .. }
+            | ErrorKind::ForwardReference { .. }
+            | ErrorKind::InvalidEnum { .. }
+            | ErrorKind::StringLengthMismatch { .. }
+            | ErrorKind::InvalidKey { .. }
+            | ErrorKind::NotSerialized { .. }
+            | ErrorKind::MissingTerminator { .. }
             | ErrorKind::Serialize { .. }
             | ErrorKind::MissingQuotes { .. } => None,
             ErrorKind::Utf8(err) => Some(err),
@@ -145,7 +454,95 @@ impl std::fmt::Display for Error {
             ErrorKind::InvalidNumber { position } => {
                 write!(f, "Invalid number at position: {position}")
             }
-            ErrorKind::Overflow { position } => write!(f, "Overflow at position: {position}"),
+            ErrorKind::InvalidBoolean { position } => {
+                write!(f, "Invalid boolean at position: {position}")
+            }
+            ErrorKind::TrailingData { position } => {
+                write!(f, "Trailing data at position: {position}")
+            }
+            ErrorKind::LengthOverflow { position } => {
+                write!(f, "Length overflow at position: {position}")
+            }
+            ErrorKind::IntegerOverflow { position } => {
+                write!(f, "Integer overflow at position: {position}")
+            }
+            ErrorKind::CountMismatch {
+                declared,
+                found,
+                position,
+            } => write!(
+                f,
+                "Declared {declared} entries but found {found} at position: {position}"
+            ),
+            ErrorKind::DepthLimitExceeded { position } => {
+                write!(f, "Depth limit exceeded at position: {position}")
+            }
+            ErrorKind::ResourceLimit { limit, position } => {
+                write!(
+                    f,
+                    "Resource limit of {limit} bytes exceeded at position: {position}"
+                )
+            }
+            ErrorKind::UnsupportedReference { index, position } => write!(
+                f,
+                "Unsupported reference to value {index} at position: {position}"
+            ),
+            ErrorKind::InvalidReference { index, position } => {
+                write!(
+                    f,
+                    "Invalid reference to value {index} at position: {position}"
+                )
+            }
+            ErrorKind::ForwardReference { index, position } => {
+                write!(
+                    f,
+                    "Reference to value {index} that hasn't been parsed yet at position: {position}"
+                )
+            }
+            ErrorKind::TruncatedString { declared, position } => write!(
+                f,
+                "Input ends before the declared {declared} byte string at position: {position}"
+            ),
+            ErrorKind::StringLengthMismatch {
+                declared,
+                found,
+                position,
+            } => write!(
+                f,
+                "Declared a {declared} byte class name but found {found} bytes at position: {position}"
+            ),
+            ErrorKind::InvalidKey { kind, position } => {
+                let kind = kind.name();
+                write!(
+                    f,
+                    "Expected an integer or string key, found {kind} at position: {position}"
+                )
+            }
+            ErrorKind::NotSerialized { position } => {
+                write!(
+                    f,
+                    "String does not contain PHP serialized data at position: {position}"
+                )
+            }
+            ErrorKind::MissingTerminator { position } => {
+                write!(f, "Missing ';' at end of input at position: {position}")
+            }
+            ErrorKind::UnsupportedIgbinaryVersion { version } => {
+                write!(f, "Unsupported igbinary version: {version}")
+            }
+            ErrorKind::UnsupportedIgbinaryType { found, position } => write!(
+                f,
+                "Unsupported igbinary type 0x{found:02x} at position: {position}"
+            ),
+            ErrorKind::InvalidStringId { id, position } => {
+                write!(f, "Unknown string id {id} at position: {position}")
+            }
+            ErrorKind::InvalidEnum { position } => {
+                write!(f, "Invalid enum at position: {position}")
+            }
+            ErrorKind::UnsupportedClass { class, position } => {
+                write!(f, "Unsupported class {class} at position: {position}")
+            }
             ErrorKind::Utf8(err) => write!(f, "UTF-8 conversion error: {err}"),
             ErrorKind::Serialize { message } => write!(f, "Serialization error: {message}"),
             ErrorKind::Io(err) => write!(f, "I/O error: {err}"),
@@ -183,3 +580,95 @@ impl serde::ser::Error for Error {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_at_position() {
+        let error = Error::from(ErrorKind::Deserialize {
+            message: "bad".to_string(),
+            position: None,
+        });
+        assert_eq!(error.at_position(7).position(), Some(7));
+
+        let error = Error::from(ErrorKind::Deserialize {
+            message: "bad".to_string(),
+            position: Some(3),
+        });
+        assert_eq!(error.at_position(7).position(), Some(3));
+
+        let error = Error::from(ErrorKind::Eof).at_position(7);
+        assert_eq!(error.position(), None);
+    }
+
+    #[test]
+    fn test_is_parse_error() {
+        let parse = crate::PhpParser::new(b"a:1:{").read_token().unwrap_err();
+        assert!(parse.is_parse_error());
+        assert!(
+            Error::from(ErrorKind::UnexpectedByte {
+                found: b'x',
+                position: 0
+            })
+            .is_parse_error()
+        );
+
+        let custom = Error::from(ErrorKind::Deserialize {
+            message: "bad".to_string(),
+            position: None,
+        });
+        assert!(!custom.is_parse_error());
+        assert!(!Error::from(std::io::Error::other("boom")).is_parse_error());
+    }
+
+    #[test]
+    fn test_into_owned_report() {
+        let input = b"a:2:{i:0;s:3:\"abc\";\ni:1;d:1.5x;}".to_vec();
+        let error = Error::from(ErrorKind::MismatchByte {
+            expected: b';',
+            found: b'x',
+            position: 29,
+        });
+        let report = error.into_owned_report(&input);
+        drop(input);
+
+        assert!(matches!(
+            report.error.kind(),
+            ErrorKind::MismatchByte { position: 29, .. }
+        ));
+        assert_eq!(report.line, Some(2));
+        assert_eq!(report.column, Some(10));
+        assert_eq!(report.snippet, b"\"abc\";\ni:1;d:1.5x;}");
+        assert_eq!(report.snippet_offset, 16);
+        assert_eq!(report.snippet[report.snippet_offset], b'x');
+        assert_eq!(
+            report.to_string(),
+            r#"Expected byte ';', found 'x' at position: 29 (line 2, column 10) near "\"abc\";\ni:1;d:1.5x;}""#
+        );
+    }
+
+    #[test]
+    fn test_into_owned_report_without_position() {
+        let report = Error::from(ErrorKind::Eof).into_owned_report(b"i:1");
+        assert!(matches!(report.error.kind(), ErrorKind::Eof));
+        assert_eq!((report.line, report.column), (None, None));
+        assert!(report.snippet.is_empty());
+        assert_eq!(report.to_string(), Error::from(ErrorKind::Eof).to_string());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_custom_errors_are_not_parse_errors() {
+        use serde::Deserialize;
+
+        let mut deserializer = crate::PhpDeserializer::new(b"s:3:\"abc\";");
+        let error = bool::deserialize(&mut deserializer).unwrap_err();
+        assert!(!error.is_parse_error());
+
+        let mut deserializer = crate::PhpDeserializer::new(b"s:3:\"abc");
+        let error = String::deserialize(&mut deserializer).unwrap_err();
+        assert!(error.is_parse_error());
+    }
+}
@@ -0,0 +1,332 @@
+use crate::errors::{Error, ErrorKind};
+use crate::parser::{PhpBstr, PhpParser, PhpToken};
+
+/// A SAX-style callback interface for walking PHP serialized data.
+///
+/// Drive it with [`PhpParser::visit_events`], which takes care of the
+/// container nesting bookkeeping: handlers are told when arrays and objects
+/// start and end, and which tokens are keys versus values. Every method has a
+/// default no-op implementation so handlers only need to implement the events
+/// they care about.
+///
+/// The example below pretty prints the structure of a payload:
+///
+/// ```rust
+/// use phpserz::{Error, EventHandler, PhpBstr, PhpParser, PhpToken};
+///
+/// #[derive(Default)]
+/// struct Printer {
+///     out: String,
+///     depth: usize,
+/// }
+///
+/// impl Printer {
+///     fn line(&mut self, text: &str) {
+///         self.out.push_str(&"  ".repeat(self.depth));
+///         self.out.push_str(text);
+///         self.out.push('\n');
+///     }
+/// }
+///
+/// impl<'a> EventHandler<'a> for Printer {
+///     fn on_scalar(&mut self, token: PhpToken<'a>) -> Result<(), Error> {
+///         self.line(&format!("{token:?}"));
+///         Ok(())
+///     }
+///
+///     fn on_key(&mut self, key: PhpToken<'a>) -> Result<(), Error> {
+///         self.line(&format!("{key:?} =>"));
+///         Ok(())
+///     }
+///
+///     fn on_array_start(&mut self, elements: u32) -> Result<(), Error> {
+///         self.line(&format!("array({elements}) {{"));
+///         self.depth += 1;
+///         Ok(())
+///     }
+///
+///     fn on_array_end(&mut self) -> Result<(), Error> {
+///         self.depth -= 1;
+///         self.line("}");
+///         Ok(())
+///     }
+///
+///     fn on_object_start(&mut self, class: PhpBstr<'a>, properties: u32) -> Result<(), Error> {
+///         let class = String::from_utf8_lossy(class.as_bytes());
+///         self.line(&format!("{class}({properties}) {{"));
+///         self.depth += 1;
+///         Ok(())
+///     }
+///
+///     fn on_object_end(&mut self) -> Result<(), Error> {
+///         self.depth -= 1;
+///         self.line("}");
+///         Ok(())
+///     }
+/// }
+///
+/// let mut printer = Printer::default();
+/// let parser = PhpParser::new(b"O:3:\"Foo\":1:{s:3:\"bar\";a:1:{i:0;b:1;}}");
+/// parser.visit_events(&mut printer).unwrap();
+/// assert_eq!(
+///     printer.out,
+///     r#"Foo(1) {
+///   String(PhpBstr { data: [98, 97, 114] }) =>
+///   array(1) {
+///     Integer(0) =>
+///     Boolean(true)
+///   }
+/// }
+/// "#
+/// );
+/// ```
+pub trait EventHandler<'a> {
+    /// A value that isn't an array or object: null, boolean, integer, float,
+    /// string, custom object, enum, or reference.
+    fn on_scalar(&mut self, token: PhpToken<'a>) -> Result<(), Error> {
+        let _ = token;
+        Ok(())
+    }
+
+    /// The key of the next entry in an array or object.
+    fn on_key(&mut self, key: PhpToken<'a>) -> Result<(), Error> {
+        let _ = key;
+        Ok(())
+    }
+
+    /// The start of an array with the declared number of elements.
+    fn on_array_start(&mut self, elements: u32) -> Result<(), Error> {
+        let _ = elements;
+        Ok(())
+    }
+
+    /// The end of the most recently started array.
+    fn on_array_end(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The start of an object with its class and declared number of properties.
+    fn on_object_start(&mut self, class: PhpBstr<'a>, properties: u32) -> Result<(), Error> {
+        let _ = (class, properties);
+        Ok(())
+    }
+
+    /// The end of the most recently started object.
+    fn on_object_end(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a, H: EventHandler<'a> + ?Sized> EventHandler<'a> for &mut H {
+    fn on_scalar(&mut self, token: PhpToken<'a>) -> Result<(), Error> {
+        (**self).on_scalar(token)
+    }
+
+    fn on_key(&mut self, key: PhpToken<'a>) -> Result<(), Error> {
+        (**self).on_key(key)
+    }
+
+    fn on_array_start(&mut self, elements: u32) -> Result<(), Error> {
+        (**self).on_array_start(elements)
+    }
+
+    fn on_array_end(&mut self) -> Result<(), Error> {
+        (**self).on_array_end()
+    }
+
+    fn on_object_start(&mut self, class: PhpBstr<'a>, properties: u32) -> Result<(), Error> {
+        (**self).on_object_start(class, properties)
+    }
+
+    fn on_object_end(&mut self) -> Result<(), Error> {
+        (**self).on_object_end()
+    }
+}
+
+impl<'a> PhpParser<'a> {
+    /// Walk the remaining input, reporting each token to the event handler.
+    ///
+    /// Errors from the parser and the handler are returned immediately. Input
+    /// that ends while an array or object is still open is an error.
+    pub fn visit_events<H: EventHandler<'a>>(mut self, mut handler: H) -> Result<(), Error> {
+        // Whether each open container is an object, innermost last.
+        let mut stack = Vec::new();
+        let mut expect_key = false;
+
+        loop {
+            let position = self.position();
+            let Some(token) = self.next_token()? else {
+                if stack.is_empty() {
+                    return Ok(());
+                }
+                return Err(Error::from(ErrorKind::Eof));
+            };
+
+            match token {
+                PhpToken::End => {
+                    if !expect_key {
+                        return Err(Error::from(ErrorKind::UnexpectedByte {
+                            found: b'}',
+                            position,
+                        }));
+                    }
+
+                    match stack.pop() {
+                        Some(true) => handler.on_object_end()?,
+                        Some(false) => handler.on_array_end()?,
+                        None => unreachable!("keys are only expected inside containers"),
+                    }
+                    expect_key = !stack.is_empty();
+                }
+                token if expect_key => {
+                    handler.on_key(token)?;
+                    expect_key = false;
+                }
+                PhpToken::Array { elements } => {
+                    handler.on_array_start(elements)?;
+                    stack.push(false);
+                    expect_key = true;
+                }
+                PhpToken::Object { class, properties } => {
+                    handler.on_object_start(class, properties)?;
+                    stack.push(true);
+                    expect_key = true;
+                }
+                token => {
+                    handler.on_scalar(token)?;
+                    expect_key = !stack.is_empty();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Event<'a> {
+        Scalar(PhpToken<'a>),
+        Key(PhpToken<'a>),
+        ArrayStart(u32),
+        ArrayEnd,
+        ObjectStart(PhpBstr<'a>, u32),
+        ObjectEnd,
+    }
+
+    #[derive(Default)]
+    struct Recorder<'a> {
+        events: Vec<Event<'a>>,
+    }
+
+    impl<'a> EventHandler<'a> for Recorder<'a> {
+        fn on_scalar(&mut self, token: PhpToken<'a>) -> Result<(), Error> {
+            self.events.push(Event::Scalar(token));
+            Ok(())
+        }
+
+        fn on_key(&mut self, key: PhpToken<'a>) -> Result<(), Error> {
+            self.events.push(Event::Key(key));
+            Ok(())
+        }
+
+        fn on_array_start(&mut self, elements: u32) -> Result<(), Error> {
+            self.events.push(Event::ArrayStart(elements));
+            Ok(())
+        }
+
+        fn on_array_end(&mut self) -> Result<(), Error> {
+            self.events.push(Event::ArrayEnd);
+            Ok(())
+        }
+
+        fn on_object_start(&mut self, class: PhpBstr<'a>, properties: u32) -> Result<(), Error> {
+            self.events.push(Event::ObjectStart(class, properties));
+            Ok(())
+        }
+
+        fn on_object_end(&mut self) -> Result<(), Error> {
+            self.events.push(Event::ObjectEnd);
+            Ok(())
+        }
+    }
+
+    fn record(input: &[u8]) -> Result<Vec<Event<'_>>, Error> {
+        let mut recorder = Recorder::default();
+        PhpParser::new(input).visit_events(&mut recorder)?;
+        Ok(recorder.events)
+    }
+
+    #[test]
+    fn test_events_nested() {
+        let input = b"a:2:{i:0;O:3:\"Foo\":1:{s:3:\"bar\";a:0:{}}s:1:\"k\";N;}";
+        assert_eq!(
+            record(input).unwrap(),
+            vec![
+                Event::ArrayStart(2),
+                Event::Key(PhpToken::Integer(0)),
+                Event::ObjectStart(PhpBstr::new(b"Foo"), 1),
+                Event::Key(PhpToken::String(PhpBstr::new(b"bar"))),
+                Event::ArrayStart(0),
+                Event::ArrayEnd,
+                Event::ObjectEnd,
+                Event::Key(PhpToken::String(PhpBstr::new(b"k"))),
+                Event::Scalar(PhpToken::Null),
+                Event::ArrayEnd,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_top_level_scalars() {
+        assert_eq!(
+            record(b"i:1;s:1:\"a\";").unwrap(),
+            vec![
+                Event::Scalar(PhpToken::Integer(1)),
+                Event::Scalar(PhpToken::String(PhpBstr::new(b"a"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_events_unclosed_container() {
+        let error = record(b"a:1:{i:0;i:1;").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Eof));
+    }
+
+    #[test]
+    fn test_events_unexpected_end() {
+        let error = record(b"a:1:{i:0;}N;").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'}',
+                position: 9
+            }
+        ));
+
+        let error = record(b"}").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'}',
+                position: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_events_handler_error_stops_walk() {
+        struct Failing;
+
+        impl EventHandler<'_> for Failing {
+            fn on_array_start(&mut self, _elements: u32) -> Result<(), Error> {
+                Err(Error::from(ErrorKind::Eof))
+            }
+        }
+
+        let result = PhpParser::new(b"a:0:{}").visit_events(Failing);
+        assert!(matches!(result.unwrap_err().kind(), ErrorKind::Eof));
+    }
+}
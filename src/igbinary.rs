@@ -0,0 +1,593 @@
+use crate::errors::{Error, ErrorKind};
+use crate::parser::{PhpBstr, PhpRef, PhpReferenceKind, PhpToken};
+use crate::value::{PhpValue, read_value};
+
+const TYPE_NULL: u8 = 0x00;
+const TYPE_REF8: u8 = 0x01;
+const TYPE_REF16: u8 = 0x02;
+const TYPE_REF32: u8 = 0x03;
+const TYPE_BOOL_FALSE: u8 = 0x04;
+const TYPE_BOOL_TRUE: u8 = 0x05;
+const TYPE_LONG8P: u8 = 0x06;
+const TYPE_LONG8N: u8 = 0x07;
+const TYPE_LONG16P: u8 = 0x08;
+const TYPE_LONG16N: u8 = 0x09;
+const TYPE_LONG32P: u8 = 0x0a;
+const TYPE_LONG32N: u8 = 0x0b;
+const TYPE_DOUBLE: u8 = 0x0c;
+const TYPE_STRING_EMPTY: u8 = 0x0d;
+const TYPE_STRING_ID8: u8 = 0x0e;
+const TYPE_STRING_ID16: u8 = 0x0f;
+const TYPE_STRING_ID32: u8 = 0x10;
+const TYPE_STRING8: u8 = 0x11;
+const TYPE_STRING16: u8 = 0x12;
+const TYPE_STRING32: u8 = 0x13;
+const TYPE_ARRAY8: u8 = 0x14;
+const TYPE_ARRAY16: u8 = 0x15;
+const TYPE_ARRAY32: u8 = 0x16;
+const TYPE_OBJECT8: u8 = 0x17;
+const TYPE_OBJECT16: u8 = 0x18;
+const TYPE_OBJECT32: u8 = 0x19;
+const TYPE_OBJECT_ID8: u8 = 0x1a;
+const TYPE_OBJECT_ID16: u8 = 0x1b;
+const TYPE_OBJECT_ID32: u8 = 0x1c;
+const TYPE_OBJECT_SER8: u8 = 0x1d;
+const TYPE_OBJECT_SER16: u8 = 0x1e;
+const TYPE_OBJECT_SER32: u8 = 0x1f;
+const TYPE_LONG64P: u8 = 0x20;
+const TYPE_LONG64N: u8 = 0x21;
+const TYPE_OBJREF8: u8 = 0x22;
+const TYPE_OBJREF16: u8 = 0x23;
+const TYPE_OBJREF32: u8 = 0x24;
+const TYPE_REF: u8 = 0x25;
+
+/// A parser for the [igbinary](https://github.com/igbinary/igbinary) binary
+/// serialization format.
+///
+/// The parser yields the same [`PhpToken`] stream as [`PhpParser`](crate::PhpParser),
+/// so code written against the tokens works with either format. igbinary
+/// containers are length prefixed rather than brace delimited, so the parser
+/// synthesizes [`PhpToken::End`] once all of a container's entries are read.
+///
+/// Reference tokens carry igbinary's own value numbering, shifted to start at
+/// 1 like [`PhpRef`] ids, which counts differently from PHP's `serialize()`.
+/// Types that aren't supported (for instance 64-bit string lengths) are
+/// reported with [`ErrorKind::UnsupportedIgbinaryType`].
+///
+/// ```rust
+/// use phpserz::{IgbinaryParser, PhpBstr, PhpToken};
+///
+/// // igbinary_serialize(["a" => 1])
+/// let data = b"\x00\x00\x00\x02\x14\x01\x11\x01a\x06\x01";
+/// let mut parser = IgbinaryParser::new(data).unwrap();
+/// assert_eq!(parser.read_token().unwrap(), PhpToken::Array { elements: 1 });
+/// assert_eq!(parser.read_token().unwrap(), PhpToken::String(PhpBstr::new(b"a")));
+/// assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(1));
+/// assert_eq!(parser.read_token().unwrap(), PhpToken::End);
+/// assert_eq!(parser.next_token().unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct IgbinaryParser<'a> {
+    data: &'a [u8],
+    original_len: usize,
+
+    // Strings (including class names) in the order they were first seen, so
+    // later occurrences can refer back to them by index.
+    strings: Vec<&'a [u8]>,
+
+    // The number of keys and values left to read in each open container,
+    // innermost last.
+    remaining: Vec<u64>,
+}
+
+impl<'a> IgbinaryParser<'a> {
+    /// Creates a parser after validating the igbinary header.
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let (header, rest) = data.split_first_chunk::<4>().ok_or(ErrorKind::Eof)?;
+        let version = u32::from_be_bytes(*header);
+        if version != 1 && version != 2 {
+            return Err(Error::from(ErrorKind::UnsupportedIgbinaryVersion {
+                version,
+            }));
+        }
+
+        Ok(Self {
+            data: rest,
+            original_len: data.len(),
+            strings: Vec::new(),
+            remaining: Vec::new(),
+        })
+    }
+
+    /// Returns the current byte offset from the start of the input.
+    #[inline]
+    pub fn position(&self) -> usize {
+        self.original_len - self.data.len()
+    }
+
+    /// Reads the next token, and will error if the end of the input is reached.
+    #[inline]
+    pub fn read_token(&mut self) -> Result<PhpToken<'a>, Error> {
+        Ok(self.next_token()?.ok_or(ErrorKind::Eof)?)
+    }
+
+    /// Reads the next token, returning `None` at the end of the input.
+    ///
+    /// Input that ends while an array or object is still open is an error.
+    pub fn next_token(&mut self) -> Result<Option<PhpToken<'a>>, Error> {
+        if self.remaining.last() == Some(&0) {
+            self.remaining.pop();
+            return Ok(Some(PhpToken::End));
+        }
+
+        if self.data.is_empty() {
+            if self.remaining.is_empty() {
+                return Ok(None);
+            }
+            return Err(Error::from(ErrorKind::Eof));
+        }
+
+        let token = self.parse_token()?;
+        if let Some(remaining) = self.remaining.last_mut() {
+            *remaining -= 1;
+        }
+
+        match token {
+            PhpToken::Array { elements } => self.remaining.push(u64::from(elements) * 2),
+            PhpToken::Object { properties, .. } => self.remaining.push(u64::from(properties) * 2),
+            _ => {}
+        }
+
+        Ok(Some(token))
+    }
+
+    /// Reads the next complete value.
+    ///
+    /// Unlike values deserialized through serde, objects keep their class
+    /// name. References and custom serialized objects have no [`PhpValue`]
+    /// representation and are reported as errors.
+    ///
+    /// ```rust
+    /// use phpserz::{IgbinaryParser, PhpKey, PhpValue};
+    ///
+    /// // igbinary_serialize([1, "a" => "foo"])
+    /// let data = b"\x00\x00\x00\x02\x14\x02\x06\x00\x06\x01\x11\x01a\x11\x03foo";
+    /// let value = IgbinaryParser::new(data).unwrap().read_value().unwrap();
+    /// assert_eq!(
+    ///     value,
+    ///     PhpValue::Array(vec![
+    ///         (PhpKey::Int(0), PhpValue::Int(1)),
+    ///         (PhpKey::Str(b"a".to_vec()), PhpValue::String(b"foo".to_vec())),
+    ///     ])
+    /// );
+    /// ```
+    pub fn read_value(&mut self) -> Result<PhpValue, Error> {
+        read_value(|| Ok((self.position(), self.read_token()?)))
+    }
+
+    fn parse_token(&mut self) -> Result<PhpToken<'a>, Error> {
+        let mut position = self.position();
+        let mut kind = self.read_u8()?;
+
+        // A reference marker only flags that the following value is the
+        // target of a PHP reference.
+        while kind == TYPE_REF {
+            position = self.position();
+            kind = self.read_u8()?;
+        }
+
+        let token = match kind {
+            TYPE_NULL => PhpToken::Null,
+            TYPE_BOOL_FALSE => PhpToken::Boolean(false),
+            TYPE_BOOL_TRUE => PhpToken::Boolean(true),
+            TYPE_LONG8P => PhpToken::Integer(i64::from(self.read_u8()?)),
+            TYPE_LONG16P => PhpToken::Integer(i64::from(self.read_u16()?)),
+            TYPE_LONG32P => PhpToken::Integer(i64::from(self.read_u32()?)),
+            TYPE_LONG64P => {
+                let value = i64::try_from(self.read_u64()?)
+                    .map_err(|_| ErrorKind::IntegerOverflow { position })?;
+                PhpToken::Integer(value)
+            }
+            TYPE_LONG8N => PhpToken::Integer(-i64::from(self.read_u8()?)),
+            TYPE_LONG16N => PhpToken::Integer(-i64::from(self.read_u16()?)),
+            TYPE_LONG32N => PhpToken::Integer(-i64::from(self.read_u32()?)),
+            TYPE_LONG64N => {
+                let value = 0i64
+                    .checked_sub_unsigned(self.read_u64()?)
+                    .ok_or(ErrorKind::IntegerOverflow { position })?;
+                PhpToken::Integer(value)
+            }
+            TYPE_DOUBLE => PhpToken::Float(f64::from_bits(self.read_u64()?)),
+            TYPE_STRING_EMPTY => PhpToken::String(PhpBstr::new(b"")),
+            TYPE_STRING_ID8 => {
+                let id = u32::from(self.read_u8()?);
+                PhpToken::String(PhpBstr::new(self.string_id(id, position)?))
+            }
+            TYPE_STRING_ID16 => {
+                let id = u32::from(self.read_u16()?);
+                PhpToken::String(PhpBstr::new(self.string_id(id, position)?))
+            }
+            TYPE_STRING_ID32 => {
+                let id = self.read_u32()?;
+                PhpToken::String(PhpBstr::new(self.string_id(id, position)?))
+            }
+            TYPE_STRING8 => {
+                let len = u32::from(self.read_u8()?);
+                PhpToken::String(self.read_string(len)?)
+            }
+            TYPE_STRING16 => {
+                let len = u32::from(self.read_u16()?);
+                PhpToken::String(self.read_string(len)?)
+            }
+            TYPE_STRING32 => {
+                let len = self.read_u32()?;
+                PhpToken::String(self.read_string(len)?)
+            }
+            TYPE_ARRAY8 => PhpToken::Array {
+                elements: u32::from(self.read_u8()?),
+            },
+            TYPE_ARRAY16 => PhpToken::Array {
+                elements: u32::from(self.read_u16()?),
+            },
+            TYPE_ARRAY32 => PhpToken::Array {
+                elements: self.read_u32()?,
+            },
+            TYPE_OBJECT8 => {
+                let len = u32::from(self.read_u8()?);
+                let class = self.read_string(len)?;
+                self.read_object_body(class)?
+            }
+            TYPE_OBJECT16 => {
+                let len = u32::from(self.read_u16()?);
+                let class = self.read_string(len)?;
+                self.read_object_body(class)?
+            }
+            TYPE_OBJECT32 => {
+                let len = self.read_u32()?;
+                let class = self.read_string(len)?;
+                self.read_object_body(class)?
+            }
+            TYPE_OBJECT_ID8 => {
+                let id = u32::from(self.read_u8()?);
+                let class = self.string_id(id, position)?;
+                self.read_object_body(PhpBstr::new(class))?
+            }
+            TYPE_OBJECT_ID16 => {
+                let id = u32::from(self.read_u16()?);
+                let class = self.string_id(id, position)?;
+                self.read_object_body(PhpBstr::new(class))?
+            }
+            TYPE_OBJECT_ID32 => {
+                let id = self.read_u32()?;
+                let class = self.string_id(id, position)?;
+                self.read_object_body(PhpBstr::new(class))?
+            }
+            TYPE_REF8 => reference(u32::from(self.read_u8()?), PhpReferenceKind::Alias),
+            TYPE_REF16 => reference(u32::from(self.read_u16()?), PhpReferenceKind::Alias),
+            TYPE_REF32 => reference(self.read_u32()?, PhpReferenceKind::Alias),
+            TYPE_OBJREF8 => reference(u32::from(self.read_u8()?), PhpReferenceKind::Repeated),
+            TYPE_OBJREF16 => reference(u32::from(self.read_u16()?), PhpReferenceKind::Repeated),
+            TYPE_OBJREF32 => reference(self.read_u32()?, PhpReferenceKind::Repeated),
+            found => {
+                return Err(Error::from(ErrorKind::UnsupportedIgbinaryType {
+                    found,
+                    position,
+                }));
+            }
+        };
+
+        Ok(token)
+    }
+
+    /// After an object's class name comes either its properties, encoded as an
+    /// array header, or a custom serialization payload.
+    fn read_object_body(&mut self, class: PhpBstr<'a>) -> Result<PhpToken<'a>, Error> {
+        let position = self.position();
+        let properties = match self.read_u8()? {
+            TYPE_ARRAY8 => u32::from(self.read_u8()?),
+            TYPE_ARRAY16 => u32::from(self.read_u16()?),
+            TYPE_ARRAY32 => self.read_u32()?,
+            TYPE_OBJECT_SER8 => {
+                let len = u32::from(self.read_u8()?);
+                let payload = PhpBstr::new(self.read_bytes(len)?);
+                return Ok(PhpToken::CustomObject { class, payload });
+            }
+            TYPE_OBJECT_SER16 => {
+                let len = u32::from(self.read_u16()?);
+                let payload = PhpBstr::new(self.read_bytes(len)?);
+                return Ok(PhpToken::CustomObject { class, payload });
+            }
+            TYPE_OBJECT_SER32 => {
+                let len = self.read_u32()?;
+                let payload = PhpBstr::new(self.read_bytes(len)?);
+                return Ok(PhpToken::CustomObject { class, payload });
+            }
+            found => {
+                return Err(Error::from(ErrorKind::UnsupportedIgbinaryType {
+                    found,
+                    position,
+                }));
+            }
+        };
+
+        Ok(PhpToken::Object { class, properties })
+    }
+
+    fn string_id(&self, id: u32, position: usize) -> Result<&'a [u8], Error> {
+        let index = usize::try_from(id).unwrap_or(usize::MAX);
+        self.strings
+            .get(index)
+            .copied()
+            .ok_or_else(|| Error::from(ErrorKind::InvalidStringId { id, position }))
+    }
+
+    /// Reads a string of the given length and records it for later string
+    /// id references.
+    fn read_string(&mut self, len: u32) -> Result<PhpBstr<'a>, Error> {
+        let data = self.read_bytes(len)?;
+        self.strings.push(data);
+        Ok(PhpBstr::new(data))
+    }
+
+    fn read_bytes(&mut self, len: u32) -> Result<&'a [u8], Error> {
+        let position = self.position();
+        let len_usize = usize::try_from(len).unwrap_or(usize::MAX);
+        if len_usize > self.data.len() {
+            return Err(Error::from(ErrorKind::TruncatedString {
+                declared: len,
+                position,
+            }));
+        }
+
+        let (data, rest) = self.data.split_at(len_usize);
+        self.data = rest;
+        Ok(data)
+    }
+
+    #[inline]
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        let (head, rest) = self.data.split_first_chunk::<N>().ok_or(ErrorKind::Eof)?;
+        self.data = rest;
+        Ok(*head)
+    }
+
+    #[inline]
+    fn read_u8(&mut self) -> Result<u8, Error> {
+        self.read_array::<1>().map(|[x]| x)
+    }
+
+    #[inline]
+    fn read_u16(&mut self) -> Result<u16, Error> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    #[inline]
+    fn read_u32(&mut self) -> Result<u32, Error> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    #[inline]
+    fn read_u64(&mut self) -> Result<u64, Error> {
+        self.read_array().map(u64::from_be_bytes)
+    }
+}
+
+#[inline]
+fn reference<'a>(id: u32, kind: PhpReferenceKind) -> PhpToken<'a> {
+    let id = PhpRef::new(u64::from(id) + 1).expect("shifted id is at least 1");
+    PhpToken::Reference { id, kind }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::PhpKey;
+    use rstest::rstest;
+
+    fn tokens(data: &[u8]) -> Result<Vec<PhpToken<'_>>, Error> {
+        let mut parser = IgbinaryParser::new(data)?;
+        let mut result = Vec::new();
+        while let Some(token) = parser.next_token()? {
+            result.push(token);
+        }
+        Ok(result)
+    }
+
+    #[rstest]
+    #[case(b"\x00", PhpToken::Null)]
+    #[case(b"\x04", PhpToken::Boolean(false))]
+    #[case(b"\x05", PhpToken::Boolean(true))]
+    #[case(b"\x06\x2a", PhpToken::Integer(42))]
+    #[case(b"\x07\x05", PhpToken::Integer(-5))]
+    #[case(b"\x08\x01\x00", PhpToken::Integer(256))]
+    #[case(b"\x09\x01\x00", PhpToken::Integer(-256))]
+    #[case(b"\x0a\x00\x01\x00\x00", PhpToken::Integer(65536))]
+    #[case(b"\x0b\x00\x01\x00\x00", PhpToken::Integer(-65536))]
+    #[case(b"\x20\x7f\xff\xff\xff\xff\xff\xff\xff", PhpToken::Integer(i64::MAX))]
+    #[case(b"\x21\x80\x00\x00\x00\x00\x00\x00\x00", PhpToken::Integer(i64::MIN))]
+    #[case(b"\x0c\x3f\xf8\x00\x00\x00\x00\x00\x00", PhpToken::Float(1.5))]
+    #[case(b"\x0d", PhpToken::String(PhpBstr::new(b"")))]
+    #[case(b"\x11\x03foo", PhpToken::String(PhpBstr::new(b"foo")))]
+    #[case(b"\x12\x00\x03foo", PhpToken::String(PhpBstr::new(b"foo")))]
+    #[case(b"\x13\x00\x00\x00\x03foo", PhpToken::String(PhpBstr::new(b"foo")))]
+    #[case(b"\x25\x06\x01", PhpToken::Integer(1))]
+    fn test_scalars(#[case] body: &[u8], #[case] expected: PhpToken) {
+        let data = [b"\x00\x00\x00\x02", body].concat();
+        assert_eq!(tokens(&data).unwrap(), vec![expected]);
+    }
+
+    #[test]
+    fn test_string_ids() {
+        // igbinary_serialize(["a" => "foo", "b" => "foo", "foo" => 1])
+        let data = b"\x00\x00\x00\x02\x14\x03\x11\x01a\x11\x03foo\x11\x01b\x0e\x01\x0e\x01\x06\x01";
+        assert_eq!(
+            tokens(data).unwrap(),
+            vec![
+                PhpToken::Array { elements: 3 },
+                PhpToken::String(PhpBstr::new(b"a")),
+                PhpToken::String(PhpBstr::new(b"foo")),
+                PhpToken::String(PhpBstr::new(b"b")),
+                PhpToken::String(PhpBstr::new(b"foo")),
+                PhpToken::String(PhpBstr::new(b"foo")),
+                PhpToken::Integer(1),
+                PhpToken::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_objects() {
+        // Two stdClass objects, the second referring to the class name by id,
+        // followed by a custom serialized object.
+        let data = b"\x00\x00\x00\x02\x14\x03\x06\x00\x17\x08stdClass\x14\x01\x11\x01x\x06\x01\x06\x01\x1a\x00\x14\x00\x06\x02\x17\x03Foo\x1d\x02xy";
+        assert_eq!(
+            tokens(data).unwrap(),
+            vec![
+                PhpToken::Array { elements: 3 },
+                PhpToken::Integer(0),
+                PhpToken::Object {
+                    class: PhpBstr::new(b"stdClass"),
+                    properties: 1
+                },
+                PhpToken::String(PhpBstr::new(b"x")),
+                PhpToken::Integer(1),
+                PhpToken::End,
+                PhpToken::Integer(1),
+                PhpToken::Object {
+                    class: PhpBstr::new(b"stdClass"),
+                    properties: 0
+                },
+                PhpToken::End,
+                PhpToken::Integer(2),
+                PhpToken::CustomObject {
+                    class: PhpBstr::new(b"Foo"),
+                    payload: PhpBstr::new(b"xy")
+                },
+                PhpToken::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_references() {
+        let data = b"\x00\x00\x00\x02\x14\x02\x06\x00\x22\x00\x06\x01\x01\x00";
+        assert_eq!(
+            tokens(data).unwrap(),
+            vec![
+                PhpToken::Array { elements: 2 },
+                PhpToken::Integer(0),
+                PhpToken::Reference {
+                    id: PhpRef::new(1).unwrap(),
+                    kind: PhpReferenceKind::Repeated
+                },
+                PhpToken::Integer(1),
+                PhpToken::Reference {
+                    id: PhpRef::new(1).unwrap(),
+                    kind: PhpReferenceKind::Alias
+                },
+                PhpToken::End,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_value_object() {
+        let data = b"\x00\x00\x00\x02\x17\x03Foo\x14\x02\x11\x01a\x14\x01\x06\x00\x00\x06\x07\x0d";
+        let value = IgbinaryParser::new(data).unwrap().read_value().unwrap();
+        assert_eq!(
+            value,
+            PhpValue::Object {
+                class: b"Foo".to_vec(),
+                properties: vec![
+                    (
+                        b"a".to_vec(),
+                        PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Null)])
+                    ),
+                    (b"7".to_vec(), PhpValue::String(Vec::new())),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_read_value_depth_limit() {
+        let mut data = b"\x00\x00\x00\x02".to_vec();
+        data.extend(b"\x14\x01\x06\x00".repeat(100_000));
+        data.push(0x00);
+        let error = IgbinaryParser::new(&data)
+            .unwrap()
+            .read_value()
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::DepthLimitExceeded { position } if *position == 4 + 128 * 4
+        ));
+    }
+
+    #[test]
+    fn test_read_value_reference() {
+        let data = b"\x00\x00\x00\x02\x14\x01\x06\x00\x22\x00";
+        let error = IgbinaryParser::new(data).unwrap().read_value().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedReference {
+                index: 1,
+                position: 8
+            }
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let error = IgbinaryParser::new(b"\x00\x00\x00\x03\x00").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedIgbinaryVersion { version: 3 }
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_type() {
+        let error = tokens(b"\x00\x00\x00\x02\x14\x01\x06\x00\x26").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedIgbinaryType {
+                found: 0x26,
+                position: 8
+            }
+        ));
+    }
+
+    #[test]
+    fn test_invalid_string_id() {
+        let error = tokens(b"\x00\x00\x00\x02\x0e\x00").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::InvalidStringId { id: 0, position: 4 }
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let error = tokens(b"\x00\x00\x00\x02\x11\x05ab").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::TruncatedString {
+                declared: 5,
+                position: 6
+            }
+        ));
+
+        let error = tokens(b"\x00\x00\x00\x02\x14\x02\x06\x00\x00").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Eof));
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let error = tokens(b"\x00\x00\x00\x02\x20\x80\x00\x00\x00\x00\x00\x00\x00").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::IntegerOverflow { position: 4 }
+        ));
+    }
+}
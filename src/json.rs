@@ -0,0 +1,364 @@
+use crate::errors::{Error, ErrorKind};
+use crate::parser::{DEFAULT_MAX_DEPTH, PhpBstr, PhpParser, PhpToken};
+use std::collections::HashSet;
+use std::io::Write;
+
+/// Output is buffered while an array that may still turn out not to be a
+/// list is open, and otherwise written once it reaches this size.
+const FLUSH_LEN: usize = 8 * 1024;
+
+struct Frame {
+    /// Whether the keys so far have been `0..n` in order, so the array is
+    /// written as a JSON array.
+    list: bool,
+    object: bool,
+    first: bool,
+    expect_key: bool,
+
+    /// Where the opening bracket of a list is in the buffer, and where each
+    /// of its values starts, so it can be rewritten as a JSON object.
+    start: usize,
+    values: Vec<usize>,
+
+    /// The keys written so far, when not a list.
+    keys: HashSet<Vec<u8>>,
+}
+
+/// Convert PHP serialized data to JSON, writing the output while the input is
+/// parsed instead of building an intermediate value.
+///
+/// Arrays whose keys are `0..n` in order become JSON arrays, and all other
+/// arrays become JSON objects with integer keys written as strings. Objects
+/// become JSON objects keyed by their demangled property names, and enum
+/// cases become the name of the case. Floats that JSON can't represent
+/// (infinities and NaN) are written as null.
+///
+/// Strings must be valid UTF-8, the input must hold a single value nested
+/// at most 128 levels deep, and keys that are the same once written, like
+/// the public and private property `a`, are reported as errors. So are
+/// references and custom serialized objects, as they have no JSON
+/// equivalent. The output of an array is held back until its keys show
+/// whether it is a list.
+///
+/// ```rust
+/// use phpserz::php_to_json_writer;
+/// let data = b"a:2:{s:4:\"tags\";a:2:{i:0;s:3:\"php\";i:1;s:4:\"rust\";}s:2:\"id\";i:7;}";
+/// let mut out = Vec::new();
+/// php_to_json_writer(data, &mut out).unwrap();
+/// assert_eq!(out, br#"{"tags":["php","rust"],"id":7}"#);
+/// ```
+pub fn php_to_json_writer<W: Write>(data: &[u8], mut out: W) -> Result<(), Error> {
+    let mut parser = PhpParser::new(data);
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut buf: Vec<u8> = Vec::new();
+    let mut lists = 0usize;
+    loop {
+        if lists == 0 && buf.len() >= FLUSH_LEN {
+            out.write_all(&buf)?;
+            buf.clear();
+        }
+
+        let (position, found) = parser.upcoming();
+        let token = parser.read_token()?;
+
+        if let Some(frame) = stack.last_mut().filter(|frame| frame.expect_key) {
+            if token == PhpToken::End {
+                if frame.list {
+                    lists -= 1;
+                }
+                buf.push(if frame.list { b']' } else { b'}' });
+                stack.pop();
+            } else {
+                frame.expect_key = false;
+                let key = match token {
+                    PhpToken::Integer(key)
+                        if frame.list && usize::try_from(key) == Ok(frame.values.len()) =>
+                    {
+                        if !frame.first {
+                            buf.push(b',');
+                        }
+                        frame.first = false;
+                        frame.values.push(buf.len());
+                        continue;
+                    }
+                    PhpToken::Integer(key) => key.to_string().into_bytes(),
+                    PhpToken::String(key) if frame.object => key.to_property().as_bytes().to_vec(),
+                    PhpToken::String(key) => key.as_bytes().to_vec(),
+                    _ => return Err(Error::from(ErrorKind::UnexpectedByte { found, position })),
+                };
+
+                if frame.list {
+                    lists -= 1;
+                    into_object(&mut buf, frame);
+                }
+
+                if frame.keys.contains(&key) {
+                    return Err(Error::from(ErrorKind::Deserialize {
+                        message: format!(
+                            "Duplicate JSON key \"{}\"",
+                            String::from_utf8_lossy(&key)
+                        ),
+                        position: Some(position),
+                    }));
+                }
+
+                if !frame.first {
+                    buf.push(b',');
+                }
+                frame.first = false;
+                write_string(&mut buf, PhpBstr::new(&key))?;
+                buf.push(b':');
+                frame.keys.insert(key);
+                continue;
+            }
+        } else {
+            match token {
+                PhpToken::Null => buf.extend_from_slice(b"null"),
+                PhpToken::Boolean(b) => buf.extend_from_slice(if b { b"true" } else { b"false" }),
+                PhpToken::Integer(i) => write!(buf, "{i}")?,
+                PhpToken::Float(f) if f.is_finite() => write!(buf, "{f}")?,
+                PhpToken::Float(_) => buf.extend_from_slice(b"null"),
+                PhpToken::String(s) => write_string(&mut buf, s)?,
+                PhpToken::Enum { case, .. } => write_string(&mut buf, case)?,
+                PhpToken::Array { .. } | PhpToken::Object { .. } => {
+                    if stack.len() >= DEFAULT_MAX_DEPTH {
+                        return Err(Error::from(ErrorKind::DepthLimitExceeded { position }));
+                    }
+
+                    let object = matches!(token, PhpToken::Object { .. });
+                    if !object {
+                        lists += 1;
+                    }
+                    stack.push(Frame {
+                        list: !object,
+                        object,
+                        first: true,
+                        expect_key: true,
+                        start: buf.len(),
+                        values: Vec::new(),
+                        keys: HashSet::new(),
+                    });
+                    buf.push(if object { b'{' } else { b'[' });
+                    continue;
+                }
+                PhpToken::End => {
+                    return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+                }
+                PhpToken::CustomObject { .. } => {
+                    return Err(Error::from(ErrorKind::Deserialize {
+                        message: "Custom serialized objects cannot be represented as JSON"
+                            .to_string(),
+                        position: Some(position),
+                    }));
+                }
+                PhpToken::Reference { id, .. } => {
+                    return Err(Error::from(ErrorKind::UnsupportedReference {
+                        index: id.index(),
+                        position,
+                    }));
+                }
+            }
+        }
+
+        match stack.last_mut() {
+            Some(frame) => frame.expect_key = true,
+            None => break,
+        }
+    }
+
+    let (position, _) = parser.upcoming();
+    if !parser.is_done() {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+
+    out.write_all(&buf)?;
+    Ok(())
+}
+
+/// Rewrite the list at the end of the buffer as a JSON object, keying each
+/// value by its index.
+fn into_object(buf: &mut Vec<u8>, frame: &mut Frame) {
+    let list = buf.split_off(frame.start);
+    let values = std::mem::take(&mut frame.values);
+    buf.push(b'{');
+    for (index, &start) in values.iter().enumerate() {
+        // Each value but the last is followed by a comma.
+        let end = values
+            .get(index + 1)
+            .map_or(frame.start + list.len(), |&next| next - 1);
+        if index > 0 {
+            buf.push(b',');
+        }
+
+        let key = index.to_string().into_bytes();
+        buf.push(b'"');
+        buf.extend_from_slice(&key);
+        buf.extend_from_slice(b"\":");
+        buf.extend_from_slice(&list[start - frame.start..end - frame.start]);
+        frame.keys.insert(key);
+    }
+    frame.list = false;
+}
+
+fn write_string<W: Write>(out: &mut W, s: PhpBstr<'_>) -> Result<(), Error> {
+    let s = s.to_str()?;
+    out.write_all(b"\"")?;
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    for (i, &b) in bytes.iter().enumerate() {
+        let escape: &[u8] = match b {
+            b'"' => b"\\\"",
+            b'\\' => b"\\\\",
+            b'\n' => b"\\n",
+            b'\r' => b"\\r",
+            b'\t' => b"\\t",
+            0x08 => b"\\b",
+            0x0c => b"\\f",
+            0x00..=0x1f => {
+                out.write_all(&bytes[start..i])?;
+                write!(out, "\\u{b:04x}")?;
+                start = i + 1;
+                continue;
+            }
+            _ => continue,
+        };
+
+        out.write_all(&bytes[start..i])?;
+        out.write_all(escape)?;
+        start = i + 1;
+    }
+
+    out.write_all(&bytes[start..])?;
+    out.write_all(b"\"")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn to_json(data: &[u8]) -> Result<String, Error> {
+        let mut out = Vec::new();
+        php_to_json_writer(data, &mut out)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[rstest]
+    #[case(b"N;", "null")]
+    #[case(b"b:0;", "false")]
+    #[case(b"i:-12;", "-12")]
+    #[case(b"d:1.5;", "1.5")]
+    #[case(b"d:INF;", "null")]
+    #[case(b"s:7:\"a\"b\\c\n\x01\";", r#""a\"b\\c\n\u0001""#)]
+    #[case(b"E:11:\"Suit:Hearts\";", r#""Hearts""#)]
+    #[case(b"a:0:{}", "[]")]
+    #[case(b"a:2:{i:0;i:1;i:1;a:1:{i:0;N;}}", "[1,[null]]")]
+    #[case(b"a:2:{i:1;i:1;i:0;i:2;}", r#"{"1":1,"0":2}"#)]
+    #[case(b"a:2:{i:0;b:1;s:1:\"a\";b:0;}", r#"{"0":true,"a":false}"#)]
+    #[case(
+        b"a:3:{i:0;a:1:{i:0;s:1:\"x\";}i:1;a:0:{}i:5;a:2:{i:0;N;i:2;N;}}",
+        r#"{"0":["x"],"1":[],"5":{"0":null,"2":null}}"#
+    )]
+    #[case(b"a:2:{i:-1;N;i:0;N;}", r#"{"-1":null,"0":null}"#)]
+    #[case(b"O:8:\"stdClass\":0:{}", "{}")]
+    #[case(
+        b"O:3:\"Foo\":3:{s:1:\"a\";i:1;s:4:\"\0*\0b\";a:0:{}s:6:\"\0Foo\0c\";N;}",
+        r#"{"a":1,"b":[],"c":null}"#
+    )]
+    fn test_php_to_json(#[case] data: &[u8], #[case] expected: &str) {
+        assert_eq!(to_json(data).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(b"a:2:{i:0;O:8:\"stdClass\":0:{}i:1;r:2;}")]
+    #[case(b"C:3:\"Foo\":1:{x}")]
+    #[case(b"s:1:\"\xff\";")]
+    #[case(b"i:1;i:2;")]
+    #[case(b"a:1:{i:0;")]
+    #[case(b"a:2:{i:0;N;i:0;N;}")]
+    #[case(b"a:3:{i:0;N;s:1:\"a\";N;i:0;N;}")]
+    #[case(b"a:2:{i:1;N;s:1:\"1\";N;}")]
+    #[case(b"O:3:\"Foo\":2:{s:1:\"a\";N;s:6:\"\0Foo\0a\";N;}")]
+    fn test_php_to_json_errors(#[case] data: &[u8]) {
+        assert!(to_json(data).is_err());
+    }
+
+    #[test]
+    fn test_php_to_json_duplicate_key() {
+        let data = b"O:3:\"Foo\":2:{s:4:\"\0*\0a\";N;s:1:\"a\";N;}";
+        let error = to_json(data).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, position: Some(26) } if message == "Duplicate JSON key \"a\""
+        ));
+    }
+
+    #[test]
+    fn test_php_to_json_depth_limit() {
+        let depth = 100_000;
+        let mut data = "a:1:{i:0;".repeat(depth).into_bytes();
+        data.extend_from_slice(b"N;");
+        data.extend(std::iter::repeat_n(b'}', depth));
+        assert!(matches!(
+            to_json(&data).unwrap_err().kind(),
+            ErrorKind::DepthLimitExceeded { position } if *position == 128 * 9
+        ));
+
+        let depth = DEFAULT_MAX_DEPTH;
+        let mut data = "a:1:{i:0;".repeat(depth).into_bytes();
+        data.extend_from_slice(b"N;");
+        data.extend(std::iter::repeat_n(b'}', depth));
+        let expected = format!("{}null{}", "[".repeat(depth), "]".repeat(depth));
+        assert_eq!(to_json(&data).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_php_to_json_large_list() {
+        let mut data = b"a:100000:{".to_vec();
+        let mut expected = String::from("[");
+        for i in 0..100_000 {
+            data.extend_from_slice(format!("i:{i};a:1:{{i:0;i:{i};}}").as_bytes());
+            if i > 0 {
+                expected.push(',');
+            }
+            expected.push_str(&format!("[{i}]"));
+        }
+        data.push(b'}');
+        expected.push(']');
+        assert_eq!(to_json(&data).unwrap(), expected);
+
+        // The last key turns it into an object
+        data.truncate(data.len() - 1);
+        data.extend_from_slice(b"s:1:\"k\";N;}");
+        data[2..8].copy_from_slice(b"100001");
+        let json: serde_json::Value = serde_json::from_str(&to_json(&data).unwrap()).unwrap();
+        let object = json.as_object().unwrap();
+        assert_eq!(object.len(), 100_001);
+        assert_eq!(object["99999"], serde_json::json!([99999]));
+        assert_eq!(object["k"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn test_php_to_json_sensors_corpus() {
+        let data = include_bytes!("../assets/corpus/sensors.txt");
+        let json: serde_json::Value = serde_json::from_str(&to_json(data).unwrap()).unwrap();
+        let sensors = json.as_object().unwrap();
+        assert_eq!(sensors.len(), 50);
+        let sensor = &sensors["sensor_0"];
+        assert_eq!(sensor["temperature"], 20.3);
+        assert_eq!(sensor["coordinates"]["latitude"], -4.5753);
+        assert_eq!(sensor["timestamp"], 1744111040.670525);
+    }
+
+    #[test]
+    fn test_php_to_json_awbw_corpus() {
+        let data = include_bytes!("../assets/corpus/awbw.txt").trim_ascii_end();
+        let json: serde_json::Value = serde_json::from_str(&to_json(data).unwrap()).unwrap();
+        assert_eq!(json["id"], 1362397);
+        assert_eq!(json["name"], "1v4");
+        assert_eq!(json["end_date"], serde_json::Value::Null);
+        assert_eq!(json["boot_interval"], -1);
+    }
+}
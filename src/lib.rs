@@ -1,17 +1,69 @@
 #![doc = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/README.md"))]
 
+#[cfg(feature = "bumpalo")]
+mod arena;
+#[cfg(feature = "datetime")]
+mod datetime;
 #[cfg(feature = "serde")]
 mod de;
+#[cfg(feature = "rust_decimal")]
+mod decimal;
 mod errors;
+mod events;
+#[cfg(feature = "igbinary")]
+mod igbinary;
+#[cfg(feature = "json")]
+mod json;
 mod parser;
 #[cfg(feature = "serde")]
 mod ser;
+#[cfg(feature = "spl")]
+mod spl;
+mod stats;
+#[cfg(feature = "serde")]
+pub mod time;
+mod value;
 
+#[cfg(feature = "bumpalo")]
+pub use arena::{PhpArenaKey, PhpArenaValue};
+#[cfg(feature = "datetime")]
+pub use datetime::{PhpDateTime, deserialize_datetime};
 #[cfg(feature = "serde")]
-pub use de::PhpDeserializer;
-pub use errors::{Error, ErrorKind};
+pub use de::{DuplicateFields, PhpDeserializer, from_reader};
+#[cfg(feature = "rust_decimal")]
+pub use decimal::decimal;
+pub use errors::{Error, ErrorKind, OwnedErrorReport};
+pub use events::EventHandler;
+#[cfg(feature = "igbinary")]
+pub use igbinary::IgbinaryParser;
+#[cfg(feature = "json")]
+pub use json::php_to_json_writer;
 pub use parser::{
-    PhpBstr, PhpParser, PhpProperty, PhpReferenceKind, PhpToken, PhpTokenKind, PhpVisibility,
+    PhpArrayKey, PhpBstr, PhpCheckpoint, PhpParser, PhpProperty, PhpRef, PhpReferenceKind,
+    PhpToken, PhpTokenKind, PhpVisibility, class_names, debug_tokens, demangle_property,
+    mangle_property, top_level_kind, validate,
 };
 #[cfg(feature = "serde")]
-pub use ser::{PhpSerializer, StructStyle};
+pub use ser::{
+    FloatFormat, PhpSerializer, PhpSerializerBuilder, StructStyle, to_string, to_vec, to_writer,
+};
+#[cfg(feature = "spl")]
+pub use spl::{
+    PhpArrayObject, PhpObjectStorage, deserialize_array_object, deserialize_object_storage,
+};
+pub use stats::{PayloadStats, stats};
+pub use value::{PhpEntries, PhpKey, PhpValue, structurally_equal, unwrap_double_serialized};
+
+/// The most commonly used items, for a single glob import.
+///
+/// ```rust
+/// use phpserz::prelude::*;
+///
+/// let mut parser = PhpParser::new(b"i:1;");
+/// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+/// ```
+pub mod prelude {
+    pub use crate::{Error, ErrorKind, PhpKey, PhpParser, PhpToken, PhpTokenKind, PhpValue};
+    #[cfg(feature = "serde")]
+    pub use crate::{PhpDeserializer, PhpSerializer, from_reader, to_string, to_vec, to_writer};
+}
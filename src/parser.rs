@@ -1,4 +1,11 @@
 use crate::errors::{Error, ErrorKind};
+use std::collections::HashSet;
+use std::num::NonZeroU64;
+use std::ops::Range;
+use std::slice::SliceIndex;
+
+/// The default limit on how deeply arrays and objects may be nested.
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
 
 /// A byte string that is conventionally UTF-8.
 ///
@@ -17,11 +24,78 @@ impl<'a> PhpBstr<'a> {
         Self { data }
     }
 
+    /// Create a new byte string from the bytes of a string.
+    ///
+    /// ```rust
+    /// use phpserz::PhpBstr;
+    /// assert_eq!(PhpBstr::from_str("foo"), PhpBstr::new(b"foo"));
+    /// ```
+    #[must_use]
+    #[allow(clippy::should_implement_trait)] // FromStr can't borrow the input
+    pub const fn from_str(s: &'a str) -> Self {
+        Self::new(s.as_bytes())
+    }
+
+    /// The length in bytes of this byte string serialized as a PHP string,
+    /// `s:<len>:"<bytes>";`.
+    ///
+    /// ```rust
+    /// use phpserz::PhpBstr;
+    /// assert_eq!(PhpBstr::from_str("foo").serialized_len(), br#"s:3:"foo";"#.len());
+    /// ```
+    #[must_use]
+    pub const fn serialized_len(&self) -> usize {
+        let len = self.data.len();
+        let digits = match len.checked_ilog10() {
+            Some(log) => log as usize + 1,
+            None => 1,
+        };
+
+        // s, two colons, two quotes, and the semicolon
+        len + digits + 6
+    }
+
     /// Get the underlying bytes of the byte string.
     pub const fn as_bytes(&self) -> &'a [u8] {
         self.data
     }
 
+    /// The part of the byte string within `range`, without copying, or `None`
+    /// if the range is out of bounds.
+    ///
+    /// ```rust
+    /// use phpserz::PhpBstr;
+    /// let key = PhpBstr::from_str("user:42");
+    /// assert_eq!(key.slice(5..), Some(PhpBstr::from_str("42")));
+    /// assert_eq!(key.slice(5..10), None);
+    /// ```
+    #[must_use]
+    pub fn slice<R>(&self, range: R) -> Option<PhpBstr<'a>>
+    where
+        R: SliceIndex<[u8], Output = [u8]>,
+    {
+        self.data.get(range).map(PhpBstr::new)
+    }
+
+    /// Divide the byte string in two at `mid`, without copying.
+    ///
+    /// ```rust
+    /// use phpserz::PhpBstr;
+    /// let (head, tail) = PhpBstr::from_str("user:42").split_at(4);
+    /// assert_eq!(head, PhpBstr::from_str("user"));
+    /// assert_eq!(tail, PhpBstr::from_str(":42"));
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `mid` is greater than the length, like [`slice::split_at`].
+    /// Use [`PhpBstr::slice`] to avoid panicking.
+    #[must_use]
+    pub const fn split_at(&self, mid: usize) -> (PhpBstr<'a>, PhpBstr<'a>) {
+        let (head, tail) = self.data.split_at(mid);
+        (PhpBstr::new(head), PhpBstr::new(tail))
+    }
+
     /// Convert the byte string to a string.
     pub fn to_str(self) -> Result<&'a str, Error> {
         std::str::from_utf8(self.data).map_err(|e| Error::from(ErrorKind::Utf8(e)))
@@ -57,6 +131,23 @@ impl<'a> PhpBstr<'a> {
     }
 }
 
+/// A saved position of a [`PhpParser`], see [`PhpParser::checkpoint`].
+#[derive(Debug, PartialEq, Clone, Copy, Eq)]
+pub struct PhpCheckpoint {
+    position: usize,
+    depth: u32,
+    expect_key: bool,
+    value_count: u64,
+}
+
+impl PhpCheckpoint {
+    /// The position of the parser when the checkpoint was taken.
+    #[must_use]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
+}
+
 /// The visibility of a property in a PHP object.
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 pub enum PhpVisibility {
@@ -74,6 +165,36 @@ pub enum PhpReferenceKind {
     Alias,
 }
 
+/// The target of a reference token: the 1-based id of an earlier value.
+///
+/// See [`PhpParser::next_token_with_ref_id`] for how PHP numbers values.
+#[derive(Debug, PartialEq, Clone, Copy, Eq, PartialOrd, Ord, Hash)]
+pub struct PhpRef(NonZeroU64);
+
+impl PhpRef {
+    /// Create a reference to the value with the given id, or `None` for `0`
+    /// as ids start at 1.
+    ///
+    /// ```rust
+    /// use phpserz::PhpRef;
+    /// assert_eq!(PhpRef::new(2).map(PhpRef::index), Some(2));
+    /// assert_eq!(PhpRef::new(0), None);
+    /// ```
+    #[must_use]
+    pub const fn new(index: u64) -> Option<Self> {
+        match NonZeroU64::new(index) {
+            Some(index) => Some(Self(index)),
+            None => None,
+        }
+    }
+
+    /// The id of the value being referred to.
+    #[must_use]
+    pub const fn index(self) -> u64 {
+        self.0.get()
+    }
+}
+
 /// A PHP object property with its name and visibility.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub struct PhpProperty<'a> {
@@ -123,6 +244,8 @@ pub enum PhpToken<'a> {
     Array { elements: u32 },
 
     /// The object token.
+    ///
+    /// The class name is kept as raw bytes and is not required to be UTF-8.
     Object { class: PhpBstr<'a>, properties: u32 },
 
     /// A custom-serialized object token.
@@ -131,11 +254,47 @@ pub enum PhpToken<'a> {
         payload: PhpBstr<'a>,
     },
 
+    /// A PHP 8.1 enum case, serialized as `E:11:"Suit:Hearts";`.
+    Enum {
+        class: PhpBstr<'a>,
+        case: PhpBstr<'a>,
+    },
+
     /// The end of an array or object.
     End,
 
     /// The reference token.
-    Reference { id: i64, kind: PhpReferenceKind },
+    Reference { id: PhpRef, kind: PhpReferenceKind },
+}
+
+impl PhpToken<'_> {
+    /// The kind of the token, without its data.
+    pub(crate) const fn kind(&self) -> PhpTokenKind {
+        match self {
+            PhpToken::Null => PhpTokenKind::Null,
+            PhpToken::Boolean(_) => PhpTokenKind::Boolean,
+            PhpToken::Integer(_) => PhpTokenKind::Integer,
+            PhpToken::Float(_) => PhpTokenKind::Float,
+            PhpToken::String(_) => PhpTokenKind::String,
+            PhpToken::Array { .. } => PhpTokenKind::Array,
+            PhpToken::Object { .. } => PhpTokenKind::Object,
+            PhpToken::CustomObject { .. } => PhpTokenKind::CustomObject,
+            PhpToken::Enum { .. } => PhpTokenKind::Enum,
+            PhpToken::End => PhpTokenKind::End,
+            PhpToken::Reference { kind, .. } => PhpTokenKind::Reference(*kind),
+        }
+    }
+}
+
+/// The key of an array entry or the name of an object property, which PHP
+/// only allows to be an integer or a string.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PhpArrayKey<'a> {
+    /// An integer key.
+    Integer(i64),
+
+    /// A string key or property name.
+    String(PhpBstr<'a>),
 }
 
 /// The kind of token without data.
@@ -149,15 +308,42 @@ pub enum PhpTokenKind {
     Array,
     Object,
     CustomObject,
+    Enum,
     End,
     Reference(PhpReferenceKind),
 }
 
+impl PhpTokenKind {
+    /// A human readable name for error messages.
+    pub(crate) const fn name(self) -> &'static str {
+        match self {
+            PhpTokenKind::Null => "null",
+            PhpTokenKind::Boolean => "boolean",
+            PhpTokenKind::Integer => "integer",
+            PhpTokenKind::Float => "float",
+            PhpTokenKind::String => "string",
+            PhpTokenKind::Array => "array",
+            PhpTokenKind::Object => "object",
+            PhpTokenKind::CustomObject => "custom serialized object",
+            PhpTokenKind::Enum => "enum",
+            PhpTokenKind::End => "end of container",
+            PhpTokenKind::Reference(_) => "reference",
+        }
+    }
+}
+
 /// A parser for the PHP serialized format.
 #[derive(Debug)]
 pub struct PhpParser<'a> {
+    input: &'a [u8],
     data: &'a [u8],
     original_len: usize,
+    skip_line_breaks: bool,
+    allow_unterminated: bool,
+    unterminated: bool,
+    depth: u32,
+    expect_key: bool,
+    value_count: u64,
 }
 
 impl<'a> PhpParser<'a> {
@@ -166,18 +352,158 @@ impl<'a> PhpParser<'a> {
     pub const fn new(data: &'a [u8]) -> Self {
         Self {
             original_len: data.len(),
+            input: data,
             data,
+            skip_line_breaks: false,
+            allow_unterminated: false,
+            unterminated: false,
+            depth: 0,
+            expect_key: false,
+            value_count: 0,
         }
     }
 
+    /// Skip `\r` and `\n` bytes found between tokens (default: `false`).
+    ///
+    /// PHP never emits line breaks between tokens, but serialized data stored
+    /// in text files can have them injected (eg: CRLF conversion).
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"i:1;\r\ni:2;").skip_line_breaks(true);
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(2)));
+    /// ```
+    #[must_use]
+    pub const fn skip_line_breaks(mut self, enabled: bool) -> Self {
+        self.skip_line_breaks = enabled;
+        self
+    }
+
+    /// Consume a leading UTF-8 byte order mark and any ASCII whitespace that
+    /// precedes the first token.
+    ///
+    /// PHP never emits either, so the parser rejects them by default, but data
+    /// pasted from editors can pick them up. Positions continue to be reported
+    /// relative to the original input.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"\xEF\xBB\xBF  i:1;").skip_leading_bom();
+    /// assert_eq!(parser.position(), 5);
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+    /// ```
+    #[must_use]
+    pub const fn skip_leading_bom(mut self) -> Self {
+        if let [0xEF, 0xBB, 0xBF, rest @ ..] = self.data {
+            self.data = rest;
+        }
+
+        while let [c, rest @ ..] = self.data {
+            if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.data = rest;
+        }
+
+        self
+    }
+
     /// Get the current position of the parser.
+    ///
+    /// A token that fails to parse is not consumed, so after an error the
+    /// position is where the last successfully read token ended, while the
+    /// error's own position points at the offending byte.
+    ///
+    /// ```rust
+    /// use phpserz::PhpParser;
+    /// let mut parser = PhpParser::new(b"i:1;i:x;");
+    /// parser.read_token().unwrap();
+    /// assert_eq!(parser.position(), 4);
+    /// assert!(parser.read_token().is_err());
+    /// assert_eq!(parser.position(), 4);
+    /// ```
     #[must_use]
-    pub fn position(&self) -> usize {
+    pub const fn position(&self) -> usize {
         self.original_len - self.data.len()
     }
 
+    /// Save where the parser is, so it can go back there with
+    /// [`PhpParser::restore`] after reading ahead.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"i:1;s:1:\"a\";");
+    /// let checkpoint = parser.checkpoint();
+    /// assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(1));
+    /// parser.restore(checkpoint);
+    /// assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(1));
+    /// ```
+    #[must_use]
+    pub const fn checkpoint(&self) -> PhpCheckpoint {
+        PhpCheckpoint {
+            position: self.position(),
+            depth: self.depth,
+            expect_key: self.expect_key,
+            value_count: self.value_count,
+        }
+    }
+
+    /// Go back to a position saved with [`PhpParser::checkpoint`], along with
+    /// the nesting and the values seen at the time, so the tokens read again
+    /// are checked the same way.
+    ///
+    /// A checkpoint is only meaningful for the parser it was taken from.
+    pub fn restore(&mut self, checkpoint: PhpCheckpoint) {
+        self.data = self.input.get(checkpoint.position..).unwrap_or_default();
+        self.depth = checkpoint.depth;
+        self.expect_key = checkpoint.expect_key;
+        self.value_count = checkpoint.value_count;
+    }
+
+    /// Get the length of the input the parser was created with.
+    #[must_use]
+    pub const fn total_len(&self) -> usize {
+        self.original_len
+    }
+
+    /// Get the fraction of the input consumed so far, from `0.0` to `1.0`,
+    /// for reporting progress through large payloads. Empty input is
+    /// considered fully consumed.
+    ///
+    /// ```rust
+    /// use phpserz::PhpParser;
+    /// let mut parser = PhpParser::new(b"i:1;i:2;");
+    /// parser.read_token().unwrap();
+    /// assert_eq!(parser.progress(), 0.5);
+    /// ```
+    #[must_use]
+    pub fn progress(&self) -> f64 {
+        if self.original_len == 0 {
+            return 1.0;
+        }
+
+        self.position() as f64 / self.original_len as f64
+    }
+
+    /// A new parser over the same input that starts at the given position.
+    /// References read by the new parser are checked against the values this
+    /// parser has seen.
+    pub(crate) fn at(&self, position: usize) -> PhpParser<'a> {
+        let mut parser = self.rewind();
+        parser.data = &self.input[position..];
+        parser.value_count = self.value_count;
+        parser
+    }
+
+    /// A new parser over the same input that starts from the beginning.
+    pub(crate) fn rewind(&self) -> PhpParser<'a> {
+        PhpParser::new(self.input).skip_line_breaks(self.skip_line_breaks)
+    }
+
+    /// Consume the next byte, which must be `expected`.
     #[inline]
-    fn expect(&mut self, expected: u8) -> Result<(), Error> {
+    pub(crate) fn expect(&mut self, expected: u8) -> Result<(), Error> {
         let (&c, rest) = self
             .data
             .split_first()
@@ -195,8 +521,38 @@ impl<'a> PhpParser<'a> {
         Ok(())
     }
 
+    /// Consume the `;` that ends a scalar. Under `parse_lenient`, a top
+    /// level scalar cut off right before it is accepted and flagged instead.
+    #[inline]
+    fn expect_terminator(&mut self) -> Result<(), Error> {
+        match self.expect(b';') {
+            Err(error) if self.accepts_unterminated() => {
+                debug_assert!(matches!(error.kind(), ErrorKind::Eof));
+                self.unterminated = true;
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Whether the input ends where a top level scalar expects its `;`, and
+    /// `parse_lenient` tolerates that.
+    fn accepts_unterminated(&self) -> bool {
+        self.allow_unterminated && self.depth == 0 && self.data.is_empty()
+    }
+
+    #[inline]
+    fn skip_insignificant(&mut self) {
+        if self.skip_line_breaks {
+            while let [b'\r' | b'\n', rest @ ..] = self.data {
+                self.data = rest;
+            }
+        }
+    }
+
     #[inline]
     fn read_next(&mut self) -> Result<Option<PhpTokenKind>, Error> {
+        self.skip_insignificant();
         let position = self.position();
         let Some((&c, rest)) = self.data.split_first() else {
             return Ok(None);
@@ -212,6 +568,7 @@ impl<'a> PhpParser<'a> {
             b'a' => PhpTokenKind::Array,
             b'O' => PhpTokenKind::Object,
             b'C' => PhpTokenKind::CustomObject,
+            b'E' => PhpTokenKind::Enum,
             b'r' => PhpTokenKind::Reference(PhpReferenceKind::Repeated),
             b'R' => PhpTokenKind::Reference(PhpReferenceKind::Alias),
             b'}' => PhpTokenKind::End,
@@ -227,12 +584,34 @@ impl<'a> PhpParser<'a> {
         Ok(Some(kind))
     }
 
+    /// Read the kind and body of the next token. Nothing is consumed when the
+    /// token is malformed, so the parser stays where the previous token ended.
+    #[inline]
+    fn read_next_token(&mut self) -> Result<Option<(PhpTokenKind, PhpToken<'a>)>, Error> {
+        let data = self.data;
+        let result = match self.read_next() {
+            Ok(Some(kind)) => self.parse_token_body(kind).map(|token| Some((kind, token))),
+            Ok(None) => Ok(None),
+            Err(error) => Err(error),
+        };
+
+        if result.is_err() {
+            self.data = data;
+        }
+        result
+    }
+
     /// Peek at the kind of the next token without consuming it.
     ///
     /// Useful for detecting end of arrays and objects.
     ///
     /// Calling this function multiple times will return the same token.
     ///
+    /// Peeking keeps no lookahead state: it only skips insignificant bytes
+    /// before the token, so a caller that peeks speculatively can always
+    /// follow up with [`PhpParser::next_token`] and have the token parsed from
+    /// scratch.
+    ///
     /// ```rust
     /// use phpserz::{PhpParser, PhpToken, PhpTokenKind};
     /// let mut parser = PhpParser::new(b"i:42;");
@@ -240,6 +619,7 @@ impl<'a> PhpParser<'a> {
     /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(42)));
     /// ```
     pub fn peek_token(&mut self) -> Result<Option<PhpTokenKind>, Error> {
+        self.skip_insignificant();
         let Some((&c, _rest)) = self.data.split_first() else {
             return Ok(None);
         };
@@ -253,6 +633,7 @@ impl<'a> PhpParser<'a> {
             b'a' => PhpTokenKind::Array,
             b'O' => PhpTokenKind::Object,
             b'C' => PhpTokenKind::CustomObject,
+            b'E' => PhpTokenKind::Enum,
             b'r' => PhpTokenKind::Reference(PhpReferenceKind::Repeated),
             b'R' => PhpTokenKind::Reference(PhpReferenceKind::Alias),
             b'}' => PhpTokenKind::End,
@@ -267,11 +648,61 @@ impl<'a> PhpParser<'a> {
         Ok(Some(kind))
     }
 
+    /// Peek at the declared byte length of the next token if it is a string,
+    /// without consuming it or looking at its contents.
+    ///
+    /// Returns `Ok(None)` when the next token is not a string or the input
+    /// has been consumed. Like [`PhpParser::peek_token`], nothing is
+    /// consumed, so the string can still be read with
+    /// [`PhpParser::next_token`] or skipped with [`PhpParser::skip_value`].
+    /// Only the length prefix is checked, so a string whose contents are
+    /// shorter than declared is reported once it is read.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpBstr, PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"s:5:\"hello\";i:1;");
+    /// assert_eq!(parser.peek_string_len().unwrap(), Some(5));
+    /// assert_eq!(
+    ///     parser.next_token().unwrap(),
+    ///     Some(PhpToken::String(PhpBstr::new(b"hello")))
+    /// );
+    /// assert_eq!(parser.peek_string_len().unwrap(), None);
+    /// ```
+    pub fn peek_string_len(&mut self) -> Result<Option<u32>, Error> {
+        self.skip_insignificant();
+        if self.data.first() != Some(&b's') {
+            return Ok(None);
+        }
+
+        let mut lookahead = self.at(self.position() + 1);
+        lookahead.expect(b':')?;
+        match read_u32(lookahead.data, b':') {
+            Ok((len, _)) => Ok(Some(len)),
+            Err(e) => Err(lookahead.map_error(e)),
+        }
+    }
+
+    /// Returns true once all of the input has been consumed, which is when
+    /// [`PhpParser::peek_token`] would return `None`.
+    ///
+    /// ```rust
+    /// use phpserz::PhpParser;
+    /// let mut parser = PhpParser::new(b"i:1;");
+    /// assert!(!parser.is_done());
+    /// parser.read_token().unwrap();
+    /// assert!(parser.is_done());
+    /// ```
+    pub fn is_done(&mut self) -> bool {
+        self.skip_insignificant();
+        self.data.is_empty()
+    }
+
     /// Reads the next token, and will error if the end of the input is reached.
     #[inline]
     pub fn read_token(&mut self) -> Result<PhpToken<'a>, Error> {
-        let kind = self.read_next()?.ok_or(ErrorKind::Eof)?;
-        self.parse_token_body(kind)
+        let (kind, token) = self.read_next_token()?.ok_or(ErrorKind::Eof)?;
+        self.track(kind);
+        Ok(token)
     }
 
     /// Attempt to read the next token. Will return Ok(None) if the end of the input is reached.
@@ -283,12 +714,433 @@ impl<'a> PhpParser<'a> {
     /// ```
     #[inline]
     pub fn next_token(&mut self) -> Result<Option<PhpToken<'a>>, Error> {
-        let kind = match self.read_next()? {
-            Some(kind) => kind,
-            None => return Ok(None),
+        let Some((kind, token)) = self.read_next_token()? else {
+            return Ok(None);
         };
 
-        self.parse_token_body(kind).map(Some)
+        self.track(kind);
+        Ok(Some(token))
+    }
+
+    /// Attempt to read the next token along with its reference id. Will
+    /// return Ok(None) if the end of the input is reached.
+    ///
+    /// PHP numbers every value it serializes, starting at 1, so that later
+    /// `r:N;` and `R:N;` tokens can refer back to it. Array keys, property
+    /// names, end tokens, and `R:` tokens don't receive an id, while every
+    /// other value does (including `r:` tokens and the contents of nested
+    /// containers).
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken, PhpReferenceKind};
+    /// // PHP: $o = new stdClass; serialize([$o, $o]);
+    /// let mut parser = PhpParser::new(b"a:2:{i:0;O:8:\"stdClass\":0:{}i:1;r:2;}");
+    /// let mut ids = Vec::new();
+    /// while let Some((_, id)) = parser.next_token_with_ref_id().unwrap() {
+    ///     ids.push(id);
+    /// }
+    /// assert_eq!(ids, [Some(1), None, Some(2), None, None, Some(3), None]);
+    /// ```
+    #[inline]
+    pub fn next_token_with_ref_id(&mut self) -> Result<Option<(PhpToken<'a>, Option<u64>)>, Error> {
+        let Some((kind, token)) = self.read_next_token()? else {
+            return Ok(None);
+        };
+
+        let id = self.track(kind);
+        Ok(Some((token, id)))
+    }
+
+    /// The number of values that have been assigned a reference id so far,
+    /// which is also the id of the most recent one.
+    ///
+    /// ```rust
+    /// use phpserz::PhpParser;
+    /// let mut parser = PhpParser::new(b"a:1:{s:1:\"a\";i:1;}");
+    /// parser.skip_value().unwrap();
+    /// assert_eq!(parser.value_count(), 2);
+    /// ```
+    #[must_use]
+    pub const fn value_count(&self) -> u64 {
+        self.value_count
+    }
+
+    /// Update the key and value bookkeeping after consuming a token, returning
+    /// the reference id assigned to it.
+    #[inline]
+    fn track(&mut self, kind: PhpTokenKind) -> Option<u64> {
+        if kind == PhpTokenKind::End {
+            self.depth = self.depth.saturating_sub(1);
+            self.expect_key = self.depth > 0;
+            return None;
+        }
+
+        if self.expect_key {
+            self.expect_key = false;
+            return None;
+        }
+
+        if matches!(kind, PhpTokenKind::Array | PhpTokenKind::Object) {
+            self.depth += 1;
+            self.expect_key = true;
+        } else {
+            self.expect_key = self.depth > 0;
+        }
+
+        if kind == PhpTokenKind::Reference(PhpReferenceKind::Alias) {
+            return None;
+        }
+
+        self.value_count += 1;
+        Some(self.value_count)
+    }
+
+    /// Attempt to read the next token along with the byte range it occupies in
+    /// the original input. Will return Ok(None) if the end of the input is
+    /// reached.
+    ///
+    /// The range of an array or object covers only its header (`a:2:{`), as
+    /// its contents are separate tokens.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let input = b"a:1:{i:0;s:3:\"foo\";}";
+    /// let mut parser = PhpParser::new(input);
+    /// parser.next_token().unwrap();
+    /// parser.next_token().unwrap();
+    /// let (token, span) = parser.next_token_spanned().unwrap().unwrap();
+    /// assert!(matches!(token, PhpToken::String(_)));
+    /// assert_eq!(&input[span], b"s:3:\"foo\";");
+    /// ```
+    pub fn next_token_spanned(&mut self) -> Result<Option<(PhpToken<'a>, Range<usize>)>, Error> {
+        self.skip_insignificant();
+        let start = self.position();
+        let token = self.next_token()?;
+        Ok(token.map(|token| (token, start..self.position())))
+    }
+
+    /// Read the key of the next entry in an array or object, or `None` once
+    /// the container ends.
+    ///
+    /// Keys can only be integers or strings, so any other token is reported
+    /// as [`ErrorKind::InvalidKey`] and left unconsumed.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpArrayKey, PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"a:1:{i:3;b:1;}");
+    /// parser.next_value_token().unwrap();
+    /// assert_eq!(parser.next_key_token().unwrap(), Some(PhpArrayKey::Integer(3)));
+    /// assert_eq!(parser.next_value_token().unwrap(), PhpToken::Boolean(true));
+    /// assert_eq!(parser.next_key_token().unwrap(), None);
+    ///
+    /// let mut parser = PhpParser::new(b"a:1:{d:1.5;b:1;}");
+    /// parser.next_value_token().unwrap();
+    /// assert!(parser.next_key_token().is_err());
+    /// ```
+    pub fn next_key_token(&mut self) -> Result<Option<PhpArrayKey<'a>>, Error> {
+        let (position, _) = self.upcoming();
+        let kind = self.peek_token()?.ok_or(ErrorKind::Eof)?;
+        if !matches!(
+            kind,
+            PhpTokenKind::Integer | PhpTokenKind::String | PhpTokenKind::End
+        ) {
+            return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+        }
+
+        match self.read_token()? {
+            PhpToken::Integer(key) => Ok(Some(PhpArrayKey::Integer(key))),
+            PhpToken::String(key) => Ok(Some(PhpArrayKey::String(key))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Read the next value: a top level value or the value of an entry whose
+    /// key was read with [`PhpParser::next_key_token`].
+    ///
+    /// The end of a container where a value belongs is reported as an
+    /// unexpected byte and left unconsumed.
+    pub fn next_value_token(&mut self) -> Result<PhpToken<'a>, Error> {
+        let (position, found) = self.upcoming();
+        if found == b'}' {
+            return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+        }
+
+        self.read_token()
+    }
+
+    /// Skip over the next value, including all the contents of an array or
+    /// object.
+    ///
+    /// The contents are checked as they are skipped: keys must be integers or
+    /// strings and each array or object must hold as many entries as it
+    /// declares.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"a:1:{i:0;a:0:{}}i:7;");
+    /// parser.skip_value().unwrap();
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(7)));
+    /// ```
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        self.skip_value_within(usize::MAX)
+    }
+
+    /// Skip over the next value like [`PhpParser::skip_value`], failing if
+    /// arrays and objects are nested more than `max_depth` levels deep.
+    pub(crate) fn skip_value_within(&mut self, max_depth: usize) -> Result<(), Error> {
+        // The declared and seen number of entries of each open container.
+        let mut open: Vec<(u32, u32)> = Vec::new();
+        let mut expect_key = false;
+        loop {
+            let (position, found) = self.upcoming();
+            if open.is_empty() && found == b'}' {
+                return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+            }
+
+            let token = self.read_token()?;
+            if expect_key {
+                match token {
+                    PhpToken::End => {
+                        let (declared, seen) = open.pop().unwrap_or_default();
+                        if declared != seen {
+                            return Err(Error::from(ErrorKind::CountMismatch {
+                                declared,
+                                found: seen,
+                                position,
+                            }));
+                        }
+                    }
+                    PhpToken::Integer(_) | PhpToken::String(_) => {
+                        if let Some((_, seen)) = open.last_mut() {
+                            *seen += 1;
+                        }
+                        expect_key = false;
+                        continue;
+                    }
+                    _ => {
+                        let kind = token.kind();
+                        return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+                    }
+                }
+            } else {
+                match token {
+                    PhpToken::Array {
+                        elements: declared, ..
+                    }
+                    | PhpToken::Object {
+                        properties: declared,
+                        ..
+                    } => {
+                        if open.len() >= max_depth {
+                            return Err(Error::from(ErrorKind::DepthLimitExceeded { position }));
+                        }
+                        open.push((declared, 0));
+                    }
+                    PhpToken::End => {
+                        return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+                    }
+                    _ => {}
+                }
+            }
+
+            if open.is_empty() {
+                return Ok(());
+            }
+            expect_key = true;
+        }
+    }
+
+    /// Read an array or object header and advance to the value of the entry
+    /// with the given key, skipping over the entries before it.
+    ///
+    /// Property names are compared after demangling and integer keys are
+    /// compared by their decimal representation. Returns `false`, with the
+    /// container fully consumed, if no entry matches.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"O:3:\"Foo\":2:{s:1:\"a\";a:0:{}s:6:\"\0*\0bar\";i:7;}");
+    /// assert!(parser.seek_to_field(b"bar").unwrap());
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(7)));
+    /// ```
+    pub fn seek_to_field(&mut self, name: &[u8]) -> Result<bool, Error> {
+        let (position, found) = self.upcoming();
+        match self.peek_token()? {
+            Some(PhpTokenKind::Array | PhpTokenKind::Object) => self.read_token()?,
+            Some(_) => return Err(Error::from(ErrorKind::UnexpectedByte { found, position })),
+            None => return Err(Error::from(ErrorKind::Eof)),
+        };
+
+        loop {
+            let (position, found) = self.upcoming();
+            if !matches!(found, b'i' | b's' | b'}') {
+                self.peek_token()?.ok_or(ErrorKind::Eof)?;
+                return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+            }
+
+            let matched = match self.read_token()? {
+                PhpToken::End => return Ok(false),
+                PhpToken::String(key) => key.to_property().as_bytes() == name,
+                PhpToken::Integer(key) => key.to_string().as_bytes() == name,
+                _ => return Err(Error::from(ErrorKind::UnexpectedByte { found, position })),
+            };
+
+            if matched {
+                return Ok(true);
+            }
+
+            self.skip_value()?;
+        }
+    }
+
+    /// Whether the input after any insignificant bytes starts with `prefix`.
+    #[cfg(feature = "serde")]
+    pub(crate) fn starts_with(&mut self, prefix: &[u8]) -> bool {
+        self.skip_insignificant();
+        self.data.starts_with(prefix)
+    }
+
+    /// Read the next token for `parse_lenient`, recording malformed scalars
+    /// and stray bytes in `errors` and resynchronizing past them. A malformed
+    /// value is replaced by null and an entry with a malformed key, or a key
+    /// that is neither an integer nor a string, is dropped. Errors that can't
+    /// be recovered from are returned.
+    pub(crate) fn read_token_recovering(
+        &mut self,
+        errors: &mut Vec<Error>,
+    ) -> Result<(usize, PhpToken<'a>), Error> {
+        loop {
+            let (start, c) = self.upcoming();
+            if self.expect_key {
+                if let Ok(Some(kind)) = self.peek_token() {
+                    if !matches!(
+                        kind,
+                        PhpTokenKind::Integer | PhpTokenKind::String | PhpTokenKind::End
+                    ) {
+                        errors.push(Error::from(ErrorKind::InvalidKey {
+                            kind,
+                            position: start,
+                        }));
+                        self.skip_entry()?;
+                        continue;
+                    }
+                }
+            }
+
+            let error = if c == b'}' && self.depth == 0 {
+                Error::from(ErrorKind::UnexpectedByte {
+                    found: c,
+                    position: start,
+                })
+            } else {
+                match self.read_token() {
+                    Ok(token) => {
+                        if std::mem::take(&mut self.unterminated) {
+                            errors.push(Error::from(ErrorKind::MissingTerminator {
+                                position: self.position(),
+                            }));
+                        }
+                        return Ok((start, token));
+                    }
+                    Err(error) => error,
+                }
+            };
+
+            let scalar = matches!(c, b'N' | b'b' | b'i' | b'd' | b's' | b'E');
+            let stray = matches!(
+                error.kind(),
+                ErrorKind::UnexpectedByte { position, .. } if *position == start
+            );
+            if !(scalar || stray) || !self.resync(start) {
+                return Err(error);
+            }
+
+            errors.push(error);
+            if !scalar {
+                continue;
+            }
+
+            let key = self.expect_key;
+            self.track(PhpTokenKind::Null);
+            if !key {
+                return Ok((start, PhpToken::Null));
+            }
+
+            self.skip_value()?;
+        }
+    }
+
+    /// Skip an entry whose key is a value that can't be a key, giving the key
+    /// no reference id.
+    fn skip_entry(&mut self) -> Result<(), Error> {
+        let value_count = self.value_count;
+        self.expect_key = false;
+        self.skip_value()?;
+        self.value_count = value_count;
+        self.expect_key = false;
+        self.skip_value()
+    }
+
+    /// Move past the first `;` after `start` that is followed by what looks
+    /// like the start of a token, or the end of the input. Returns false if
+    /// there is no such `;`.
+    fn resync(&mut self, start: usize) -> bool {
+        let mut from = start + 1;
+        while let Some(offset) = self.input[from..].iter().position(|&b| b == b';') {
+            from += offset + 1;
+            let mut rest = &self.input[from..];
+            if self.skip_line_breaks {
+                while let [b'\r' | b'\n', tail @ ..] = rest {
+                    rest = tail;
+                }
+            }
+
+            let boundary = matches!(
+                rest,
+                [] | [b'}', ..]
+                    | [b'N', b';', ..]
+                    | [
+                        b'b' | b'i' | b'd' | b's' | b'a' | b'O' | b'C' | b'E' | b'r' | b'R',
+                        b':',
+                        ..
+                    ]
+            );
+            if boundary {
+                self.data = &self.input[from..];
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Accept a top level scalar that is missing its final `;` for
+    /// `parse_lenient`.
+    pub(crate) fn allow_unterminated(&mut self, enabled: bool) {
+        self.allow_unterminated = enabled;
+    }
+
+    /// Give up on the rest of the input for `parse_lenient`, returning the
+    /// token that closes the innermost open container, or null if there is
+    /// none.
+    pub(crate) fn abandon(&mut self) -> (usize, PhpToken<'a>) {
+        self.data = &self.data[self.data.len()..];
+        let position = self.position();
+        if self.depth == 0 {
+            return (position, PhpToken::Null);
+        }
+
+        self.track(PhpTokenKind::End);
+        (position, PhpToken::End)
+    }
+
+    /// The position and byte of the next token, for error reporting.
+    pub(crate) fn upcoming(&mut self) -> (usize, u8) {
+        self.skip_insignificant();
+        (
+            self.position(),
+            self.data.first().copied().unwrap_or_default(),
+        )
     }
 
     #[inline]
@@ -296,7 +1148,7 @@ impl<'a> PhpParser<'a> {
         match kind {
             PhpTokenKind::End => Ok(PhpToken::End),
             PhpTokenKind::Null => {
-                self.expect(b';')?;
+                self.expect_terminator()?;
                 Ok(PhpToken::Null)
             }
             PhpTokenKind::Boolean => {
@@ -318,13 +1170,27 @@ impl<'a> PhpParser<'a> {
                     }
                 };
 
+                if rest.first().is_some_and(u8::is_ascii_digit) {
+                    return Err(Error::from(ErrorKind::InvalidBoolean {
+                        position: self.position(),
+                    }));
+                }
+
                 self.data = rest;
-                self.expect(b';')?;
+                self.expect_terminator()?;
                 Ok(token)
             }
             PhpTokenKind::Integer => {
                 self.expect(b':')?;
-                let (int, rest) = to_i64(self.data).map_err(|e| self.map_error(e))?;
+                let (int, rest) = match to_i64(self.data) {
+                    Err(ScalarError::Eof) if self.allow_unterminated && self.depth == 0 => {
+                        let terminated = [self.data, b";"].concat();
+                        let (int, _) = to_i64(&terminated).map_err(|e| self.map_error(e))?;
+                        self.unterminated = true;
+                        (int, &self.data[self.data.len()..])
+                    }
+                    result => result.map_err(|e| self.map_error(e))?,
+                };
                 self.data = rest;
                 Ok(PhpToken::Integer(int))
             }
@@ -338,39 +1204,58 @@ impl<'a> PhpParser<'a> {
                 })?;
 
                 self.data = &self.data[len..];
-                self.expect(b';')?;
+                self.expect_terminator()?;
                 Ok(PhpToken::Float(num))
             }
             PhpTokenKind::String => {
                 self.expect(b':')?;
                 let (s, rest) = read_str(self.data).map_err(|e| self.map_error(e))?;
                 self.data = rest;
-                self.expect(b';')?;
+                self.expect_terminator()?;
                 Ok(PhpToken::String(s))
             }
+            PhpTokenKind::Enum => {
+                self.expect(b':')?;
+                let position = self.position();
+                let (s, rest) = read_str(self.data).map_err(|e| self.map_error(e))?;
+                let bytes = s.as_bytes();
+                let Some(colon) = bytes.iter().rposition(|&b| b == b':') else {
+                    return Err(Error::from(ErrorKind::InvalidEnum { position }));
+                };
+                self.data = rest;
+                self.expect_terminator()?;
+                Ok(PhpToken::Enum {
+                    class: PhpBstr::new(&bytes[..colon]),
+                    case: PhpBstr::new(&bytes[colon + 1..]),
+                })
+            }
             PhpTokenKind::Array => {
                 self.expect(b':')?;
+                let count_position = self.position();
                 let (elements, rest) = read_u32(self.data, b':').map_err(|e| self.map_error(e))?;
                 self.data = rest;
                 self.expect(b'{')?;
+                self.check_count(elements, count_position)?;
                 Ok(PhpToken::Array { elements })
             }
             PhpTokenKind::Object => {
                 self.expect(b':')?;
-                let (class, rest) = read_str(self.data).map_err(|e| self.map_error(e))?;
+                let (class, rest) = self.read_class()?;
                 self.data = rest;
                 self.expect(b':')?;
 
+                let count_position = self.position();
                 let (properties, rest) =
                     read_u32(self.data, b':').map_err(|e| self.map_error(e))?;
                 self.data = rest;
                 self.expect(b'{')?;
+                self.check_count(properties, count_position)?;
 
                 Ok(PhpToken::Object { class, properties })
             }
             PhpTokenKind::CustomObject => {
                 self.expect(b':')?;
-                let (class, rest) = read_str(self.data).map_err(|e| self.map_error(e))?;
+                let (class, rest) = self.read_class()?;
                 self.data = rest;
                 self.expect(b':')?;
 
@@ -380,7 +1265,11 @@ impl<'a> PhpParser<'a> {
                 self.expect(b'{')?;
 
                 let Some((payload, rest)) = self.data.split_at_checked(payload_len as usize) else {
-                    return Err(ErrorKind::Eof.into());
+                    return Err(ErrorKind::TruncatedString {
+                        declared: payload_len,
+                        position: self.position(),
+                    }
+                    .into());
                 };
                 self.data = rest;
                 self.expect(b'}')?;
@@ -392,16 +1281,62 @@ impl<'a> PhpParser<'a> {
             }
             PhpTokenKind::Reference(kind) => {
                 self.expect(b':')?;
+                let position = self.position();
                 let (int, rest) = to_i64(self.data).map_err(|e| self.map_error(e))?;
+                let id = u64::try_from(int)
+                    .ok()
+                    .and_then(PhpRef::new)
+                    .ok_or_else(|| {
+                        Error::from(ErrorKind::InvalidReference {
+                            index: int,
+                            position,
+                        })
+                    })?;
+                if id.index() > self.value_count {
+                    return Err(Error::from(ErrorKind::ForwardReference {
+                        index: id.index(),
+                        position,
+                    }));
+                }
                 self.data = rest;
-                Ok(PhpToken::Reference { id: int, kind })
+                Ok(PhpToken::Reference { id, kind })
             }
         }
     }
 
+    /// Read the next token if it is a string and return its contents, without
+    /// the overhead of a [`PhpToken`]. Returns `None`, and leaves the token
+    /// unconsumed, when the next token is something else or the input has
+    /// ended.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpToken};
+    /// let mut parser = PhpParser::new(b"s:3:\"foo\";i:1;");
+    /// assert_eq!(parser.next_string_bytes().unwrap(), Some(&b"foo"[..]));
+    /// assert_eq!(parser.next_string_bytes().unwrap(), None);
+    /// assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+    /// assert_eq!(parser.next_string_bytes().unwrap(), None);
+    /// ```
+    #[inline]
+    pub fn next_string_bytes(&mut self) -> Result<Option<&'a [u8]>, Error> {
+        if let Some(s) = self.try_read_str() {
+            return Ok(Some(s.as_bytes()));
+        }
+
+        if self.peek_token()? != Some(PhpTokenKind::String) {
+            return Ok(None);
+        }
+
+        match self.read_token()? {
+            PhpToken::String(s) => Ok(Some(s.as_bytes())),
+            _ => unreachable!("peeked a string token"),
+        }
+    }
+
     /// Try to read the next token as a string up to 99 characters long
     #[inline]
     pub(crate) fn try_read_str(&mut self) -> Option<PhpBstr<'a>> {
+        self.skip_insignificant();
         let data = self.data;
         let d = data.get(..16)?;
         if d[0] != b's' || d[1] != b':' || !d[2].is_ascii_digit() {
@@ -416,6 +1351,7 @@ impl<'a> PhpParser<'a> {
             }
 
             self.data = &data[end + 2..];
+            self.track(PhpTokenKind::String);
             return Some(PhpBstr::new(&d[5..end]));
         }
 
@@ -431,15 +1367,18 @@ impl<'a> PhpParser<'a> {
         }
 
         self.data = rest;
+        self.track(PhpTokenKind::String);
         Some(PhpBstr::new(&s[6..end]))
     }
 
     /// Try to consume an end token (`}`). Returns true if consumed.
     #[inline]
     pub(crate) fn try_read_end(&mut self) -> bool {
+        self.skip_insignificant();
         match self.data {
             [b'}', rest @ ..] => {
                 self.data = rest;
+                self.track(PhpTokenKind::End);
                 true
             }
             _ => false,
@@ -449,11 +1388,13 @@ impl<'a> PhpParser<'a> {
     /// Try to read the next token as an integer.
     #[inline]
     pub(crate) fn try_read_i64(&mut self) -> Option<i64> {
+        self.skip_insignificant();
         let data = self.data;
         match data {
             [b'i', b':', rest @ ..] => {
                 let (int, data) = to_i64(rest).ok()?;
                 self.data = data;
+                self.track(PhpTokenKind::Integer);
                 Some(int)
             }
             _ => None,
@@ -463,6 +1404,7 @@ impl<'a> PhpParser<'a> {
     /// Try to read the next token as a float.
     #[inline]
     pub(crate) fn try_read_f64(&mut self) -> Option<f64> {
+        self.skip_insignificant();
         let data = self.data;
         match data {
             [b'd', b':', rest @ ..] => {
@@ -473,6 +1415,7 @@ impl<'a> PhpParser<'a> {
                 }
 
                 self.data = rest;
+                self.track(PhpTokenKind::Float);
                 Some(float)
             }
             _ => None,
@@ -482,13 +1425,15 @@ impl<'a> PhpParser<'a> {
     /// Try to read the next token as an array header.
     #[inline]
     pub(crate) fn try_read_seq_start(&mut self) -> Option<u32> {
+        self.skip_insignificant();
         let data = self.data;
         match data {
             [b'a', b':', rest @ ..] => {
                 let (elements, rest) = read_u32(rest, b':').ok()?;
                 match rest {
-                    [b'{', rest @ ..] => {
+                    [b'{', rest @ ..] if u64::from(elements) <= max_entries(rest) => {
                         self.data = rest;
+                        self.track(PhpTokenKind::Array);
                         Some(elements)
                     }
                     _ => None,
@@ -498,6 +1443,51 @@ impl<'a> PhpParser<'a> {
         }
     }
 
+    /// Reject container counts that the remaining input can't possibly hold,
+    /// so that callers can trust the count as an allocation hint.
+    #[inline]
+    fn check_count(&self, declared: u32, position: usize) -> Result<(), Error> {
+        let found = max_entries(self.data);
+        if u64::from(declared) <= found {
+            return Ok(());
+        }
+
+        Err(Error::from(ErrorKind::CountMismatch {
+            declared,
+            found: u32::try_from(found).unwrap_or(u32::MAX),
+            position,
+        }))
+    }
+
+    #[inline]
+    fn read_class(&self) -> Result<(PhpBstr<'a>, &'a [u8]), Error> {
+        read_str(self.data).map_err(|e| match e {
+            ScalarError::MissingQuotes | ScalarError::Truncated { .. } => self
+                .class_len_mismatch()
+                .unwrap_or_else(|| self.map_error(e)),
+            _ => self.map_error(e),
+        })
+    }
+
+    /// PHP class names can't contain quotes, so a class name whose closing
+    /// quote isn't where the length prefix says is reported as a length
+    /// mismatch rather than whatever error the misplaced read runs into.
+    #[cold]
+    fn class_len_mismatch(&self) -> Option<Error> {
+        let (declared, rest) = read_u32(self.data, b':').ok()?;
+        let name = rest.strip_prefix(b"\"")?;
+        let found = name.iter().position(|&b| b == b'"')?;
+        if name.get(found + 1) != Some(&b':') || found == declared as usize {
+            return None;
+        }
+
+        Some(Error::from(ErrorKind::StringLengthMismatch {
+            declared,
+            found: u32::try_from(found).ok()?,
+            position: self.position(),
+        }))
+    }
+
     #[cold]
     fn map_error(&self, error: ScalarError) -> Error {
         match error {
@@ -509,7 +1499,11 @@ impl<'a> PhpParser<'a> {
                 position: self.position(),
             })
             .into(),
-            ScalarError::Overflow => (ErrorKind::Overflow {
+            ScalarError::LengthOverflow => (ErrorKind::LengthOverflow {
+                position: self.position(),
+            })
+            .into(),
+            ScalarError::IntegerOverflow => (ErrorKind::IntegerOverflow {
                 position: self.position(),
             })
             .into(),
@@ -517,26 +1511,262 @@ impl<'a> PhpParser<'a> {
                 position: self.position(),
             })
             .into(),
+            ScalarError::Truncated { declared } => (ErrorKind::TruncatedString {
+                declared,
+                position: self.position(),
+            })
+            .into(),
             ScalarError::Eof => ErrorKind::Eof.into(),
         }
     }
 }
 
+/// Demangle a raw property name into its name and visibility.
+///
+/// This is the same logic as [`PhpBstr::to_property`] for callers that hold a
+/// plain byte slice, like a map key that was already extracted.
+///
+/// ```rust
+/// use phpserz::{demangle_property, PhpVisibility};
+/// assert_eq!(
+///     demangle_property(b"\0*\0isActive").unwrap(),
+///     ("isActive", PhpVisibility::Protected)
+/// );
+/// ```
+pub fn demangle_property(name: &[u8]) -> Result<(&str, PhpVisibility), Error> {
+    let property = PhpBstr::new(name).to_property();
+    Ok((property.to_str()?, property.visibility()))
+}
+
+/// Mangle a property name with its visibility, the inverse of
+/// [`demangle_property`].
+///
+/// Private properties are prefixed with `\0ClassName\0` and protected
+/// properties with `\0*\0`, while public properties are left as is. The class
+/// is only used for private properties, and a private property without one
+/// gets an empty class name.
+///
+/// ```rust
+/// use phpserz::{mangle_property, PhpVisibility};
+/// assert_eq!(
+///     mangle_property("pin", PhpVisibility::Private, Some("Account")),
+///     b"\0Account\0pin"
+/// );
+/// assert_eq!(
+///     mangle_property("balance", PhpVisibility::Protected, None),
+///     b"\0*\0balance"
+/// );
+/// ```
+#[must_use]
+pub fn mangle_property(name: &str, visibility: PhpVisibility, class: Option<&str>) -> Vec<u8> {
+    let prefix: &[&[u8]] = match visibility {
+        PhpVisibility::Public => &[],
+        PhpVisibility::Protected => &[b"\0*\0"],
+        PhpVisibility::Private => &[b"\0", class.unwrap_or_default().as_bytes(), b"\0"],
+    };
+
+    let mut result = prefix.concat();
+    result.extend_from_slice(name.as_bytes());
+    result
+}
+
+/// Collect the class names of every object and enum case in the payload.
+///
+/// Names are deduplicated, by byte comparison, and returned in the order they
+/// are first seen. Regular (`O`) and custom-serialized (`C`) objects are
+/// included, as are the enums of enum cases (`E`).
+///
+/// ```rust
+/// use phpserz::{class_names, PhpBstr};
+/// let data = b"a:2:{i:0;O:3:\"Foo\":0:{}i:1;O:3:\"Bar\":1:{s:1:\"a\";O:3:\"Foo\":0:{}}}";
+/// assert_eq!(
+///     class_names(data).unwrap(),
+///     vec![PhpBstr::new(b"Foo"), PhpBstr::new(b"Bar")]
+/// );
+/// ```
+pub fn class_names(data: &[u8]) -> Result<Vec<PhpBstr<'_>>, Error> {
+    let mut parser = PhpParser::new(data);
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    while let Some(token) = parser.next_token()? {
+        let class = match token {
+            PhpToken::Object { class, .. }
+            | PhpToken::CustomObject { class, .. }
+            | PhpToken::Enum { class, .. } => class,
+            _ => continue,
+        };
+
+        if seen.insert(class) {
+            result.push(class);
+        }
+    }
+
+    Ok(result)
+}
+
+/// Peek at the kind of the top-level value without parsing it, for instance
+/// to route a payload to the right handler.
+///
+/// Only the first byte of the value is inspected, so the rest of the payload
+/// may still be malformed. Empty input fails with [`ErrorKind::Eof`], and the
+/// end of a container, which can't start a value, with
+/// [`ErrorKind::UnexpectedByte`].
+///
+/// ```rust
+/// use phpserz::{top_level_kind, PhpTokenKind};
+/// let data = b"a:1:{i:0;s:3:\"foo\";}";
+/// assert_eq!(top_level_kind(data).unwrap(), PhpTokenKind::Array);
+/// assert!(top_level_kind(b"").is_err());
+/// ```
+pub fn top_level_kind(data: &[u8]) -> Result<PhpTokenKind, Error> {
+    let mut parser = PhpParser::new(data);
+    let (position, found) = parser.upcoming();
+    match parser.peek_token()? {
+        Some(PhpTokenKind::End) => Err(Error::from(ErrorKind::UnexpectedByte { found, position })),
+        Some(kind) => Ok(kind),
+        None => Err(Error::from(ErrorKind::Eof)),
+    }
+}
+
+/// Check that the data is a single well-formed serialized value without
+/// materializing anything.
+///
+/// The whole input must be consumed, entry counts must match what arrays and
+/// objects declare, keys must be integers or strings, and containers may not
+/// be nested more than 128 levels deep. The first problem found is returned.
+///
+/// ```rust
+/// use phpserz::{validate, ErrorKind};
+/// assert!(validate(b"a:1:{s:1:\"a\";O:3:\"Foo\":0:{}}").is_ok());
+/// assert!(matches!(
+///     validate(b"i:1;i:2;").unwrap_err().kind(),
+///     ErrorKind::TrailingData { position: 4 }
+/// ));
+/// ```
+pub fn validate(data: &[u8]) -> Result<(), Error> {
+    let mut parser = PhpParser::new(data);
+    parser.skip_value_within(DEFAULT_MAX_DEPTH)?;
+
+    let (position, _) = parser.upcoming();
+    if position < data.len() {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+
+    Ok(())
+}
+
+/// Render the token stream as an indented tree for debugging, with each
+/// entry on its own line as `key => value` and the contents of arrays and
+/// objects nested between braces.
+///
+/// ```rust
+/// use phpserz::debug_tokens;
+/// let tree = debug_tokens(b"a:2:{i:0;d:1.5;s:3:\"foo\";O:3:\"Bar\":1:{s:1:\"x\";N;}}").unwrap();
+/// assert_eq!(
+///     tree,
+///     r#"a:2 {
+///   i:0 => d:1.5
+///   s:"foo" => O:"Bar":1 {
+///     s:"x" => N
+///   }
+/// }
+/// "#
+/// );
+/// ```
+pub fn debug_tokens(data: &[u8]) -> Result<String, Error> {
+    use std::fmt::Write;
+
+    let mut parser = PhpParser::new(data);
+    let mut out = String::new();
+    let mut depth = 0usize;
+    let mut after_key = false;
+    loop {
+        let key = parser.expect_key;
+        let Some(token) = parser.next_token()? else {
+            break;
+        };
+
+        if token == PhpToken::End {
+            depth = depth.saturating_sub(1);
+        }
+
+        if !after_key {
+            out.extend(std::iter::repeat_n("  ", depth));
+        }
+
+        let _ = match token {
+            PhpToken::Null => write!(out, "N"),
+            PhpToken::Boolean(b) => write!(out, "b:{b}"),
+            PhpToken::Integer(i) => write!(out, "i:{i}"),
+            PhpToken::Float(f) => write!(out, "d:{f:?}"),
+            PhpToken::String(s) => write!(out, "s:\"{}\"", s.as_bytes().escape_ascii()),
+            PhpToken::Array { elements } => write!(out, "a:{elements} {{"),
+            PhpToken::Object { class, properties } => write!(
+                out,
+                "O:\"{}\":{properties} {{",
+                class.as_bytes().escape_ascii()
+            ),
+            PhpToken::CustomObject { class, payload } => write!(
+                out,
+                "C:\"{}\" {{{}}}",
+                class.as_bytes().escape_ascii(),
+                payload.as_bytes().escape_ascii()
+            ),
+            PhpToken::Enum { class, case } => write!(
+                out,
+                "E:\"{}:{}\"",
+                class.as_bytes().escape_ascii(),
+                case.as_bytes().escape_ascii()
+            ),
+            PhpToken::End => write!(out, "}}"),
+            PhpToken::Reference { id, kind } => match kind {
+                PhpReferenceKind::Repeated => write!(out, "r:{}", id.index()),
+                PhpReferenceKind::Alias => write!(out, "R:{}", id.index()),
+            },
+        };
+
+        after_key = key && token != PhpToken::End;
+        if after_key {
+            out.push_str(" => ");
+        } else {
+            if matches!(token, PhpToken::Array { .. } | PhpToken::Object { .. }) {
+                depth += 1;
+            }
+            out.push('\n');
+        }
+    }
+
+    Ok(out)
+}
+
+/// The smallest encoding of a container entry is an integer key followed by
+/// null (`i:0;N;`).
+const MIN_ENTRY_LEN: usize = 6;
+
+/// The most entries that a container body could hold, accounting for the
+/// closing brace.
+#[inline]
+fn max_entries(body: &[u8]) -> u64 {
+    (body.len().saturating_sub(1) / MIN_ENTRY_LEN) as u64
+}
+
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 enum ScalarError {
     MissingQuotes,
     Empty,
-    Overflow,
+    LengthOverflow,
+    IntegerOverflow,
     Invalid,
+    Truncated { declared: u32 },
     Eof,
 }
 
 #[inline]
 fn read_str(data: &[u8]) -> Result<(PhpBstr<'_>, &[u8]), ScalarError> {
-    let (len, data) = read_u32(data, b':')?;
-    let len = len as usize;
+    let (declared, data) = read_u32(data, b':')?;
+    let len = declared as usize;
     let Some((contents, rest)) = data.split_at_checked(len + 2) else {
-        return Err(ScalarError::Eof);
+        return Err(ScalarError::Truncated { declared });
     };
 
     match contents {
@@ -564,7 +1794,7 @@ fn read_u32(mut data: &[u8], delimiter: u8) -> Result<(u32, &[u8]), ScalarError>
             // For ≤10 digits, the value fits in u64 without wrapping, so
             // comparing against u32::MAX is sufficient.
             if digits > 10 || result > u64::from(u32::MAX) {
-                return Err(ScalarError::Overflow);
+                return Err(ScalarError::LengthOverflow);
             }
             return Ok((result as u32, rest));
         } else {
@@ -606,7 +1836,7 @@ fn to_i64(d: &[u8]) -> Result<(i64, &[u8]), ScalarError> {
             // i64::MAX is 19 digits; u64::MAX is 20 digits, so wrapping only
             // occurs at 20+ digits. Guard against that before the value check.
             if digits > 19 || result > (i64::MAX as u64) + u64::from(negative) {
-                return Err(ScalarError::Overflow);
+                return Err(ScalarError::IntegerOverflow);
             }
 
             let sign: i64 = if negative { -1 } else { 1 };
@@ -641,11 +1871,12 @@ mod tests {
             assert_eq!(
                 &actual_token,
                 expected_token,
-                "Token mismatch at position {}: expected {:?}, got {:?} at position {}",
+                "Token mismatch at position {}: expected {:?}, got {:?} at position {} in:\n{}",
                 token_index,
                 expected_token,
                 actual_token,
-                parser.position()
+                parser.position(),
+                debug_tokens(input).unwrap_or_else(|e| e.to_string())
             );
 
             token_index += 1;
@@ -655,8 +1886,9 @@ mod tests {
         assert_eq!(
             token_index,
             expected_tokens.len(),
-            "Missing expected tokens: {:?}",
-            &expected_tokens[token_index..]
+            "Missing expected tokens: {:?} in:\n{}",
+            &expected_tokens[token_index..],
+            debug_tokens(input).unwrap_or_else(|e| e.to_string())
         );
     }
 
@@ -691,6 +1923,36 @@ mod tests {
         assert_eq!(parser.next_token().unwrap(), Some(expected));
     }
 
+    #[rstest]
+    #[case(0..3, Some(&b"foo"[..]))]
+    #[case(3..6, Some(&b"bar"[..]))]
+    #[case(6..6, Some(&b""[..]))]
+    #[case(4..7, None)]
+    #[case(Range { start: 5, end: 2 }, None)]
+    fn test_bstr_slice(#[case] range: Range<usize>, #[case] expected: Option<&[u8]>) {
+        let bstr = PhpBstr::new(b"foobar");
+        assert_eq!(bstr.slice(range), expected.map(PhpBstr::new));
+    }
+
+    #[test]
+    fn test_bstr_slice_range_kinds() {
+        let bstr = PhpBstr::new(b"foobar");
+        assert_eq!(bstr.slice(..), Some(bstr));
+        assert_eq!(bstr.slice(..=2), Some(PhpBstr::new(b"foo")));
+        assert_eq!(bstr.slice(4..), Some(PhpBstr::new(b"ar")));
+        assert_eq!(bstr.slice(7..), None);
+        assert_eq!(bstr.slice(..=6), None);
+    }
+
+    #[rstest]
+    #[case(0, b"", b"foobar")]
+    #[case(3, b"foo", b"bar")]
+    #[case(6, b"foobar", b"")]
+    fn test_bstr_split_at(#[case] mid: usize, #[case] head: &[u8], #[case] tail: &[u8]) {
+        let bstr = PhpBstr::new(b"foobar");
+        assert_eq!(bstr.split_at(mid), (PhpBstr::new(head), PhpBstr::new(tail)));
+    }
+
     #[rstest]
     #[case("s:5:\"hello\";", PhpToken::String(PhpBstr::new(b"hello")))]
     #[case("s:0:\"\";", PhpToken::String(PhpBstr::new(b"")))]
@@ -816,7 +2078,12 @@ mod tests {
         5,
         PhpToken::Integer(0)
     )]
-    #[case(b"a:12:{}i:7;", 12, 6, PhpToken::End)]
+    #[case(
+        b"a:12:{i:0;N;i:1;N;i:2;N;i:3;N;i:4;N;i:5;N;i:6;N;i:7;N;i:8;N;i:9;N;i:10;N;i:11;N;}",
+        12,
+        6,
+        PhpToken::Integer(0)
+    )]
     fn test_try_read_seq_start(
         #[case] input: &[u8],
         #[case] expected_elements: u32,
@@ -862,6 +2129,22 @@ mod tests {
         assert_eq!((prop.to_str().unwrap(), prop.visibility()), expected);
     }
 
+    #[rstest]
+    #[case(b"hello", ("hello", PhpVisibility::Public))]
+    #[case(b"\0MyClass\0privateVar", ("privateVar", PhpVisibility::Private))]
+    #[case(b"\0MySecretClass\0pv", ("pv", PhpVisibility::Private))]
+    #[case(b"\0*\0protectedVar", ("protectedVar", PhpVisibility::Protected))]
+    #[case(b"\0*\0pwho", ("pwho", PhpVisibility::Protected))]
+    fn test_demangle_property(#[case] input: &[u8], #[case] expected: (&str, PhpVisibility)) {
+        assert_eq!(demangle_property(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_demangle_property_invalid_utf8() {
+        let error = demangle_property(b"\0*\0\xff").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Utf8(_)));
+    }
+
     #[test]
     fn test_parse_null() {
         let input = b"N;";
@@ -909,6 +2192,53 @@ mod tests {
         validate_tokens(input, &expected);
     }
 
+    #[test]
+    fn test_parse_object_empty_class() {
+        let input = b"O:0:\"\":1:{s:1:\"a\";i:1;}";
+        let expected = [
+            PhpToken::Object {
+                class: PhpBstr::new(b""),
+                properties: 1,
+            },
+            PhpToken::String(PhpBstr::new(b"a")),
+            PhpToken::Integer(1),
+            PhpToken::End,
+        ];
+        validate_tokens(input, &expected);
+        assert!(validate(input).is_ok());
+    }
+
+    #[test]
+    fn test_parse_enum() {
+        let input = b"a:2:{i:0;E:11:\"Suit:Hearts\";i:1;E:17:\"App\\Suit:Diamonds\";}";
+        let expected = [
+            PhpToken::Array { elements: 2 },
+            PhpToken::Integer(0),
+            PhpToken::Enum {
+                class: PhpBstr::new(b"Suit"),
+                case: PhpBstr::new(b"Hearts"),
+            },
+            PhpToken::Integer(1),
+            PhpToken::Enum {
+                class: PhpBstr::new(b"App\\Suit"),
+                case: PhpBstr::new(b"Diamonds"),
+            },
+            PhpToken::End,
+        ];
+        validate_tokens(input, &expected);
+        assert!(validate(input).is_ok());
+    }
+
+    #[rstest]
+    #[case(b"E:6:\"Hearts\";", 2)]
+    #[case(b"E:0:\"\";", 2)]
+    fn test_parse_enum_without_case(#[case] input: &[u8], #[case] expected: usize) {
+        let error = PhpParser::new(input).read_token().unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::InvalidEnum { position } if *position == expected)
+        );
+    }
+
     #[test]
     fn test_parse_custom_object() {
         let input = b"C:5:\"Test2\":6:{foobar}";
@@ -921,21 +2251,33 @@ mod tests {
 
     #[test]
     fn test_parse_lowercase_reference() {
-        let input = b"r:1;";
-        let expected = [PhpToken::Reference {
-            id: 1,
-            kind: PhpReferenceKind::Repeated,
-        }];
+        let input = b"a:1:{i:0;r:1;}";
+        let expected = [
+            PhpToken::Array { elements: 1 },
+            PhpToken::Integer(0),
+            PhpToken::Reference {
+                id: PhpRef::new(1).unwrap(),
+                kind: PhpReferenceKind::Repeated,
+            },
+            PhpToken::End,
+        ];
         validate_tokens(input, &expected);
     }
 
     #[test]
     fn test_parse_uppercase_reference() {
-        let input = b"R:2;";
-        let expected = [PhpToken::Reference {
-            id: 2,
-            kind: PhpReferenceKind::Alias,
-        }];
+        let input = b"a:2:{i:0;N;i:1;R:2;}";
+        let expected = [
+            PhpToken::Array { elements: 2 },
+            PhpToken::Integer(0),
+            PhpToken::Null,
+            PhpToken::Integer(1),
+            PhpToken::Reference {
+                id: PhpRef::new(2).unwrap(),
+                kind: PhpReferenceKind::Alias,
+            },
+            PhpToken::End,
+        ];
         validate_tokens(input, &expected);
     }
 
@@ -964,6 +2306,297 @@ mod tests {
         validate_tokens(input, &expected);
     }
 
+    #[rstest]
+    #[case(b"\xEF\xBB\xBFi:1;", 3)]
+    #[case(b"   i:1;", 3)]
+    #[case(b"\xEF\xBB\xBF \t\r\ni:1;", 7)]
+    #[case(b"i:1;", 0)]
+    fn test_skip_leading_bom(#[case] input: &[u8], #[case] start: usize) {
+        let mut parser = PhpParser::new(input).skip_leading_bom();
+        assert_eq!(parser.position(), start);
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+        assert_eq!(parser.next_token().unwrap(), None);
+    }
+
+    #[rstest]
+    #[case(b"\xEF\xBB\xBFi:1;")]
+    #[case(b" i:1;")]
+    fn test_leading_bom_rejected_by_default(#[case] input: &[u8]) {
+        let error = PhpParser::new(input).next_token().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte { position: 0, .. }
+        ));
+    }
+
+    #[test]
+    fn test_skip_leading_bom_only_strips_prefix() {
+        let mut parser = PhpParser::new(b" i:1; i:2;").skip_leading_bom();
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+        assert!(parser.next_token().is_err());
+    }
+
+    fn ref_ids(input: &[u8]) -> Vec<(PhpToken<'_>, Option<u64>)> {
+        let mut parser = PhpParser::new(input);
+        let mut result = Vec::new();
+        while let Some(entry) = parser.next_token_with_ref_id().unwrap() {
+            result.push(entry);
+        }
+        result
+    }
+
+    #[test]
+    fn test_ref_ids_repeated_object() {
+        // PHP: $o = new stdClass; serialize([$o, $o]);
+        let input = b"a:2:{i:0;O:8:\"stdClass\":0:{}i:1;r:2;}";
+        let stdclass = PhpToken::Object {
+            class: PhpBstr::new(b"stdClass"),
+            properties: 0,
+        };
+        let repeated = PhpToken::Reference {
+            id: PhpRef::new(2).unwrap(),
+            kind: PhpReferenceKind::Repeated,
+        };
+        assert_eq!(
+            ref_ids(input),
+            vec![
+                (PhpToken::Array { elements: 2 }, Some(1)),
+                (PhpToken::Integer(0), None),
+                (stdclass, Some(2)),
+                (PhpToken::End, None),
+                (PhpToken::Integer(1), None),
+                (repeated, Some(3)),
+                (PhpToken::End, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ref_ids_skip_aliases() {
+        // PHP: $x = 1; $o = new stdClass; serialize([&$x, &$x, $o, $o]);
+        let input = b"a:4:{i:0;i:1;i:1;R:2;i:2;O:8:\"stdClass\":0:{}i:3;r:3;}";
+        let values: Vec<_> = ref_ids(input)
+            .into_iter()
+            .filter_map(|(token, id)| id.map(|id| (id, token)))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                (1, PhpToken::Array { elements: 4 }),
+                (2, PhpToken::Integer(1)),
+                (
+                    3,
+                    PhpToken::Object {
+                        class: PhpBstr::new(b"stdClass"),
+                        properties: 0
+                    }
+                ),
+                (
+                    4,
+                    PhpToken::Reference {
+                        id: PhpRef::new(3).unwrap(),
+                        kind: PhpReferenceKind::Repeated
+                    }
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ref_ids_self_reference() {
+        // PHP: $o = new stdClass; $o->self = $o; serialize($o);
+        let input = b"O:8:\"stdClass\":1:{s:4:\"self\";r:1;}";
+        let ids: Vec<_> = ref_ids(input).into_iter().map(|(_, id)| id).collect();
+        assert_eq!(ids, vec![Some(1), None, Some(2), None]);
+    }
+
+    #[test]
+    fn test_ref_ids_nested_and_top_level_sequence() {
+        let input = b"a:2:{s:1:\"a\";a:1:{i:0;s:1:\"x\";}s:1:\"b\";C:3:\"Foo\":1:{a}}d:1.5;";
+        let ids: Vec<_> = ref_ids(input).into_iter().map(|(_, id)| id).collect();
+        assert_eq!(
+            ids,
+            vec![
+                Some(1), // outer array
+                None,    // "a"
+                Some(2), // inner array
+                None,    // 0
+                Some(3), // "x"
+                None,    // end
+                None,    // "b"
+                Some(4), // custom object
+                None,    // end
+                Some(5), // 1.5
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ref_ids_tracked_through_fast_paths() {
+        let input = b"a:3:{i:0;s:1:\"x\";i:1;d:1.5;i:2;a:0:{}}";
+        let mut parser = PhpParser::new(input);
+        assert_eq!(parser.try_read_seq_start(), Some(3));
+        assert_eq!(parser.try_read_i64(), Some(0));
+        assert_eq!(parser.try_read_str(), Some(PhpBstr::new(b"x")));
+        assert_eq!(parser.try_read_i64(), Some(1));
+        assert_eq!(parser.try_read_f64(), Some(1.5));
+        assert_eq!(parser.value_count(), 3);
+        assert_eq!(parser.try_read_i64(), Some(2));
+        assert_eq!(
+            parser.next_token_with_ref_id().unwrap(),
+            Some((PhpToken::Array { elements: 0 }, Some(4)))
+        );
+        assert!(parser.try_read_end());
+        assert!(parser.try_read_end());
+        assert_eq!(parser.value_count(), 4);
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let input = b"i:1;a:2:{i:0;O:3:\"Foo\":1:{s:1:\"a\";a:0:{}}i:1;s:1:\"x\";}N;";
+        let mut parser = PhpParser::new(input);
+        parser.skip_value().unwrap();
+        assert_eq!(parser.position(), 4);
+        parser.skip_value().unwrap();
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Null));
+    }
+
+    #[rstest]
+    #[case(b"}")]
+    #[case(b"a:1:{i:0;N;}}")]
+    fn test_skip_value_rejects_unbalanced_end(#[case] input: &[u8]) {
+        let mut parser = PhpParser::new(input);
+        let mut result = parser.skip_value();
+        if result.is_ok() {
+            result = parser.skip_value();
+        }
+
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::UnexpectedByte { found: b'}', .. }
+        ));
+    }
+
+    #[test]
+    fn test_skip_value_checks_contents() {
+        let mut parser = PhpParser::new(b"a:1:{i:0;a:1:{d:1.5;N;}}");
+        assert!(matches!(
+            parser.skip_value().unwrap_err().kind(),
+            ErrorKind::InvalidKey {
+                kind: PhpTokenKind::Float,
+                position: 14
+            }
+        ));
+
+        let mut parser = PhpParser::new(b"a:1:{i:0;a:2:{i:0;N;}i:1;N;}");
+        assert!(matches!(
+            parser.skip_value().unwrap_err().kind(),
+            ErrorKind::CountMismatch {
+                declared: 2,
+                found: 1,
+                position: 20
+            }
+        ));
+    }
+
+    #[test]
+    fn test_skip_value_truncated() {
+        let mut parser = PhpParser::new(b"a:1:{i:0;a:0:{}");
+        assert!(matches!(
+            parser.skip_value().unwrap_err().kind(),
+            ErrorKind::Eof
+        ));
+    }
+
+    #[test]
+    fn test_seek_to_field() {
+        let input = b"O:7:\"Example\":3:{s:4:\"name\";a:1:{i:0;O:3:\"Foo\":1:{s:1:\"a\";s:1:\"}\";}}s:12:\"\0Example\0age\";i:42;s:4:\"tags\";a:0:{}}";
+        let mut parser = PhpParser::new(input);
+        assert!(parser.seek_to_field(b"age").unwrap());
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(42)));
+
+        // The remaining entries are left for the caller
+        assert_eq!(
+            parser.next_token().unwrap(),
+            Some(PhpToken::String(PhpBstr::new(b"tags")))
+        );
+    }
+
+    #[test]
+    fn test_seek_to_field_absent() {
+        let input = b"a:2:{s:1:\"a\";a:1:{s:1:\"b\";i:1;}s:1:\"c\";i:2;}i:3;";
+        let mut parser = PhpParser::new(input);
+        assert!(!parser.seek_to_field(b"b").unwrap());
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(3)));
+    }
+
+    #[test]
+    fn test_seek_to_field_integer_key() {
+        let mut parser = PhpParser::new(b"a:2:{i:0;s:1:\"a\";i:10;s:1:\"b\";}");
+        assert!(parser.seek_to_field(b"10").unwrap());
+        assert_eq!(
+            parser.next_token().unwrap(),
+            Some(PhpToken::String(PhpBstr::new(b"b")))
+        );
+    }
+
+    #[test]
+    fn test_seek_to_field_requires_container() {
+        let mut parser = PhpParser::new(b"i:1;");
+        assert!(matches!(
+            parser.seek_to_field(b"a").unwrap_err().kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'i',
+                position: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_next_token_spanned() {
+        let input = b"a:2:{i:0;d:1.5;s:3:\"key\";O:3:\"Foo\":1:{s:1:\"a\";N;}}b:1;";
+        let mut parser = PhpParser::new(input);
+        let mut spans = Vec::new();
+        while let Some((token, span)) = parser.next_token_spanned().unwrap() {
+            spans.push((token, span));
+        }
+
+        let expected = [
+            (PhpToken::Array { elements: 2 }, 0..5),
+            (PhpToken::Integer(0), 5..9),
+            (PhpToken::Float(1.5), 9..15),
+            (PhpToken::String(PhpBstr::new(b"key")), 15..25),
+            (
+                PhpToken::Object {
+                    class: PhpBstr::new(b"Foo"),
+                    properties: 1,
+                },
+                25..38,
+            ),
+            (PhpToken::String(PhpBstr::new(b"a")), 38..46),
+            (PhpToken::Null, 46..48),
+            (PhpToken::End, 48..49),
+            (PhpToken::End, 49..50),
+            (PhpToken::Boolean(true), 50..54),
+        ];
+        assert_eq!(spans, expected);
+    }
+
+    #[test]
+    fn test_next_token_spanned_excludes_line_breaks() {
+        let input = b"i:1;\r\ni:2;";
+        let mut parser = PhpParser::new(input).skip_line_breaks(true);
+        assert_eq!(
+            parser.next_token_spanned().unwrap(),
+            Some((PhpToken::Integer(1), 0..4))
+        );
+        assert_eq!(
+            parser.next_token_spanned().unwrap(),
+            Some((PhpToken::Integer(2), 6..10))
+        );
+        assert_eq!(parser.next_token_spanned().unwrap(), None);
+    }
+
     fn error_case(input: &[u8]) -> Result<(), Error> {
         let mut parser = PhpParser::new(input);
         loop {
@@ -1017,11 +2650,54 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_carriage_return_is_rejected_by_default() {
+        let mut parser = PhpParser::new(b"i:1;\r\ni:2;");
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(1)));
+        let error = parser.next_token().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'\r',
+                position: 4
+            }
+        ));
+    }
+
+    #[rstest]
+    #[case(b"i:1;\r\ni:2;")]
+    #[case(b"i:1;\ni:2;")]
+    #[case(b"\r\ni:1;\r\n\r\ni:2;\r\n")]
+    fn test_skip_line_breaks(#[case] input: &[u8]) {
+        let mut parser = PhpParser::new(input).skip_line_breaks(true);
+        let expected = [PhpToken::Integer(1), PhpToken::Integer(2)];
+        for token in expected {
+            assert_eq!(parser.next_token().unwrap(), Some(token));
+        }
+        assert_eq!(parser.next_token().unwrap(), None);
+        assert_eq!(parser.position(), input.len());
+    }
+
+    #[test]
+    fn test_skip_line_breaks_in_containers() {
+        let input = b"a:2:{i:0;s:1:\"a\";\r\ni:1;s:1:\"b\";\r\n}";
+        let mut parser = PhpParser::new(input).skip_line_breaks(true);
+        assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::Array));
+        assert_eq!(parser.try_read_seq_start(), Some(2));
+        assert_eq!(parser.try_read_i64(), Some(0));
+        assert_eq!(parser.try_read_str(), Some(PhpBstr::new(b"a")));
+        assert_eq!(parser.try_read_i64(), Some(1));
+        assert_eq!(
+            parser.read_token().unwrap(),
+            PhpToken::String(PhpBstr::new(b"b"))
+        );
+        assert!(parser.try_read_end());
+    }
+
     #[rstest]
     #[case(b"b:2;")]
     #[case(b"b:3;")]
     #[case(b"b:-1;")]
-    #[case(b"b:10;")]
     #[case(b"b:text;")]
     fn test_invalid_boolean_values(#[case] input: &[u8]) {
         assert!(
@@ -1031,6 +2707,18 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(b"b:10;")]
+    #[case(b"b:01;")]
+    #[case(b"b:1000;")]
+    fn test_boolean_extra_digits(#[case] input: &[u8]) {
+        let error = error_case(input).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::InvalidBoolean { position: 2 }
+        ));
+    }
+
     #[rstest]
     #[case(b"i:abc;")]
     #[case(b"i:-;")]
@@ -1077,26 +2765,182 @@ mod tests {
     }
 
     #[rstest]
-    #[case(b"s:10:\"hello\";")] // String length exceeds available data
-    #[case(b"s:3:\"hello\";")] // String length is less than actual content
-    #[case(b"s:5:\"hello;")] // Missing closing quote
-    #[case(b"s:5:hello\";")] // Missing opening quote
-    #[case(b"s:1000:\"hello\";")] // Extreme case: length much larger than input
-    fn test_string_content_mismatch(#[case] input: &[u8]) {
-        assert!(
-            error_case(input).is_err(),
-            "Expected an error for string content mismatch: {}",
-            String::from_utf8_lossy(input)
+    #[case(b"s:10:\"hello\";", Some(10))] // String length exceeds available data
+    #[case(b"s:3:\"hello\";", None)] // String length is less than actual content
+    #[case(b"s:5:\"hello;", None)] // Missing closing quote
+    #[case(b"s:5:hello\";", None)] // Missing opening quote
+    #[case(b"s:1000:\"hello\";", Some(1000))] // Extreme case: length much larger than input
+    fn test_string_content_mismatch(#[case] input: &[u8], #[case] truncated: Option<u32>) {
+        let error = error_case(input).unwrap_err();
+        match truncated {
+            Some(expected) => assert!(
+                matches!(
+                    error.kind(),
+                    ErrorKind::TruncatedString { declared, position: 2 } if *declared == expected
+                ),
+                "Expected a truncated string for {}: {error:?}",
+                String::from_utf8_lossy(input)
+            ),
+            None => assert!(
+                matches!(error.kind(), ErrorKind::MissingQuotes { .. }),
+                "Expected missing quotes for {}: {error:?}",
+                String::from_utf8_lossy(input)
+            ),
+        }
+    }
+
+    #[test]
+    fn test_truncated_custom_object_payload() {
+        let error = error_case(b"C:3:\"Foo\":10:{abc}").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::TruncatedString {
+                declared: 10,
+                position: 14
+            }
+        ));
+    }
+
+    #[test]
+    fn test_key_and_value_tokens() {
+        let mut parser = PhpParser::new(b"O:3:\"Foo\":2:{s:1:\"a\";a:1:{i:-1;N;}s:1:\"b\";d:0.5;}");
+        assert!(matches!(
+            parser.next_value_token().unwrap(),
+            PhpToken::Object { properties: 2, .. }
+        ));
+        assert_eq!(
+            parser.next_key_token().unwrap(),
+            Some(PhpArrayKey::String(PhpBstr::new(b"a")))
+        );
+        assert_eq!(
+            parser.next_value_token().unwrap(),
+            PhpToken::Array { elements: 1 }
+        );
+        assert_eq!(
+            parser.next_key_token().unwrap(),
+            Some(PhpArrayKey::Integer(-1))
+        );
+        assert_eq!(parser.next_value_token().unwrap(), PhpToken::Null);
+        assert_eq!(parser.next_key_token().unwrap(), None);
+        assert_eq!(
+            parser.next_key_token().unwrap(),
+            Some(PhpArrayKey::String(PhpBstr::new(b"b")))
         );
+        assert_eq!(parser.next_value_token().unwrap(), PhpToken::Float(0.5));
+        assert_eq!(parser.next_key_token().unwrap(), None);
+        assert!(parser.is_done());
     }
 
     #[rstest]
-    #[case(b"s:10:\"hello\";")]
-    #[case(b"s:1000:\"hello\";")]
-    fn test_truncated_string_reports_eof(#[case] input: &[u8]) {
+    #[case(b"a:1:{d:1.5;i:1;}", PhpTokenKind::Float)]
+    #[case(b"a:1:{N;i:1;}", PhpTokenKind::Null)]
+    #[case(b"a:1:{a:0:{}i:1;}", PhpTokenKind::Array)]
+    fn test_invalid_key_token(#[case] input: &[u8], #[case] expected: PhpTokenKind) {
         let mut parser = PhpParser::new(input);
-        let error = parser.next_token().unwrap_err();
-        assert!(matches!(error.kind(), ErrorKind::Eof));
+        parser.next_value_token().unwrap();
+        let error = parser.next_key_token().unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::InvalidKey { kind, position: 5 } if *kind == expected),
+            "{error:?}"
+        );
+        assert!(error.is_parse_error());
+        assert_eq!(parser.peek_token().unwrap(), Some(expected));
+    }
+
+    #[test]
+    fn test_value_token_at_end() {
+        let mut parser = PhpParser::new(b"a:2:{i:0;i:1;i:1;}");
+        parser.next_value_token().unwrap();
+        parser.next_key_token().unwrap();
+        parser.next_value_token().unwrap();
+        parser.next_key_token().unwrap();
+        let error = parser.next_value_token().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'}',
+                position: 17
+            }
+        ));
+    }
+
+    #[test]
+    fn test_mangle_property_roundtrip() {
+        let names = ["a", "isActive", "name with spaces", "ünïcödé", "*", ""];
+        let visibilities = [
+            PhpVisibility::Public,
+            PhpVisibility::Protected,
+            PhpVisibility::Private,
+        ];
+        for name in names {
+            for visibility in visibilities {
+                for class in [None, Some("Foo"), Some("App\\Model\\User")] {
+                    let mangled = mangle_property(name, visibility, class);
+                    if visibility == PhpVisibility::Public && name.is_empty() {
+                        assert!(mangled.is_empty());
+                        continue;
+                    }
+
+                    assert_eq!(
+                        demangle_property(&mangled).unwrap(),
+                        (name, visibility),
+                        "{}",
+                        mangled.escape_ascii()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_debug_tokens() {
+        let input = b"a:3:{i:0;C:3:\"Foo\":2:{xy}s:1:\"\0\";a:0:{}i:1;a:1:{i:0;E:11:\"Suit:Hearts\";}}b:1;R:1;";
+        assert_eq!(
+            debug_tokens(input).unwrap(),
+            r#"a:3 {
+  i:0 => C:"Foo" {xy}
+  s:"\x00" => a:0 {
+  }
+  i:1 => a:1 {
+    i:0 => E:"Suit:Hearts"
+  }
+}
+b:true
+R:1
+"#
+        );
+        assert!(debug_tokens(b"a:1:{i:0;").is_err());
+    }
+
+    #[test]
+    fn test_truncated_class_name() {
+        let error = error_case(b"O:30:\"Foo").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::TruncatedString { declared: 30, .. }
+        ));
+    }
+
+    #[rstest]
+    #[case(b"O:3:\"Foobar\":1:{s:1:\"a\";N;}", 3, 6)]
+    #[case(b"O:30:\"Foo\":0:{}", 30, 3)]
+    #[case(b"O:4:\"Foo\":0:{}", 4, 3)]
+    #[case(b"C:2:\"Foo\":0:{}", 2, 3)]
+    fn test_class_name_length_mismatch(
+        #[case] input: &[u8],
+        #[case] expected_declared: u32,
+        #[case] expected_found: u32,
+    ) {
+        let error = error_case(input).unwrap_err();
+        assert!(
+            matches!(
+                error.kind(),
+                ErrorKind::StringLengthMismatch { declared, found, position: 2 }
+                    if *declared == expected_declared && *found == expected_found
+            ),
+            "{error:?}"
+        );
+        assert!(error.is_parse_error());
     }
 
     #[rstest]
@@ -1124,6 +2968,64 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(b"a:9999999999:{}")]
+    #[case(b"a:4294967296:{}")]
+    #[case(b"s:4294967296:\"\";")]
+    #[case(b"O:3:\"Foo\":99999999999:{}")]
+    fn test_length_overflow(#[case] input: &[u8]) {
+        let error = error_case(input).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::LengthOverflow { .. }),
+            "Expected length overflow for {}: {error:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    #[rstest]
+    #[case(b"a:4000000000:{}", 4000000000, 0, 2)]
+    #[case(b"a:2:{i:0;N;}", 2, 1, 2)]
+    #[case(b"O:3:\"Foo\":100:{s:1:\"a\";N;}", 100, 1, 10)]
+    #[case(b"a:1:{i:0;a:3:{}}", 3, 0, 11)]
+    fn test_declared_count_exceeds_input(
+        #[case] input: &[u8],
+        #[case] declared: u32,
+        #[case] found: u32,
+        #[case] position: usize,
+    ) {
+        let error = error_case(input).unwrap_err();
+        assert!(
+            matches!(
+                error.kind(),
+                ErrorKind::CountMismatch { declared: d, found: f, position: p }
+                    if *d == declared && *f == found && *p == position
+            ),
+            "unexpected error for {}: {error:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
+    #[test]
+    fn test_try_read_seq_start_rejects_impossible_count() {
+        let mut parser = PhpParser::new(b"a:4000000000:{}");
+        assert_eq!(parser.try_read_seq_start(), None);
+        assert_eq!(parser.position(), 0);
+    }
+
+    #[rstest]
+    #[case(b"i:9223372036854775808;", 2)]
+    #[case(b"i:-9223372036854775809;", 2)]
+    #[case(b"a:1:{i:0;i:99999999999999999999;}", 11)]
+    #[case(b"r:9223372036854775808;", 2)]
+    fn test_integer_overflow(#[case] input: &[u8], #[case] expected: usize) {
+        let error = error_case(input).unwrap_err();
+        assert!(
+            matches!(error.kind(), ErrorKind::IntegerOverflow { position } if *position == expected),
+            "Expected integer overflow for {}: {error:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+
     #[rstest]
     #[case(b"O:3:\"Foo\":;")]
     #[case(b"O:3:\"Foo\"::{")]
@@ -1154,6 +3056,63 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(b"r:0;", 0)]
+    #[case(b"R:0;", 0)]
+    #[case(b"r:-1;", -1)]
+    #[case(b"R:-9223372036854775808;", i64::MIN)]
+    fn test_reference_below_one(#[case] input: &[u8], #[case] expected: i64) {
+        let error = PhpParser::new(input).read_token().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::InvalidReference { index, position: 2 } if *index == expected
+        ));
+        assert!(error.is_parse_error());
+    }
+
+    #[rstest]
+    #[case(b"a:1:{i:0;r:1;}", 1)]
+    #[case(b"a:2:{i:0;N;i:1;R:2;}", 2)]
+    fn test_reference_index(#[case] input: &[u8], #[case] expected: u64) {
+        let mut parser = PhpParser::new(input);
+        let mut ids = Vec::new();
+        while let Some(token) = parser.next_token().unwrap() {
+            if let PhpToken::Reference { id, .. } = token {
+                ids.push(id.index());
+            }
+        }
+        assert_eq!(ids, [expected]);
+    }
+
+    #[rstest]
+    #[case(b"r:1;", 1, 2)]
+    #[case(b"R:9223372036854775807;", i64::MAX as u64, 2)]
+    #[case(b"a:1:{i:0;r:2;}", 2, 11)]
+    #[case(b"a:2:{i:0;N;i:1;r:3;}", 3, 17)] // the r: token would be value 3
+    #[case(b"a:2:{i:0;N;i:1;R:9999999999;}", 9_999_999_999, 17)]
+    #[case(b"a:2:{i:0;r:3;i:1;i:5;}", 3, 11)]
+    fn test_forward_reference(
+        #[case] input: &[u8],
+        #[case] expected: u64,
+        #[case] expected_position: usize,
+    ) {
+        let mut parser = PhpParser::new(input);
+        let error = loop {
+            if let Err(error) = parser.read_token() {
+                break error;
+            }
+        };
+        assert!(
+            matches!(
+                error.kind(),
+                ErrorKind::ForwardReference { index, position }
+                    if *index == expected && *position == expected_position
+            ),
+            "{error:?}"
+        );
+        assert!(error.is_parse_error());
+    }
+
     #[test]
     fn test_position_tracking() {
         let input = b"i:42;s:5:\"hello\";";
@@ -1232,6 +3191,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_progress() {
+        let input = b"i:42;s:5:\"hello\";";
+        let mut parser = PhpParser::new(input);
+        assert_eq!(parser.total_len(), 17);
+        assert_eq!(parser.progress(), 0.0);
+
+        parser.peek_token().unwrap();
+        assert_eq!(parser.total_len(), 17);
+        assert_eq!(parser.progress(), 0.0);
+
+        parser.read_token().unwrap();
+        assert_eq!(parser.total_len(), 17);
+        assert_eq!(parser.progress(), 5.0 / 17.0);
+
+        parser.read_token().unwrap();
+        assert_eq!(parser.progress(), 1.0);
+
+        assert_eq!(PhpParser::new(b"").progress(), 1.0);
+    }
+
     #[test]
     fn test_multiple_peeks() {
         let input = b"i:42;s:5:\"hello\";";
@@ -1261,6 +3241,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_is_done() {
+        let input = b"a:1:{i:0;s:1:\"x\";}\r\n";
+        let mut parser = PhpParser::new(input).skip_line_breaks(true);
+        let mut tokens = 0;
+        while !parser.is_done() {
+            assert!(parser.peek_token().unwrap().is_some());
+            parser.read_token().unwrap();
+            tokens += 1;
+        }
+        assert_eq!(tokens, 4);
+        assert_eq!(parser.peek_token().unwrap(), None);
+        assert!(parser.is_done());
+
+        assert!(PhpParser::new(b"").is_done());
+        assert!(!PhpParser::new(b"?").is_done());
+    }
+
+    #[test]
+    fn test_peek_string_len() {
+        let input = b"a:3:{i:0;s:3:\"abc\";s:4:\"long\";s:10:\"0123456789\";i:2;O:3:\"Foo\":0:{}}";
+        let mut parser = PhpParser::new(input);
+        let mut lengths = Vec::new();
+        while !parser.is_done() {
+            let before = parser.position();
+            if let Some(len) = parser.peek_string_len().unwrap() {
+                lengths.push(len);
+                assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::String));
+            }
+            assert_eq!(parser.position(), before);
+            parser.read_token().unwrap();
+        }
+        assert_eq!(lengths, vec![3, 4, 10]);
+        assert_eq!(parser.peek_string_len().unwrap(), None);
+    }
+
+    #[test]
+    fn test_peek_string_len_errors() {
+        let error = PhpParser::new(b"s:x:\"\";").peek_string_len().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::InvalidNumber { position: 2 }
+        ));
+
+        let error = PhpParser::new(b"s:99999999999:\"\";")
+            .peek_string_len()
+            .unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::LengthOverflow { .. }));
+
+        // The contents aren't checked until the string is read.
+        let mut parser = PhpParser::new(b"s:5:\"ab\";");
+        assert_eq!(parser.peek_string_len().unwrap(), Some(5));
+        assert!(parser.next_token().is_err());
+    }
+
+    #[test]
+    fn test_peek_then_reread() {
+        let input = b"a:1:{i:0;s:5:\"hello\";}";
+        let mut parser = PhpParser::new(input);
+        assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::Array));
+        assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::Array));
+        assert_eq!(parser.position(), 0);
+        assert_eq!(
+            parser.next_token().unwrap(),
+            Some(PhpToken::Array { elements: 1 })
+        );
+
+        assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::Integer));
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::Integer(0)));
+        assert_eq!(parser.peek_token().unwrap(), Some(PhpTokenKind::String));
+        assert_eq!(
+            parser.next_token().unwrap(),
+            Some(PhpToken::String(PhpBstr::new(b"hello")))
+        );
+        assert_eq!(parser.next_token().unwrap(), Some(PhpToken::End));
+        assert_eq!(parser.value_count(), 2);
+    }
+
     #[test]
     fn test_peek_after_next() {
         let input = b"i:42;s:5:\"hello\";b:1;";
@@ -1376,6 +3434,274 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_class_names() {
+        let input = b"a:3:{i:0;O:3:\"Foo\":1:{s:1:\"b\";O:3:\"Bar\":0:{}}i:1;C:3:\"Baz\":2:{xy}i:2;O:3:\"Bar\":0:{}}";
+        assert_eq!(
+            class_names(input).unwrap(),
+            vec![
+                PhpBstr::new(b"Foo"),
+                PhpBstr::new(b"Bar"),
+                PhpBstr::new(b"Baz")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_class_names_empty() {
+        let input = b"a:2:{i:0;O:0:\"\":0:{}i:1;O:3:\"Foo\":0:{}}";
+        let names = class_names(input).unwrap();
+        assert_eq!(names, vec![PhpBstr::new(b""), PhpBstr::new(b"Foo")]);
+        assert_eq!(names[0].to_str().unwrap(), "");
+    }
+
+    #[test]
+    fn test_class_names_non_utf8() {
+        let input =
+            b"a:3:{i:0;O:2:\"\xff\xfe\":0:{}i:1;C:2:\"\xff\xfe\":1:{x}i:2;O:2:\"\xff\xfd\":0:{}}";
+        let names = class_names(input).unwrap();
+        assert_eq!(
+            names,
+            vec![PhpBstr::new(b"\xff\xfe"), PhpBstr::new(b"\xff\xfd")]
+        );
+        assert!(matches!(
+            names[0].to_str().unwrap_err().kind(),
+            ErrorKind::Utf8(_)
+        ));
+    }
+
+    #[rstest]
+    #[case(b"N;")]
+    #[case(b"s:3:\"foo\";")]
+    #[case(b"a:0:{}")]
+    #[case(b"a:2:{i:0;a:1:{s:1:\"a\";b:1;}i:1;O:3:\"Foo\":1:{s:6:\"\0*\0bar\";r:2;}}")]
+    #[case(b"C:3:\"Foo\":2:{xy}")]
+    fn test_validate(#[case] input: &[u8]) {
+        validate(input).unwrap();
+    }
+
+    #[test]
+    fn test_next_string_bytes() {
+        let long = "x".repeat(150);
+        let input = format!("a:3:{{i:0;s:2:\"ab\";s:4:\"\0*\0k\";s:150:\"{long}\";i:1;b:1;}}");
+        let mut parser = PhpParser::new(input.as_bytes());
+        let mut strings = Vec::new();
+        loop {
+            if let Some(bytes) = parser.next_string_bytes().unwrap() {
+                strings.push(bytes);
+            } else if parser.next_token().unwrap().is_none() {
+                break;
+            }
+        }
+        assert_eq!(strings, [&b"ab"[..], b"\0*\0k", long.as_bytes()]);
+    }
+
+    #[test]
+    fn test_next_string_bytes_error() {
+        let mut parser = PhpParser::new(b"s:5:\"abc\";");
+        let error = parser.next_string_bytes().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::TruncatedString { declared: 5, .. }
+        ));
+        assert_eq!(parser.position(), 0);
+    }
+
+    #[test]
+    fn test_checkpoint_restore() {
+        let input = b"a:3:{i:0;s:3:\"foo\";i:1;R:2;i:2;a:1:{i:0;N;}}";
+        let mut parser = PhpParser::new(input);
+        parser.read_token().unwrap();
+        parser.read_token().unwrap();
+
+        let checkpoint = parser.checkpoint();
+        assert_eq!(checkpoint.position(), 9);
+        let mut first = Vec::new();
+        while let Some(token) = parser.next_token().unwrap() {
+            first.push(token);
+        }
+        assert_eq!(first.len(), 9);
+
+        parser.restore(checkpoint);
+        assert_eq!(parser.position(), 9);
+        let mut second = Vec::new();
+        while let Some(token) = parser.next_token().unwrap() {
+            second.push(token);
+        }
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_checkpoint_after_bom() {
+        let mut parser = PhpParser::new(b"\xEF\xBB\xBFi:1;i:2;").skip_leading_bom();
+        parser.read_token().unwrap();
+        let checkpoint = parser.checkpoint();
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(2));
+        parser.restore(checkpoint);
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(2));
+    }
+
+    #[rstest]
+    #[case(b"i:1;i:x;", 4)]
+    #[case(b"i:1;s:5:\"ab\";", 4)]
+    #[case(b"i:1;b:2;", 4)]
+    #[case(b"i:1;d:1.5", 4)]
+    #[case(b"i:1;q:1;", 4)]
+    #[case(b"a:2:{i:0;i:1;i:1;b:2;}", 17)]
+    #[case(b"a:1:{i:0;O:3:\"Foo\":1:{}}", 9)]
+    fn test_position_after_error(#[case] input: &[u8], #[case] expected: usize) {
+        let mut parser = PhpParser::new(input);
+        let error = loop {
+            match parser.read_token() {
+                Ok(_) => {}
+                Err(error) => break error,
+            }
+        };
+
+        assert_eq!(parser.position(), expected);
+        assert!(error.position().unwrap_or(expected) >= expected);
+
+        // Nothing was consumed, so reading again fails the same way.
+        let again = parser.read_token().unwrap_err();
+        assert_eq!(again.to_string(), error.to_string());
+        assert_eq!(parser.position(), expected);
+    }
+
+    #[test]
+    fn test_position_after_skip_value_error() {
+        let mut parser = PhpParser::new(b"a:1:{i:0;N;}");
+        parser.read_token().unwrap();
+        parser.read_token().unwrap();
+        parser.skip_value().unwrap();
+        assert_eq!(parser.position(), 11);
+
+        assert!(parser.skip_value().is_err());
+        assert_eq!(parser.position(), 11);
+        assert_eq!(parser.read_token().unwrap(), PhpToken::End);
+        assert!(parser.is_done());
+    }
+
+    #[test]
+    fn test_position_after_seek_to_field_error() {
+        let mut parser = PhpParser::new(b"i:1;");
+        assert!(parser.seek_to_field(b"a").is_err());
+        assert_eq!(parser.position(), 0);
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(1));
+
+        let mut parser = PhpParser::new(b"a:1:{N;i:1;}");
+        assert!(parser.seek_to_field(b"a").is_err());
+        assert_eq!(parser.position(), 5);
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Null);
+    }
+
+    #[rstest]
+    #[case(b"N;", PhpTokenKind::Null)]
+    #[case(b"b:1;", PhpTokenKind::Boolean)]
+    #[case(b"i:-5;", PhpTokenKind::Integer)]
+    #[case(b"d:0.5;", PhpTokenKind::Float)]
+    #[case(b"s:1:\"a\";", PhpTokenKind::String)]
+    #[case(b"a:0:{}", PhpTokenKind::Array)]
+    #[case(b"O:8:\"stdClass\":0:{}", PhpTokenKind::Object)]
+    #[case(b"C:3:\"Foo\":0:{}", PhpTokenKind::CustomObject)]
+    #[case(b"E:11:\"Suit:Hearts\";", PhpTokenKind::Enum)]
+    #[case(b"r:1;", PhpTokenKind::Reference(PhpReferenceKind::Repeated))]
+    #[case(b"R:1;", PhpTokenKind::Reference(PhpReferenceKind::Alias))]
+    #[case(b"a:1:{i:0;", PhpTokenKind::Array)]
+    fn test_top_level_kind(#[case] input: &[u8], #[case] expected: PhpTokenKind) {
+        assert_eq!(top_level_kind(input).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(b"", ErrorKind::Eof)]
+    #[case(b"}", ErrorKind::UnexpectedByte { found: b'}', position: 0 })]
+    #[case(b"x:1;", ErrorKind::UnexpectedByte { found: b'x', position: 0 })]
+    fn test_top_level_kind_errors(#[case] input: &[u8], #[case] expected: ErrorKind) {
+        let error = top_level_kind(input).unwrap_err();
+        assert_eq!(error.to_string(), Error::from(expected).to_string());
+    }
+
+    #[test]
+    fn test_validate_truncated() {
+        assert!(matches!(
+            validate(b"a:1:{i:0;i:12345;").unwrap_err().kind(),
+            ErrorKind::Eof
+        ));
+        assert!(validate(b"s:5:\"ab").is_err());
+        assert!(matches!(validate(b"").unwrap_err().kind(), ErrorKind::Eof));
+    }
+
+    #[test]
+    fn test_validate_trailing_data() {
+        assert!(matches!(
+            validate(b"a:0:{}xyz").unwrap_err().kind(),
+            ErrorKind::TrailingData { position: 6 }
+        ));
+        assert!(matches!(
+            validate(b"N;N;").unwrap_err().kind(),
+            ErrorKind::TrailingData { position: 2 }
+        ));
+    }
+
+    #[test]
+    fn test_validate_count_mismatch() {
+        assert!(matches!(
+            validate(b"a:2:{i:0;N;}i:0;i:0;").unwrap_err().kind(),
+            ErrorKind::CountMismatch {
+                declared: 2,
+                found: 1,
+                position: 11
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate_invalid_key() {
+        assert!(matches!(
+            validate(b"a:1:{d:1.5;N;}").unwrap_err().kind(),
+            ErrorKind::InvalidKey {
+                kind: PhpTokenKind::Float,
+                position: 5
+            }
+        ));
+    }
+
+    #[test]
+    fn test_validate_depth_limit() {
+        let depth = DEFAULT_MAX_DEPTH + 1;
+        let mut input = "a:1:{i:0;".repeat(depth).into_bytes();
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        assert!(matches!(
+            validate(&input).unwrap_err().kind(),
+            ErrorKind::DepthLimitExceeded { .. }
+        ));
+
+        let depth = DEFAULT_MAX_DEPTH;
+        let mut input = "a:1:{i:0;".repeat(depth).into_bytes();
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        validate(&input).unwrap();
+    }
+
+    #[test]
+    fn test_class_names_enums() {
+        let input = b"a:3:{i:0;E:11:\"Suit:Hearts\";i:1;O:3:\"Foo\":0:{}i:2;E:11:\"Suit:Spades\";}";
+        assert_eq!(
+            class_names(input).unwrap(),
+            vec![PhpBstr::new(b"Suit"), PhpBstr::new(b"Foo")]
+        );
+    }
+
+    #[test]
+    fn test_class_names_without_objects() {
+        assert_eq!(class_names(b"a:1:{i:0;s:1:\"O\";}").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_class_names_reports_errors() {
+        assert!(class_names(b"a:1:{i:0;O:3:\"Foo\"").is_err());
+    }
+
     #[test]
     fn test_readme() -> Result<(), Box<dyn std::error::Error>> {
         let serialized = b"O:7:\"Example\":5:{s:4:\"name\";s:8:\"John Doe\";s:12:\"\0Example\0age\";i:42;s:11:\"\0*\0isActive\";b:1;s:6:\"scores\";a:3:{i:0;d:95.5;i:1;d:88.0;i:2;d:92.3;}s:8:\"metadata\";a:2:{s:2:\"id\";i:12345;s:4:\"tags\";a:3:{i:0;s:3:\"php\";i:1;s:4:\"rust\";i:2;s:13:\"serialization\";}}}";
@@ -15,6 +15,27 @@ pub enum StructStyle {
     Object,
 }
 
+/// How floats are written.
+///
+/// PHP 7.1 started to serialize floats with the shortest representation that
+/// reads back to the same value. Earlier versions wrote a fixed number of
+/// significant digits, set by the `serialize_precision` ini setting (17 by
+/// default) or, before PHP 5.3, by `precision` (14 by default).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatFormat {
+    /// The shortest representation that reads back to the same value
+    /// (`d:0.1;`), like PHP 7.1 and later.
+    #[default]
+    Shortest,
+
+    /// 17 significant digits (`d:0.10000000000000001;`), which always reads
+    /// back to the same value.
+    Digits17,
+
+    /// 14 significant digits (`d:0.1;`), which may lose precision.
+    Digits14,
+}
+
 /// A serializer for the PHP serialization format.
 ///
 /// Produces bytes that can be read back by [`crate::PhpDeserializer`], allowing
@@ -23,6 +44,8 @@ pub enum StructStyle {
 pub struct PhpSerializer<W> {
     writer: W,
     struct_style: StructStyle,
+    float_format: FloatFormat,
+    php_enums: Vec<(&'static str, &'static str)>,
 }
 
 impl<W: Write> PhpSerializer<W> {
@@ -32,6 +55,8 @@ impl<W: Write> PhpSerializer<W> {
         PhpSerializer {
             writer,
             struct_style: StructStyle::Array,
+            float_format: FloatFormat::Shortest,
+            php_enums: Vec::new(),
         }
     }
 
@@ -42,6 +67,50 @@ impl<W: Write> PhpSerializer<W> {
         self
     }
 
+    /// Set how floats are written (default [`FloatFormat::Shortest`]).
+    ///
+    /// Use one of the fixed precision formats to match the output of a PHP
+    /// version before 7.1 byte for byte.
+    ///
+    /// ```rust
+    /// use phpserz::{FloatFormat, PhpSerializer};
+    /// use serde::Serialize;
+    /// let mut serializer = PhpSerializer::new(Vec::new()).float_format(FloatFormat::Digits17);
+    /// 0.1.serialize(&mut serializer).unwrap();
+    /// assert_eq!(serializer.into_inner(), b"d:0.10000000000000001;");
+    /// ```
+    #[must_use]
+    pub const fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Encode the unit variants of the Rust enum named `name` as cases of the
+    /// PHP 8.1 enum `class` (`E:len:"Class:Case";`) instead of strings.
+    ///
+    /// The name is the serde name of the enum, and `class` should be the
+    /// fully qualified PHP name. Variants with data are still encoded as
+    /// arrays.
+    ///
+    /// ```rust
+    /// use phpserz::PhpSerializer;
+    /// use serde::Serialize;
+    ///
+    /// #[derive(Serialize)]
+    /// enum Suit {
+    ///     Hearts,
+    /// }
+    ///
+    /// let mut serializer = PhpSerializer::new(Vec::new()).php_enum("Suit", "App\\Suit");
+    /// Suit::Hearts.serialize(&mut serializer).unwrap();
+    /// assert_eq!(serializer.into_inner(), b"E:15:\"App\\Suit:Hearts\";");
+    /// ```
+    #[must_use]
+    pub fn php_enum(mut self, name: &'static str, class: &'static str) -> Self {
+        self.php_enums.push((name, class));
+        self
+    }
+
     /// Consume this serializer and return the underlying writer.
     #[must_use]
     pub fn into_inner(self) -> W {
@@ -65,15 +134,65 @@ impl<W: Write> PhpSerializer<W> {
         write!(self.writer, "i:{value};")?;
         Ok(())
     }
+
+    fn write_f64(&mut self, value: f64) -> Result<(), Error> {
+        if !value.is_finite() {
+            return Err(Error::from(ErrorKind::Serialize {
+                message: "cannot serialize non-finite float".to_string(),
+            }));
+        }
+
+        match self.float_format {
+            FloatFormat::Shortest => write!(self.writer, "d:{value};")?,
+            FloatFormat::Digits17 => write_float_digits(&mut self.writer, value, 17)?,
+            FloatFormat::Digits14 => write_float_digits(&mut self.writer, value, 14)?,
+        }
+        Ok(())
+    }
 }
 
-fn float_to_token<W: Write>(writer: &mut W, value: f64) -> Result<(), Error> {
-    if !value.is_finite() {
-        return Err(Error::from(ErrorKind::Serialize {
-            message: "cannot serialize non-finite float".to_string(),
-        }));
+/// Write a float token with the given number of significant digits, like
+/// PHP's `%G` formatting: trailing zeros are trimmed, and exponents below -4
+/// or of at least `digits` switch to scientific notation (`1.0E+25`).
+fn write_float_digits<W: Write>(writer: &mut W, value: f64, digits: usize) -> Result<(), Error> {
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value == 0.0 {
+        write!(writer, "d:{sign}0;")?;
+        return Ok(());
+    }
+
+    // Rust's exponent formatting rounds to the exact number of digits, which
+    // leaves only the layout to do.
+    let formatted = format!("{:.*e}", digits - 1, value.abs());
+    let (mantissa, exponent) = formatted.split_once('e').unwrap_or((&formatted, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let significand = mantissa.replace('.', "");
+    let significand = significand.trim_end_matches('0');
+
+    write!(writer, "d:{sign}")?;
+    if exponent < -4 || exponent >= digits as i32 {
+        let (first, rest) = significand.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        let exponent_sign = if exponent < 0 { '-' } else { '+' };
+        write!(
+            writer,
+            "{first}.{rest}E{exponent_sign}{}",
+            exponent.unsigned_abs()
+        )?;
+    } else if exponent < 0 {
+        let zeros = "0".repeat(exponent.unsigned_abs() as usize - 1);
+        write!(writer, "0.{zeros}{significand}")?;
+    } else {
+        let point = exponent as usize + 1;
+        if significand.len() <= point {
+            let zeros = "0".repeat(point - significand.len());
+            write!(writer, "{significand}{zeros}")?;
+        } else {
+            let (integer, fraction) = significand.split_at(point);
+            write!(writer, "{integer}.{fraction}")?;
+        }
     }
-    write!(writer, "d:{value};")?;
+    writer.write_all(b";")?;
     Ok(())
 }
 
@@ -138,17 +257,15 @@ impl<'a, W: Write> ser::Serializer for &'a mut PhpSerializer<W> {
 
     fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
         // Format the f32 directly to preserve its shortest roundtrip representation.
-        if !v.is_finite() {
-            return Err(Error::from(ErrorKind::Serialize {
-                message: "cannot serialize non-finite float".to_string(),
-            }));
+        if self.float_format == FloatFormat::Shortest && v.is_finite() {
+            write!(self.writer, "d:{v};")?;
+            return Ok(());
         }
-        write!(self.writer, "d:{v};")?;
-        Ok(())
+        self.write_f64(f64::from(v))
     }
 
     fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
-        float_to_token(&mut self.writer, v)
+        self.write_f64(v)
     }
 
     fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
@@ -182,11 +299,23 @@ impl<'a, W: Write> ser::Serializer for &'a mut PhpSerializer<W> {
 
     fn serialize_unit_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<Self::Ok, Self::Error> {
-        self.write_str_token(variant.as_bytes())
+        let class = self
+            .php_enums
+            .iter()
+            .find_map(|&(enum_name, class)| (enum_name == name).then_some(class));
+        match class {
+            Some(class) => write!(
+                self.writer,
+                "E:{}:\"{class}:{variant}\";",
+                class.len() + variant.len() + 1
+            )
+            .map_err(Error::from),
+            None => self.write_str_token(variant.as_bytes()),
+        }
     }
 
     fn serialize_newtype_struct<T: Serialize + ?Sized>(
@@ -281,6 +410,141 @@ impl<'a, W: Write> ser::Serializer for &'a mut PhpSerializer<W> {
     }
 }
 
+/// Reusable options for creating [`PhpSerializer`]s.
+///
+/// Collects the same options as the setters on [`PhpSerializer`], but isn't
+/// tied to a writer, so one builder can serialize any number of values.
+/// [`to_vec`] and [`to_writer`] cover the case where the defaults suffice.
+///
+/// ```rust
+/// use phpserz::{FloatFormat, PhpSerializerBuilder, StructStyle};
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: f64,
+/// }
+///
+/// let builder = PhpSerializerBuilder::new()
+///     .struct_style(StructStyle::Object)
+///     .float_format(FloatFormat::Digits17);
+/// let bytes = builder.build_to_vec(&Point { x: 0.1 }).unwrap();
+/// assert_eq!(bytes, b"O:5:\"Point\":1:{s:1:\"x\";d:0.10000000000000001;}");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PhpSerializerBuilder {
+    struct_style: StructStyle,
+    float_format: FloatFormat,
+    php_enums: Vec<(&'static str, &'static str)>,
+}
+
+impl PhpSerializerBuilder {
+    /// Create a builder with the default options.
+    #[must_use]
+    pub const fn new() -> Self {
+        PhpSerializerBuilder {
+            struct_style: StructStyle::Array,
+            float_format: FloatFormat::Shortest,
+            php_enums: Vec::new(),
+        }
+    }
+
+    /// Set how named structs are encoded (default [`StructStyle::Array`]).
+    ///
+    /// See [`PhpSerializer::struct_style`].
+    #[must_use]
+    pub const fn struct_style(mut self, style: StructStyle) -> Self {
+        self.struct_style = style;
+        self
+    }
+
+    /// Set how floats are written (default [`FloatFormat::Shortest`]).
+    ///
+    /// See [`PhpSerializer::float_format`].
+    #[must_use]
+    pub const fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Encode the unit variants of the Rust enum named `name` as cases of the
+    /// PHP 8.1 enum `class`.
+    ///
+    /// See [`PhpSerializer::php_enum`].
+    #[must_use]
+    pub fn php_enum(mut self, name: &'static str, class: &'static str) -> Self {
+        self.php_enums.push((name, class));
+        self
+    }
+
+    /// Create a serializer with these options that writes to the given
+    /// writer.
+    #[must_use]
+    pub fn build<W: Write>(&self, writer: W) -> PhpSerializer<W> {
+        PhpSerializer {
+            writer,
+            struct_style: self.struct_style,
+            float_format: self.float_format,
+            php_enums: self.php_enums.clone(),
+        }
+    }
+
+    /// Serialize a value into PHP serialized bytes with these options.
+    pub fn build_to_vec<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, Error> {
+        let mut serializer = self.build(Vec::new());
+        value.serialize(&mut serializer)?;
+        Ok(serializer.into_inner())
+    }
+
+    /// Serialize a value as PHP serialized data into the writer with these
+    /// options.
+    pub fn build_to_writer<W: Write, T: Serialize + ?Sized>(
+        &self,
+        writer: W,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut self.build(writer))
+    }
+}
+
+/// Serialize a value into PHP serialized bytes.
+///
+/// ```rust
+/// let bytes = phpserz::to_vec(&vec![1, 2]).unwrap();
+/// assert_eq!(bytes, b"a:2:{i:0;i:1;i:1;i:2;}");
+/// ```
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut serializer = PhpSerializer::new(Vec::new());
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Serialize a value as PHP serialized data into the writer.
+///
+/// ```rust
+/// let mut buf = Vec::new();
+/// phpserz::to_writer(&mut buf, &Some(true)).unwrap();
+/// assert_eq!(buf, b"b:1;");
+/// ```
+pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Result<(), Error> {
+    value.serialize(&mut PhpSerializer::new(writer))
+}
+
+/// Serialize a value into a PHP serialized string.
+///
+/// PHP strings are byte strings, so the output is only a valid Rust string
+/// when all of the serialized strings are valid UTF-8. A [`ErrorKind::Utf8`]
+/// error is returned otherwise, in which case use [`to_vec`] instead.
+///
+/// ```rust
+/// let output = phpserz::to_string(&("a", 1)).unwrap();
+/// assert_eq!(output, "a:2:{i:0;s:1:\"a\";i:1;i:1;}");
+/// ```
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    let bytes = to_vec(value)?;
+    String::from_utf8(bytes).map_err(|e| Error::from(ErrorKind::Utf8(e.utf8_error())))
+}
+
 fn int_range_error() -> Error {
     Error::from(ErrorKind::Serialize {
         message: "integer out of range for PHP i64".to_string(),
@@ -625,14 +889,23 @@ mod tests {
     use serde::{Deserialize, Serialize};
     use std::collections::BTreeMap;
 
-    fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
-        let mut serializer = PhpSerializer::new(Vec::new());
-        value.serialize(&mut serializer)?;
-        Ok(serializer.into_inner())
+    fn to_string<T: Serialize>(value: &T) -> String {
+        super::to_string(value).unwrap()
     }
 
-    fn to_string<T: Serialize>(value: &T) -> String {
-        String::from_utf8(to_vec(value).unwrap()).unwrap()
+    #[rstest]
+    #[case("")]
+    #[case("a")]
+    #[case("123456789")]
+    #[case("1234567890")]
+    #[case("héllo wörld")]
+    fn test_serialized_len(#[case] value: &str) {
+        let bstr = crate::PhpBstr::from_str(value);
+        assert_eq!(bstr.serialized_len(), to_vec(&value).unwrap().len());
+
+        let long = value.repeat(100);
+        let bstr = crate::PhpBstr::from_str(&long);
+        assert_eq!(bstr.serialized_len(), to_vec(&long).unwrap().len());
     }
 
     #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -687,6 +960,59 @@ mod tests {
         assert_eq!(to_string(&3.33_f32), "d:3.33;");
     }
 
+    #[rstest]
+    #[case(0.1, "d:0.1;", "d:0.10000000000000001;", "d:0.1;")]
+    #[case(1.0 / 3.0, "d:0.3333333333333333;", "d:0.33333333333333331;", "d:0.33333333333333;")]
+    #[case(2.0, "d:2;", "d:2;", "d:2;")]
+    #[case(-1.5, "d:-1.5;", "d:-1.5;", "d:-1.5;")]
+    #[case(-0.0, "d:-0;", "d:-0;", "d:-0;")]
+    #[case(123456.789, "d:123456.789;", "d:123456.789;", "d:123456.789;")]
+    #[case(
+        1e25,
+        "d:10000000000000000000000000;",
+        "d:1.0000000000000001E+25;",
+        "d:1.0E+25;"
+    )]
+    #[case(1e-5, "d:0.00001;", "d:1.0000000000000001E-5;", "d:1.0E-5;")]
+    #[case(0.0001234, "d:0.0001234;", "d:0.00012339999999999999;", "d:0.0001234;")]
+    fn test_serialize_float_format(
+        #[case] value: f64,
+        #[case] shortest: &str,
+        #[case] digits17: &str,
+        #[case] digits14: &str,
+    ) {
+        for (format, expected) in [
+            (FloatFormat::Shortest, shortest),
+            (FloatFormat::Digits17, digits17),
+            (FloatFormat::Digits14, digits14),
+        ] {
+            let mut serializer = PhpSerializer::new(Vec::new()).float_format(format);
+            value.serialize(&mut serializer).unwrap();
+            let output = serializer.into_inner();
+            assert_eq!(
+                std::str::from_utf8(&output).unwrap(),
+                expected,
+                "{format:?}"
+            );
+
+            let parsed = f64::deserialize(&mut PhpDeserializer::new(&output)).unwrap();
+            if format != FloatFormat::Digits14 {
+                assert_eq!(parsed.to_bits(), value.to_bits(), "{format:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_serialize_f32_float_format() {
+        let mut serializer = PhpSerializer::new(Vec::new()).float_format(FloatFormat::Digits17);
+        0.1_f32.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.into_inner(), b"d:0.10000000149011612;");
+
+        let mut serializer = PhpSerializer::new(Vec::new()).float_format(FloatFormat::Digits14);
+        let error = f32::NAN.serialize(&mut serializer).unwrap_err();
+        assert!(error.to_string().contains("non-finite"));
+    }
+
     #[test]
     fn test_serialize_string_byte_length() {
         // Each emoji is 4 bytes, so the length is 8 even though it is 2 chars.
@@ -694,6 +1020,46 @@ mod tests {
         assert_eq!(to_string(&"hello"), "s:5:\"hello\";");
     }
 
+    #[test]
+    fn test_to_vec() {
+        assert_eq!(to_vec(&"hi").unwrap(), b"s:2:\"hi\";");
+        assert_eq!(to_vec(&[7_u8]).unwrap(), b"a:1:{i:0;i:7;}");
+    }
+
+    #[test]
+    fn test_to_writer() {
+        let mut buf = Vec::new();
+        super::to_writer(
+            &mut buf,
+            &Person {
+                name: "Alice".to_string(),
+                age: 30,
+            },
+        )
+        .unwrap();
+        assert_eq!(buf, b"a:2:{s:4:\"name\";s:5:\"Alice\";s:3:\"age\";i:30;}");
+
+        let mut file = std::io::Cursor::new(Vec::new());
+        super::to_writer(&mut file, &1.5_f64).unwrap();
+        assert_eq!(file.into_inner(), b"d:1.5;");
+    }
+
+    #[test]
+    fn test_to_string_requires_utf8() {
+        struct Binary;
+
+        impl Serialize for Binary {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(b"\xff\xfe")
+            }
+        }
+
+        assert_eq!(to_vec(&Binary).unwrap(), b"s:2:\"\xff\xfe\";");
+        let err = super::to_string(&Binary).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::Utf8(_)));
+        assert_eq!(super::to_string("caf\u{e9}").unwrap(), "s:5:\"caf\u{e9}\";");
+    }
+
     #[test]
     fn test_serialize_char() {
         assert_eq!(to_string(&'a'), "s:1:\"a\";");
@@ -772,6 +1138,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_serializer_builder() {
+        #[derive(Serialize)]
+        struct Reading {
+            value: f64,
+            shape: Shape,
+        }
+
+        let reading = Reading {
+            value: 0.1,
+            shape: Shape::Unit,
+        };
+        assert_eq!(
+            PhpSerializerBuilder::new().build_to_vec(&reading).unwrap(),
+            to_vec(&reading).unwrap()
+        );
+
+        let builder = PhpSerializerBuilder::new()
+            .struct_style(StructStyle::Object)
+            .float_format(FloatFormat::Digits14)
+            .php_enum("Shape", "App\\Shape");
+        let expected: &[u8] =
+            b"O:7:\"Reading\":2:{s:5:\"value\";d:0.1;s:5:\"shape\";E:14:\"App\\Shape:Unit\";}";
+        assert_eq!(builder.build_to_vec(&reading).unwrap(), expected);
+
+        let mut buf = Vec::new();
+        builder.build_to_writer(&mut buf, &reading).unwrap();
+        assert_eq!(buf, expected);
+
+        let builder = PhpSerializerBuilder::new().float_format(FloatFormat::Digits17);
+        assert_eq!(
+            builder.build_to_vec(&reading).unwrap(),
+            b"a:2:{s:5:\"value\";d:0.10000000000000001;s:5:\"shape\";s:4:\"Unit\";}"
+        );
+    }
+
     fn roundtrip<T>(value: &T)
     where
         T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
@@ -793,6 +1195,34 @@ mod tests {
         roundtrip(&Some(9_i32));
     }
 
+    #[rstest]
+    #[case(None, "N;")]
+    #[case(Some("data".to_string()), "s:4:\"data\";")]
+    fn test_roundtrip_option_string(#[case] value: Option<String>, #[case] expected: &str) {
+        // Mirrors the inputs of the deserializer's `test_deserialize_option_*`
+        assert_eq!(to_string(&value), expected);
+        roundtrip(&value);
+    }
+
+    #[test]
+    fn test_roundtrip_optional_fields() {
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Profile {
+            email: Option<String>,
+            bio: Option<String>,
+        }
+
+        let profile = Profile {
+            email: Some("a@example.com".to_string()),
+            bio: None,
+        };
+        assert_eq!(
+            to_string(&profile),
+            "a:2:{s:5:\"email\";s:13:\"a@example.com\";s:3:\"bio\";N;}"
+        );
+        roundtrip(&profile);
+    }
+
     #[test]
     fn test_roundtrip_struct_both_styles() {
         let person = Person {
@@ -810,6 +1240,33 @@ mod tests {
         assert_eq!(back, person);
     }
 
+    #[test]
+    fn test_roundtrip_property_visibility() {
+        #[derive(Debug, Deserialize, Serialize, PartialEq)]
+        struct Account {
+            owner: String,
+            #[serde(rename(serialize = "\0*\0balance"))]
+            balance: i64,
+            #[serde(rename(serialize = "\0Account\0pin"))]
+            pin: i64,
+        }
+
+        let input = b"O:7:\"Account\":3:{s:5:\"owner\";s:5:\"Alice\";s:10:\"\0*\0balance\";i:100;s:12:\"\0Account\0pin\";i:1234;}";
+        let account = Account::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(
+            account,
+            Account {
+                owner: "Alice".to_string(),
+                balance: 100,
+                pin: 1234,
+            }
+        );
+
+        let mut serializer = PhpSerializer::new(Vec::new()).struct_style(StructStyle::Object);
+        account.serialize(&mut serializer).unwrap();
+        assert_eq!(serializer.into_inner(), input);
+    }
+
     #[test]
     fn test_roundtrip_nested_and_collections() {
         roundtrip(&ObjectWrapper {
@@ -836,6 +1293,34 @@ mod tests {
         roundtrip(&Shape::Struct { x: 1, y: 2 });
     }
 
+    #[test]
+    fn test_roundtrip_php_enums() {
+        fn to_php_enum<T: Serialize>(value: &T) -> Vec<u8> {
+            let mut serializer = PhpSerializer::new(Vec::new())
+                .php_enum("Shape", "App\\Shape")
+                .php_enum("CoPower", "Power");
+            value.serialize(&mut serializer).unwrap();
+            serializer.into_inner()
+        }
+
+        let bytes = to_php_enum(&Shape::Unit);
+        assert_eq!(bytes, b"E:14:\"App\\Shape:Unit\";");
+        let mut de = PhpDeserializer::new(&bytes);
+        assert_eq!(Shape::deserialize(&mut de).unwrap(), Shape::Unit);
+
+        let powers = vec![CoPower::Power, CoPower::None];
+        let bytes = to_php_enum(&powers);
+        assert_eq!(bytes, b"a:2:{i:0;E:7:\"Power:Y\";i:1;E:7:\"Power:N\";}");
+        let mut de = PhpDeserializer::new(&bytes);
+        assert_eq!(Vec::<CoPower>::deserialize(&mut de).unwrap(), powers);
+
+        // Variants with data fall back to the array form
+        let bytes = to_php_enum(&Shape::Newtype(7));
+        assert_eq!(bytes, b"a:1:{s:7:\"Newtype\";i:7;}");
+        let mut de = PhpDeserializer::new(&bytes);
+        assert_eq!(Shape::deserialize(&mut de).unwrap(), Shape::Newtype(7));
+    }
+
     #[test]
     fn test_error_unsigned_overflow() {
         let err = to_vec(&u64::MAX).unwrap_err();
@@ -850,12 +1335,34 @@ mod tests {
         assert!(matches!(err.kind(), ErrorKind::Serialize { .. }));
     }
 
+    #[test]
+    fn test_roundtrip_int_keyed_map() {
+        let mut map = BTreeMap::new();
+        map.insert(-1_i64, "neg".to_string());
+        map.insert(7, "seven".to_string());
+        assert_eq!(to_string(&map), "a:2:{i:-1;s:3:\"neg\";i:7;s:5:\"seven\";}");
+        roundtrip(&map);
+    }
+
+    #[test]
+    fn test_roundtrip_string_keyed_map() {
+        let mut map = BTreeMap::new();
+        map.insert("a".to_string(), 1_i64);
+        map.insert("bc".to_string(), -2);
+        assert_eq!(to_string(&map), "a:2:{s:1:\"a\";i:1;s:2:\"bc\";i:-2;}");
+        roundtrip(&map);
+    }
+
     #[test]
     fn test_error_unsupported_map_key() {
         let mut map = BTreeMap::new();
         map.insert(true, 1_i32);
         let err = to_vec(&map).unwrap_err();
         assert!(matches!(err.kind(), ErrorKind::Serialize { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Serialization error: PHP array keys must be integers or strings"
+        );
     }
 
     #[test]
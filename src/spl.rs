@@ -0,0 +1,529 @@
+use crate::PhpKey;
+use serde::de::{
+    self, Deserialize, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor,
+};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// The SPL classes that [`crate::PhpDeserializer`] unwraps, identified by the
+/// newtype name their `Deserialize` impls ask for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SplClass {
+    ArrayObject,
+    ObjectStorage,
+}
+
+impl SplClass {
+    const ARRAY_OBJECT: &'static str = "$phpserz::private::ArrayObject";
+    const OBJECT_STORAGE: &'static str = "$phpserz::private::SplObjectStorage";
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            SplClass::ARRAY_OBJECT => Some(SplClass::ArrayObject),
+            SplClass::OBJECT_STORAGE => Some(SplClass::ObjectStorage),
+            _ => None,
+        }
+    }
+
+    /// Whether objects of the class are serialized in this layout.
+    pub(crate) fn accepts(self, class: &[u8]) -> bool {
+        match self {
+            SplClass::ArrayObject => matches!(
+                class,
+                b"ArrayObject" | b"ArrayIterator" | b"RecursiveArrayIterator"
+            ),
+            SplClass::ObjectStorage => class == b"SplObjectStorage",
+        }
+    }
+
+    pub(crate) fn expecting(self) -> &'static str {
+        match self {
+            SplClass::ArrayObject => "an ArrayObject or ArrayIterator",
+            SplClass::ObjectStorage => "an SplObjectStorage",
+        }
+    }
+
+    /// The byte that follows the value at `index` in the custom serialization
+    /// used before PHP 7.4, if any.
+    pub(crate) fn separator(self, index: usize) -> Option<u8> {
+        match self {
+            SplClass::ArrayObject => (index == 1).then_some(b';'),
+            SplClass::ObjectStorage if index == 0 => None,
+            SplClass::ObjectStorage => Some(if index % 2 == 1 { b',' } else { b';' }),
+        }
+    }
+}
+
+/// A serialized PHP `ArrayObject` or `ArrayIterator`, unwrapped to its
+/// backing storage.
+///
+/// PHP 7.4 and later serialize these classes as an object with four
+/// positional properties: the flags, the storage, the members, and the
+/// iterator class.
+///
+/// ```plain,ignore
+/// O:11:"ArrayObject":4:{i:0;i:0;i:1;a:1:{s:1:"a";i:1;}i:2;a:0:{}i:3;N;}
+/// ```
+///
+/// Earlier versions use a custom serialization with the flags, the storage,
+/// and the members.
+///
+/// ```plain,ignore
+/// C:11:"ArrayObject":33:{x:i:0;a:1:{s:1:"a";i:1;};m:a:0:{}}
+/// ```
+///
+/// Both forms are supported. The storage is usually an array, but can be any
+/// value that `T` deserializes from. Members, which are only set by
+/// subclasses with properties, are ignored. [`crate::PhpDeserializer`] only
+/// accepts objects of the `ArrayObject`, `ArrayIterator`, and
+/// `RecursiveArrayIterator` classes, and reads the values of the custom
+/// serialization with its own options, except that references in it are
+/// never resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhpArrayObject<T> {
+    /// The `ArrayObject::STD_PROP_LIST` and `ArrayObject::ARRAY_AS_PROPS`
+    /// flags.
+    pub flags: i64,
+
+    /// The wrapped array or object.
+    pub storage: T,
+}
+
+impl<'de, T> Deserialize<'de> for PhpArrayObject<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_newtype_struct(SplClass::ARRAY_OBJECT, PhpArrayObjectVisitor(PhantomData))
+    }
+}
+
+struct PhpArrayObjectVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for PhpArrayObjectVisitor<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = PhpArrayObject<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a PHP ArrayObject")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut flags = None;
+        let mut storage = None;
+        while let Some(key) = map.next_key::<PhpKey>()? {
+            match key {
+                PhpKey::Int(0) => flags = Some(map.next_value()?),
+                PhpKey::Int(1) => storage = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(PhpArrayObject {
+            flags: flags.ok_or_else(|| de::Error::missing_field("0"))?,
+            storage: storage.ok_or_else(|| de::Error::missing_field("1"))?,
+        })
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let flags = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let storage = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        Ok(PhpArrayObject { flags, storage })
+    }
+}
+
+/// A serialized PHP `SplObjectStorage`, unwrapped to its entries.
+///
+/// Each entry is an object with the data attached to it, in the order they
+/// were attached. PHP 7.4 and later serialize the entries as a flat array of
+/// alternating objects and data, followed by the members.
+///
+/// ```plain,ignore
+/// O:16:"SplObjectStorage":2:{i:0;a:2:{i:0;O:8:"stdClass":0:{}i:1;s:4:"data";}i:1;a:0:{}}
+/// ```
+///
+/// Earlier versions use a custom serialization with the number of entries,
+/// each object and its data, and the members.
+///
+/// ```plain,ignore
+/// C:16:"SplObjectStorage":46:{x:i:1;O:8:"stdClass":0:{},s:4:"data";;m:a:0:{}}
+/// ```
+///
+/// Both forms are supported, and members are ignored.
+/// [`crate::PhpDeserializer`] only accepts objects of the `SplObjectStorage`
+/// class, and reads the values of the custom serialization with its own
+/// options, except that references in it are never resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhpObjectStorage<O, D> {
+    /// The objects and their attached data.
+    pub entries: Vec<(O, D)>,
+}
+
+impl<'de, O, D> Deserialize<'de> for PhpObjectStorage<O, D>
+where
+    O: Deserialize<'de>,
+    D: Deserialize<'de>,
+{
+    fn deserialize<De>(deserializer: De) -> Result<Self, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(
+            SplClass::OBJECT_STORAGE,
+            PhpObjectStorageVisitor(PhantomData),
+        )
+    }
+}
+
+struct PhpObjectStorageVisitor<O, D>(PhantomData<(O, D)>);
+
+impl<'de, O, D> Visitor<'de> for PhpObjectStorageVisitor<O, D>
+where
+    O: Deserialize<'de>,
+    D: Deserialize<'de>,
+{
+    type Value = PhpObjectStorage<O, D>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a PHP SplObjectStorage")
+    }
+
+    fn visit_newtype_struct<De>(self, deserializer: De) -> Result<Self::Value, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut entries = None;
+        while let Some(key) = map.next_key::<PhpKey>()? {
+            match key {
+                PhpKey::Int(0) => entries = Some(map.next_value_seed(EntriesSeed(PhantomData))?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        Ok(PhpObjectStorage {
+            entries: entries.ok_or_else(|| de::Error::missing_field("0"))?,
+        })
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let count: u32 = seq
+            .next_element()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let mut entries = Vec::with_capacity(count.min(1024) as usize);
+        for i in 0..count as usize {
+            let object = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i * 2 + 1, &self))?;
+            let data = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i * 2 + 2, &self))?;
+            entries.push((object, data));
+        }
+
+        Ok(PhpObjectStorage { entries })
+    }
+}
+
+/// Reads the flat list of alternating objects and data.
+struct EntriesSeed<O, D>(PhantomData<(O, D)>);
+
+impl<'de, O, D> DeserializeSeed<'de> for EntriesSeed<O, D>
+where
+    O: Deserialize<'de>,
+    D: Deserialize<'de>,
+{
+    type Value = Vec<(O, D)>;
+
+    fn deserialize<De>(self, deserializer: De) -> Result<Self::Value, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, O, D> Visitor<'de> for EntriesSeed<O, D>
+where
+    O: Deserialize<'de>,
+    D: Deserialize<'de>,
+{
+    type Value = Vec<(O, D)>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("alternating objects and data")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut entries = Vec::with_capacity(seq.size_hint().unwrap_or(0) / 2);
+        while let Some(object) = seq.next_element()? {
+            let data = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(entries.len() * 2 + 1, &self))?;
+            entries.push((object, data));
+        }
+        Ok(entries)
+    }
+}
+
+/// Deserialize a PHP `ArrayObject` or `ArrayIterator` into its storage.
+///
+/// Meant to be used with `#[serde(deserialize_with)]`:
+///
+/// ```rust
+/// use phpserz::PhpDeserializer;
+/// use serde::Deserialize;
+/// use std::collections::HashMap;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Cart {
+///     #[serde(deserialize_with = "phpserz::deserialize_array_object")]
+///     items: HashMap<String, i64>,
+/// }
+///
+/// let input = b"a:1:{s:5:\"items\";O:11:\"ArrayObject\":4:{i:0;i:0;i:1;a:1:{s:5:\"apple\";i:3;}i:2;a:0:{}i:3;N;}}";
+/// let cart = Cart::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+/// assert_eq!(cart.items["apple"], 3);
+/// ```
+pub fn deserialize_array_object<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    PhpArrayObject::deserialize(deserializer).map(|array| array.storage)
+}
+
+/// Deserialize a PHP `SplObjectStorage` into its objects and their data.
+///
+/// Meant to be used with `#[serde(deserialize_with)]`:
+///
+/// ```rust
+/// use phpserz::PhpDeserializer;
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Registry {
+///     #[serde(deserialize_with = "phpserz::deserialize_object_storage")]
+///     users: Vec<(User, String)>,
+/// }
+///
+/// let input = b"a:1:{s:5:\"users\";O:16:\"SplObjectStorage\":2:{i:0;a:2:{i:0;O:4:\"User\":1:{s:4:\"name\";s:5:\"Alice\";}i:1;s:5:\"admin\";}i:1;a:0:{}}}";
+/// let registry = Registry::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+/// assert_eq!(registry.users[0].0.name, "Alice");
+/// assert_eq!(registry.users[0].1, "admin");
+/// ```
+pub fn deserialize_object_storage<'de, De, O, D>(deserializer: De) -> Result<Vec<(O, D)>, De::Error>
+where
+    De: Deserializer<'de>,
+    O: Deserialize<'de>,
+    D: Deserialize<'de>,
+{
+    PhpObjectStorage::deserialize(deserializer).map(|storage| storage.entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, PhpDeserializer};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Point {
+        x: i64,
+    }
+
+    #[test]
+    fn test_array_object() {
+        let input = b"O:11:\"ArrayObject\":4:{i:0;i:0;i:1;a:2:{s:1:\"a\";i:1;s:1:\"b\";i:2;}i:2;a:0:{}i:3;N;}";
+        let result: PhpArrayObject<BTreeMap<String, i64>> =
+            Deserialize::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(result.flags, 0);
+        assert_eq!(
+            result.storage,
+            BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_array_object_legacy() {
+        let input = b"C:11:\"ArrayObject\":45:{x:i:2;a:2:{s:1:\"a\";i:1;s:1:\"b\";i:2;};m:a:0:{}}";
+        let result: PhpArrayObject<BTreeMap<String, i64>> =
+            Deserialize::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(result.flags, 2);
+        assert_eq!(
+            result.storage,
+            BTreeMap::from([("a".to_string(), 1), ("b".to_string(), 2)])
+        );
+    }
+
+    #[test]
+    fn test_array_iterator_list() {
+        let input =
+            b"O:13:\"ArrayIterator\":4:{i:0;i:0;i:1;a:2:{i:0;s:1:\"x\";i:1;s:1:\"y\";}i:2;a:0:{}i:3;N;}";
+        let result: Vec<String> =
+            deserialize_array_object(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(result, vec!["x", "y"]);
+    }
+
+    #[test]
+    fn test_object_storage() {
+        let input = b"O:16:\"SplObjectStorage\":2:{i:0;a:4:{i:0;O:5:\"Point\":1:{s:1:\"x\";i:1;}i:1;s:4:\"data\";i:2;O:5:\"Point\":1:{s:1:\"x\";i:2;}i:3;N;}i:1;a:0:{}}";
+        let result: Vec<(Point, Option<String>)> =
+            deserialize_object_storage(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(
+            result,
+            vec![
+                (Point { x: 1 }, Some("data".to_string())),
+                (Point { x: 2 }, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_object_storage_legacy() {
+        let input = b"C:16:\"SplObjectStorage\":87:{x:i:2;O:5:\"Point\":1:{s:1:\"x\";i:1;},s:4:\"data\";;O:5:\"Point\":1:{s:1:\"x\";i:2;},N;;m:a:0:{}}";
+        let result: PhpObjectStorage<Point, Option<String>> =
+            Deserialize::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+        assert_eq!(
+            result.entries,
+            vec![
+                (Point { x: 1 }, Some("data".to_string())),
+                (Point { x: 2 }, None)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_object_storage_empty() {
+        let input = b"O:16:\"SplObjectStorage\":2:{i:0;a:0:{}i:1;a:0:{}}";
+        let result: Vec<(Point, i64)> =
+            deserialize_object_storage(&mut PhpDeserializer::new(input)).unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_object_storage_odd_entries() {
+        let input = b"O:16:\"SplObjectStorage\":2:{i:0;a:1:{i:0;O:5:\"Point\":1:{s:1:\"x\";i:1;}}i:1;a:0:{}}";
+        let result = deserialize_object_storage::<_, Point, i64>(&mut PhpDeserializer::new(input));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_legacy_payload_errors() {
+        let input = b"C:11:\"ArrayObject\":6:{i:0;N;}";
+        let result: Result<PhpArrayObject<()>, _> =
+            Deserialize::deserialize(&mut PhpDeserializer::new(input));
+        assert!(result.is_err());
+
+        let input = b"C:16:\"SplObjectStorage\":26:{x:i:1;O:5:\"Point\":0:{};N;;}";
+        let result: Result<Vec<(IgnoredAny, IgnoredAny)>, _> =
+            deserialize_object_storage(&mut PhpDeserializer::new(input));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_legacy_payload_keeps_options() {
+        let input =
+            b"C:16:\"SplObjectStorage\":46:{x:i:1;O:5:\"Point\":1:{s:1:\"x\";i:1;},N;;m:a:0:{}}";
+        let mut deserializer = PhpDeserializer::new(input).allow_class(|class| class != b"Point");
+        let error = deserialize_object_storage::<_, Point, ()>(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedClass { class, .. } if class == "Point"
+        ));
+
+        let mut deserializer = PhpDeserializer::new(input).max_depth(1);
+        let error = deserialize_object_storage::<_, Point, ()>(&mut deserializer).unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::DepthLimitExceeded { .. }));
+
+        let input = b"C:11:\"ArrayObject\":45:{x:i:0;a:2:{s:1:\"a\";i:1;s:1:\"b\";i:2;};m:a:0:{}}";
+        let mut deserializer = PhpDeserializer::new(input).max_total_bytes(3);
+        let error =
+            deserialize_array_object::<_, BTreeMap<String, i64>>(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::ResourceLimit { limit: 3, .. }
+        ));
+
+        let input = b"C:11:\"ArrayObject\":37:{x:i:0;a:2:{i:0;i:1;i:1;r:1;};m:a:0:{}}";
+        let mut deserializer = PhpDeserializer::new(input).resolve_references(true);
+        let error = deserialize_array_object::<_, Vec<i64>>(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedReference { index: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_checks_class_name() {
+        let input = b"O:3:\"Foo\":4:{i:0;i:0;i:1;a:0:{}i:2;a:0:{}i:3;N;}";
+        let result = deserialize_array_object::<_, Vec<i64>>(&mut PhpDeserializer::new(input));
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::Deserialize { message, position: Some(0) }
+                if message == "Expected an ArrayObject or ArrayIterator, found class Foo"
+        ));
+
+        let input = b"C:11:\"ArrayObject\":21:{x:i:0;a:0:{};m:a:0:{}}";
+        let result = deserialize_object_storage::<_, Point, ()>(&mut PhpDeserializer::new(input));
+        assert!(matches!(
+            result.unwrap_err().kind(),
+            ErrorKind::Deserialize { message, .. }
+                if message == "Expected an SplObjectStorage, found class ArrayObject"
+        ));
+
+        let input = b"a:2:{i:0;i:0;i:1;a:0:{}}";
+        let result = deserialize_array_object::<_, Vec<i64>>(&mut PhpDeserializer::new(input));
+        assert!(result.is_err());
+
+        let input =
+            b"O:22:\"RecursiveArrayIterator\":4:{i:0;i:0;i:1;a:1:{i:0;i:5;}i:2;a:0:{}i:3;N;}";
+        let result = deserialize_array_object::<_, Vec<i64>>(&mut PhpDeserializer::new(input));
+        assert_eq!(result.unwrap(), vec![5]);
+    }
+}
@@ -0,0 +1,185 @@
+use crate::errors::Error;
+use crate::events::EventHandler;
+use crate::parser::{PhpBstr, PhpParser, PhpToken};
+use std::collections::HashMap;
+
+/// A summary of what a payload contains, as computed by [`stats`].
+///
+/// Keys are counted like any other integer or string, so `string_bytes`
+/// reflects all of the string data in the payload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PayloadStats<'a> {
+    /// The number of nulls, booleans, integers, and floats.
+    pub scalars: u64,
+
+    /// The number of strings.
+    pub strings: u64,
+
+    /// The total length of all strings, in bytes.
+    pub string_bytes: u64,
+
+    /// The number of arrays.
+    pub arrays: u64,
+
+    /// The number of objects, including custom serialized objects and enum
+    /// cases, per class in the order the classes are first seen.
+    pub objects: Vec<(PhpBstr<'a>, u64)>,
+
+    /// The number of references, both `r:` and `R:`.
+    pub references: u64,
+
+    /// The deepest nesting of arrays and objects, where a payload without
+    /// containers has a depth of zero.
+    pub max_depth: usize,
+}
+
+struct StatsCollector<'a> {
+    stats: PayloadStats<'a>,
+    depth: usize,
+
+    // The index of each class in `stats.objects`.
+    classes: HashMap<PhpBstr<'a>, usize>,
+}
+
+impl<'a> StatsCollector<'a> {
+    fn enter(&mut self) {
+        self.depth += 1;
+        self.stats.max_depth = self.stats.max_depth.max(self.depth);
+    }
+
+    fn count_object(&mut self, class: PhpBstr<'a>) {
+        let objects = &mut self.stats.objects;
+        let index = *self.classes.entry(class).or_insert_with(|| {
+            objects.push((class, 0));
+            objects.len() - 1
+        });
+        objects[index].1 += 1;
+    }
+
+    fn count_token(&mut self, token: PhpToken<'a>) {
+        let stats = &mut self.stats;
+        match token {
+            PhpToken::Null | PhpToken::Boolean(_) | PhpToken::Integer(_) | PhpToken::Float(_) => {
+                stats.scalars += 1
+            }
+            PhpToken::String(s) => {
+                stats.strings += 1;
+                stats.string_bytes += s.as_bytes().len() as u64;
+            }
+            PhpToken::CustomObject { class, .. } | PhpToken::Enum { class, .. } => {
+                self.count_object(class)
+            }
+            PhpToken::Reference { .. } => stats.references += 1,
+            PhpToken::Array { .. } | PhpToken::Object { .. } | PhpToken::End => {}
+        }
+    }
+}
+
+impl<'a> EventHandler<'a> for StatsCollector<'a> {
+    fn on_scalar(&mut self, token: PhpToken<'a>) -> Result<(), Error> {
+        self.count_token(token);
+        Ok(())
+    }
+
+    fn on_key(&mut self, key: PhpToken<'a>) -> Result<(), Error> {
+        self.count_token(key);
+        Ok(())
+    }
+
+    fn on_array_start(&mut self, _elements: u32) -> Result<(), Error> {
+        self.stats.arrays += 1;
+        self.enter();
+        Ok(())
+    }
+
+    fn on_array_end(&mut self) -> Result<(), Error> {
+        self.depth -= 1;
+        Ok(())
+    }
+
+    fn on_object_start(&mut self, class: PhpBstr<'a>, _properties: u32) -> Result<(), Error> {
+        self.count_object(class);
+        self.enter();
+        Ok(())
+    }
+
+    fn on_object_end(&mut self) -> Result<(), Error> {
+        self.depth -= 1;
+        Ok(())
+    }
+}
+
+/// Summarize the values in a payload in a single pass, without
+/// materializing anything.
+///
+/// Useful to get a feel for large legacy payloads, for instance before
+/// migrating them. Malformed input fails with the first parse error.
+///
+/// ```rust
+/// use phpserz::{stats, PhpBstr};
+/// let data = b"a:2:{i:0;O:3:\"Foo\":1:{s:1:\"a\";d:1.5;}i:1;r:2;}";
+/// let stats = stats(data).unwrap();
+/// assert_eq!(stats.arrays, 1);
+/// assert_eq!(stats.objects, vec![(PhpBstr::new(b"Foo"), 1)]);
+/// assert_eq!(stats.references, 1);
+/// assert_eq!(stats.max_depth, 2);
+/// ```
+pub fn stats(data: &[u8]) -> Result<PayloadStats<'_>, Error> {
+    let mut collector = StatsCollector {
+        stats: PayloadStats::default(),
+        depth: 0,
+        classes: HashMap::new(),
+    };
+    PhpParser::new(data).visit_events(&mut collector)?;
+    Ok(collector.stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ErrorKind;
+
+    #[test]
+    fn test_stats_readme_example() {
+        let data = b"O:7:\"Example\":5:{s:4:\"name\";s:8:\"John Doe\";s:12:\"\0Example\0age\";i:42;s:11:\"\0*\0isActive\";b:1;s:6:\"scores\";a:3:{i:0;d:95.5;i:1;d:88.0;i:2;d:92.3;}s:8:\"metadata\";a:2:{s:2:\"id\";i:12345;s:4:\"tags\";a:3:{i:0;s:3:\"php\";i:1;s:4:\"rust\";i:2;s:13:\"serialization\";}}}";
+        assert_eq!(
+            stats(data).unwrap(),
+            PayloadStats {
+                scalars: 12,
+                strings: 11,
+                string_bytes: 75,
+                arrays: 3,
+                objects: vec![(PhpBstr::new(b"Example"), 1)],
+                references: 0,
+                max_depth: 3,
+            }
+        );
+    }
+
+    #[test]
+    fn test_stats_objects_by_class() {
+        let data = b"a:4:{i:0;O:3:\"Foo\":0:{}i:1;C:3:\"Bar\":2:{xy}i:2;O:3:\"Foo\":0:{}i:3;R:3;}";
+        let stats = stats(data).unwrap();
+        assert_eq!(
+            stats.objects,
+            vec![(PhpBstr::new(b"Foo"), 2), (PhpBstr::new(b"Bar"), 1)]
+        );
+        assert_eq!(stats.references, 1);
+        assert_eq!(stats.scalars, 4);
+        assert_eq!(stats.max_depth, 2);
+    }
+
+    #[test]
+    fn test_stats_scalar() {
+        let stats = stats(b"s:5:\"hello\";").unwrap();
+        assert_eq!(stats.strings, 1);
+        assert_eq!(stats.string_bytes, 5);
+        assert_eq!(stats.max_depth, 0);
+    }
+
+    #[test]
+    fn test_stats_truncated() {
+        let error = stats(b"a:1:{i:0;i:12345;").unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Eof));
+    }
+}
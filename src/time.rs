@@ -0,0 +1,103 @@
+//! Helpers for deserializing PHP integers as Rust time types.
+//!
+//! PHP code commonly stores unix timestamps and durations as plain integer
+//! seconds, like the result of `time()`. The helpers are meant to be used
+//! with `#[serde(deserialize_with)]`:
+//!
+//! ```rust
+//! use phpserz::PhpDeserializer;
+//! use serde::Deserialize;
+//! use std::time::{Duration, SystemTime};
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Session {
+//!     #[serde(deserialize_with = "phpserz::time::system_time_secs")]
+//!     created: SystemTime,
+//!     #[serde(deserialize_with = "phpserz::time::duration_secs")]
+//!     ttl: Duration,
+//! }
+//!
+//! let input = b"a:2:{s:7:\"created\";i:1700000000;s:3:\"ttl\";i:3600;}";
+//! let session = Session::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+//! assert_eq!(session.ttl, Duration::from_secs(3600));
+//! ```
+
+use serde::de::{self, Deserialize, Deserializer, Unexpected};
+use std::time::{Duration, SystemTime};
+
+/// Deserialize a PHP integer as a [`Duration`] of that many seconds.
+///
+/// Negative integers are rejected.
+pub fn duration_secs<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = i64::deserialize(deserializer)?;
+    u64::try_from(secs).map(Duration::from_secs).map_err(|_| {
+        de::Error::invalid_value(
+            Unexpected::Signed(secs),
+            &"a non-negative number of seconds",
+        )
+    })
+}
+
+/// Deserialize a PHP integer as a [`SystemTime`] that many seconds since the
+/// unix epoch.
+///
+/// Negative integers are times before the epoch.
+pub fn system_time_secs<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let secs = i64::deserialize(deserializer)?;
+    let offset = Duration::from_secs(secs.unsigned_abs());
+    let time = if secs < 0 {
+        SystemTime::UNIX_EPOCH.checked_sub(offset)
+    } else {
+        SystemTime::UNIX_EPOCH.checked_add(offset)
+    };
+    time.ok_or_else(|| {
+        de::Error::invalid_value(Unexpected::Signed(secs), &"a representable unix timestamp")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorKind, PhpDeserializer};
+
+    #[test]
+    fn test_system_time_secs() {
+        let mut deserializer = PhpDeserializer::new(b"i:1700000000;");
+        let time = system_time_secs(&mut deserializer).unwrap();
+        assert_eq!(
+            time,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+        );
+
+        let mut deserializer = PhpDeserializer::new(b"i:-86400;");
+        let time = system_time_secs(&mut deserializer).unwrap();
+        assert_eq!(time, SystemTime::UNIX_EPOCH - Duration::from_secs(86_400));
+    }
+
+    #[test]
+    fn test_duration_secs() {
+        let mut deserializer = PhpDeserializer::new(b"i:1700000000;");
+        let duration = duration_secs(&mut deserializer).unwrap();
+        assert_eq!(duration, Duration::from_secs(1_700_000_000));
+
+        let mut deserializer = PhpDeserializer::new(b"i:-1;");
+        let error = duration_secs(&mut deserializer).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::Deserialize { message, .. }
+                if message == "invalid value: integer `-1`, expected a non-negative number of seconds"
+        ));
+    }
+
+    #[test]
+    fn test_time_secs_rejects_strings() {
+        let mut deserializer = PhpDeserializer::new(b"s:10:\"1700000000\";");
+        assert!(system_time_secs(&mut deserializer).is_err());
+    }
+}
@@ -0,0 +1,2028 @@
+use crate::errors::{Error, ErrorKind};
+use crate::parser::{DEFAULT_MAX_DEPTH, PhpBstr, PhpParser, PhpToken, PhpTokenKind};
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A PHP array key, which is either an integer or a byte string.
+///
+/// PHP coerces string keys that are canonical decimal integers (like `"5"` or
+/// `"-12"`, but not `"05"` or `"5.0"`) to integer keys, and
+/// [`PhpKey::from_bytes`] applies the same rule. This means that
+/// `a:1:{s:1:"5";N;}` and `a:1:{i:5;N;}` produce the same key.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PhpKey {
+    /// An integer key.
+    Int(i64),
+
+    /// A string key.
+    Str(Vec<u8>),
+}
+
+impl PhpKey {
+    /// Create a key from a string, coercing canonical decimal integers to
+    /// integer keys like PHP does.
+    ///
+    /// ```rust
+    /// use phpserz::PhpKey;
+    /// assert_eq!(PhpKey::from_bytes(b"5"), PhpKey::Int(5));
+    /// assert_eq!(PhpKey::from_bytes(b"-12"), PhpKey::Int(-12));
+    /// assert_eq!(PhpKey::from_bytes(b"05"), PhpKey::Str(b"05".to_vec()));
+    /// assert_eq!(PhpKey::from_bytes(b"name"), PhpKey::Str(b"name".to_vec()));
+    /// ```
+    #[must_use]
+    pub fn from_bytes(data: &[u8]) -> Self {
+        match canonical_int(data) {
+            Some(i) => PhpKey::Int(i),
+            None => PhpKey::Str(data.to_vec()),
+        }
+    }
+}
+
+impl From<i64> for PhpKey {
+    fn from(value: i64) -> Self {
+        PhpKey::Int(value)
+    }
+}
+
+/// Parse a string as an integer only if it is how PHP would print that
+/// integer: no leading zeros, no plus sign, no `-0`, and within `i64`.
+pub(crate) fn canonical_int(data: &[u8]) -> Option<i64> {
+    let digits = data.strip_prefix(b"-").unwrap_or(data);
+    match digits {
+        [b'0'] if digits.len() == data.len() => return Some(0),
+        [b'1'..=b'9', rest @ ..] if rest.iter().all(u8::is_ascii_digit) => {}
+        _ => return None,
+    }
+
+    std::str::from_utf8(data).ok()?.parse().ok()
+}
+
+/// An owned, dynamically typed PHP value.
+///
+/// Arrays keep their entries in serialized order. When deserialized through
+/// serde, objects are indistinguishable from arrays (the class name is not
+/// visible to a visitor), so they are represented as [`PhpValue::Array`] with
+/// their raw, possibly mangled, property names as keys.
+///
+/// Like `serde_json::Value`, a `PhpValue` can be a field of a derived struct
+/// to keep part of a payload as dynamic data.
+///
+/// ```rust
+/// # #[cfg(feature = "serde")] {
+/// use phpserz::{PhpDeserializer, PhpKey, PhpValue};
+/// use serde::Deserialize;
+///
+/// let mut deserializer = PhpDeserializer::new(b"a:2:{i:0;b:1;s:3:\"foo\";d:1.5;}");
+/// let value = PhpValue::deserialize(&mut deserializer).unwrap();
+/// assert_eq!(
+///     value,
+///     PhpValue::Array(vec![
+///         (PhpKey::Int(0), PhpValue::Bool(true)),
+///         (PhpKey::Str(b"foo".to_vec()), PhpValue::Float(1.5)),
+///     ])
+/// );
+/// # }
+/// ```
+///
+/// In the other direction, a `&PhpValue` is a serde deserializer, so an
+/// already parsed value can be turned into a user type, like
+/// `serde_json::from_value`.
+///
+/// ```rust
+/// # #[cfg(feature = "serde")] {
+/// use phpserz::PhpParser;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Point {
+///     x: i64,
+///     y: i64,
+/// }
+///
+/// let value = PhpParser::new(b"O:5:\"Point\":2:{s:1:\"x\";i:1;s:1:\"y\";i:2;}")
+///     .read_value()
+///     .unwrap();
+/// let point = Point::deserialize(&value).unwrap();
+/// assert_eq!((point.x, point.y), (1, 2));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhpValue {
+    /// The null value.
+    Null,
+
+    /// A boolean.
+    Bool(bool),
+
+    /// An integer.
+    Int(i64),
+
+    /// A float.
+    Float(f64),
+
+    /// A byte string.
+    String(Vec<u8>),
+
+    /// An ordered array of key value pairs.
+    Array(Vec<(PhpKey, PhpValue)>),
+
+    /// An object with its class name and properties. Property names are kept
+    /// as serialized, including any visibility prefix.
+    Object {
+        class: Vec<u8>,
+        properties: Vec<(Vec<u8>, PhpValue)>,
+    },
+
+    /// An enum case with its class name.
+    Enum { class: Vec<u8>, case: Vec<u8> },
+}
+
+impl PhpValue {
+    /// Merge `other` into this value like PHP's `array_merge`.
+    ///
+    /// When both values are arrays:
+    ///
+    /// - Integer keys from both arrays are renumbered from 0, in order, with
+    ///   the entries of `other` appended after those of `self`.
+    /// - String keys from `other` overwrite the value of the same key in
+    ///   `self`, keeping its original position, or are appended when absent.
+    ///
+    /// Otherwise, including when either side is an object, `other` replaces
+    /// this value entirely.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpValue};
+    /// let mut base = PhpValue::Array(vec![
+    ///     (PhpKey::Str(b"color".to_vec()), PhpValue::String(b"red".to_vec())),
+    ///     (PhpKey::Int(5), PhpValue::Int(2)),
+    /// ]);
+    /// let patch = PhpValue::Array(vec![
+    ///     (PhpKey::Int(0), PhpValue::Int(3)),
+    ///     (PhpKey::Str(b"color".to_vec()), PhpValue::String(b"blue".to_vec())),
+    /// ]);
+    /// base.merge(patch);
+    /// assert_eq!(
+    ///     base,
+    ///     PhpValue::Array(vec![
+    ///         (PhpKey::Str(b"color".to_vec()), PhpValue::String(b"blue".to_vec())),
+    ///         (PhpKey::Int(0), PhpValue::Int(2)),
+    ///         (PhpKey::Int(1), PhpValue::Int(3)),
+    ///     ])
+    /// );
+    /// ```
+    pub fn merge(&mut self, other: PhpValue) {
+        match (self, other) {
+            (PhpValue::Array(entries), PhpValue::Array(others)) => merge_arrays(entries, others),
+            (this, other) => *this = other,
+        }
+    }
+
+    /// Return true if this value is an array whose keys are the integers
+    /// 0 through n - 1 in order, like PHP's `array_is_list`.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpValue};
+    /// let list = PhpValue::Array(vec![
+    ///     (PhpKey::Int(0), PhpValue::Null),
+    ///     (PhpKey::Int(1), PhpValue::Null),
+    /// ]);
+    /// assert!(list.is_list());
+    ///
+    /// let gapped = PhpValue::Array(vec![(PhpKey::Int(1), PhpValue::Null)]);
+    /// assert!(!gapped.is_list());
+    /// assert!(!PhpValue::Null.is_list());
+    /// ```
+    #[must_use]
+    pub fn is_list(&self) -> bool {
+        match self {
+            PhpValue::Array(entries) => entries
+                .iter()
+                .zip(0..)
+                .all(|((key, _), index)| *key == PhpKey::Int(index)),
+            _ => false,
+        }
+    }
+
+    /// Iterate over the entries of an array in order. Values that aren't
+    /// arrays, including objects, have no entries.
+    ///
+    /// The number of entries is known up front, and they can be iterated from
+    /// either end.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpValue};
+    /// let value = PhpValue::Array(vec![
+    ///     (PhpKey::Int(0), PhpValue::Int(10)),
+    ///     (PhpKey::Str(b"a".to_vec()), PhpValue::Int(20)),
+    /// ]);
+    /// let entries = value.entries();
+    /// assert_eq!(entries.len(), 2);
+    ///
+    /// let values: Vec<_> = entries.rev().map(|(_, value)| value).collect();
+    /// assert_eq!(values, [&PhpValue::Int(20), &PhpValue::Int(10)]);
+    /// ```
+    #[must_use]
+    pub fn entries(&self) -> PhpEntries<'_> {
+        let entries = match self {
+            PhpValue::Array(entries) => entries.as_slice(),
+            _ => &[],
+        };
+
+        PhpEntries {
+            iter: entries.iter(),
+        }
+    }
+
+    /// Put the entries of list-like arrays in key order, so they serialize as
+    /// the list PHP would emit.
+    ///
+    /// An array is list-like when its keys are exactly the integers 0
+    /// through n - 1, just not in order, such as one built by assigning
+    /// indices out of order. Its entries are sorted by key, so every key keeps
+    /// its value. Arrays with a gap in their keys or any string key are left
+    /// as is, as only renumbering could make them a list and that would
+    /// change which key holds which value. Nested arrays and object properties
+    /// are canonicalized too. Nesting is walked without recursion, so deep
+    /// values can't overflow the stack.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpValue};
+    /// let mut value = PhpValue::Array(vec![
+    ///     (PhpKey::Int(1), PhpValue::Int(10)),
+    ///     (PhpKey::Int(0), PhpValue::Int(20)),
+    /// ]);
+    /// value.canonicalize();
+    /// assert!(value.is_list());
+    /// assert_eq!(
+    ///     value,
+    ///     PhpValue::Array(vec![
+    ///         (PhpKey::Int(0), PhpValue::Int(20)),
+    ///         (PhpKey::Int(1), PhpValue::Int(10)),
+    ///     ])
+    /// );
+    /// ```
+    pub fn canonicalize(&mut self) {
+        let mut pending = vec![self];
+        while let Some(value) = pending.pop() {
+            match value {
+                PhpValue::Array(entries) => {
+                    if is_shuffled_list(entries) {
+                        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+                    }
+
+                    pending.extend(entries.iter_mut().map(|(_, value)| value));
+                }
+                PhpValue::Object { properties, .. } => {
+                    pending.extend(properties.iter_mut().map(|(_, value)| value));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Whether the keys of an array are the integers 0 through n - 1 in any
+/// order.
+fn is_shuffled_list(entries: &[(PhpKey, PhpValue)]) -> bool {
+    let mut seen = vec![false; entries.len()];
+    entries.iter().all(|(key, _)| {
+        let PhpKey::Int(index) = key else {
+            return false;
+        };
+
+        usize::try_from(*index)
+            .ok()
+            .and_then(|index| seen.get_mut(index))
+            .is_some_and(|seen| !std::mem::replace(seen, true))
+    })
+}
+
+/// An iterator over the entries of an array, created by
+/// [`PhpValue::entries`].
+#[derive(Debug, Clone)]
+pub struct PhpEntries<'a> {
+    iter: std::slice::Iter<'a, (PhpKey, PhpValue)>,
+}
+
+impl<'a> Iterator for PhpEntries<'a> {
+    type Item = (&'a PhpKey, &'a PhpValue);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(|(key, value)| (key, value))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for PhpEntries<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.iter.next_back().map(|(key, value)| (key, value))
+    }
+}
+
+impl ExactSizeIterator for PhpEntries<'_> {}
+
+impl std::iter::FusedIterator for PhpEntries<'_> {}
+
+impl<'a> PhpParser<'a> {
+    /// Read the next complete value.
+    ///
+    /// Unlike values deserialized through serde, objects keep their class
+    /// name. References and custom serialized objects have no [`PhpValue`]
+    /// representation and are reported as errors, as is nesting deeper than
+    /// 128 levels.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpParser, PhpValue};
+    /// let mut parser = PhpParser::new(b"O:3:\"Foo\":1:{s:1:\"a\";i:1;}");
+    /// assert_eq!(
+    ///     parser.read_value().unwrap(),
+    ///     PhpValue::Object {
+    ///         class: b"Foo".to_vec(),
+    ///         properties: vec![(b"a".to_vec(), PhpValue::Int(1))],
+    ///     }
+    /// );
+    /// ```
+    pub fn read_value(&mut self) -> Result<PhpValue, Error> {
+        read_value(|| {
+            let (position, _) = self.upcoming();
+            Ok((position, self.read_token()?))
+        })
+    }
+
+    /// Read the next complete value like [`PhpParser::read_value`], along with
+    /// the range of the input it was read from.
+    ///
+    /// The range makes it possible to splice a modified value back into the
+    /// input in place of the original.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpValue};
+    /// let input = b"a:2:{i:0;s:3:\"foo\";i:1;a:1:{i:0;N;}}";
+    /// let mut parser = PhpParser::new(input);
+    /// parser.read_token().unwrap();
+    /// parser.read_token().unwrap();
+    /// let (value, span) = parser.read_value_with_span().unwrap();
+    /// assert_eq!(value, PhpValue::String(b"foo".to_vec()));
+    /// assert_eq!(&input[span], b"s:3:\"foo\";");
+    /// ```
+    pub fn read_value_with_span(&mut self) -> Result<(PhpValue, Range<usize>), Error> {
+        let (start, _) = self.upcoming();
+        let value = self.read_value()?;
+        Ok((value, start..self.position()))
+    }
+
+    /// Read an array or object and return its keys, skipping over the values
+    /// without materializing them. Property names are returned as they are
+    /// serialized, including the markers of protected and private
+    /// properties.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpParser};
+    /// let mut parser = PhpParser::new(b"a:2:{i:3;a:1:{i:0;N;}s:4:\"name\";s:3:\"Foo\";}");
+    /// assert_eq!(
+    ///     parser.collect_keys().unwrap(),
+    ///     vec![PhpKey::Int(3), PhpKey::Str(b"name".to_vec())]
+    /// );
+    /// ```
+    pub fn collect_keys(&mut self) -> Result<Vec<PhpKey>, Error> {
+        let mut keys = Vec::new();
+        self.visit_entries(|parser, key| {
+            keys.push(key);
+            parser.skip_value()
+        })?;
+        Ok(keys)
+    }
+
+    /// Read an array or object and return its values, skipping over the
+    /// keys.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpParser, PhpValue};
+    /// let mut parser = PhpParser::new(b"a:2:{i:3;b:1;s:4:\"name\";N;}");
+    /// assert_eq!(
+    ///     parser.collect_values().unwrap(),
+    ///     vec![PhpValue::Bool(true), PhpValue::Null]
+    /// );
+    /// ```
+    pub fn collect_values(&mut self) -> Result<Vec<PhpValue>, Error> {
+        let mut values = Vec::new();
+        self.visit_entries(|parser, _| {
+            values.push(parser.read_value()?);
+            Ok(())
+        })?;
+        Ok(values)
+    }
+
+    /// Read an object and return its class name and properties, with the
+    /// property names demangled by [`PhpBstr::to_property`].
+    ///
+    /// ```rust
+    /// use phpserz::{PhpBstr, PhpParser, PhpValue};
+    /// let mut parser = PhpParser::new(b"O:3:\"Foo\":2:{s:6:\"\0Foo\0a\";i:1;s:4:\"\0*\0b\";N;}");
+    /// let (class, properties) = parser.read_object_entries().unwrap();
+    /// assert_eq!(class, PhpBstr::new(b"Foo"));
+    /// assert_eq!(
+    ///     properties,
+    ///     vec![
+    ///         (String::from("a"), PhpValue::Int(1)),
+    ///         (String::from("b"), PhpValue::Null),
+    ///     ]
+    /// );
+    /// ```
+    pub fn read_object_entries(&mut self) -> Result<(PhpBstr<'a>, Vec<(String, PhpValue)>), Error> {
+        let (position, found) = self.upcoming();
+        let PhpToken::Object { class, properties } = self.read_token()? else {
+            return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+        };
+
+        let mut entries = Vec::with_capacity(properties.min(1024) as usize);
+        loop {
+            let (position, _) = self.upcoming();
+            let name = match self.read_token()? {
+                PhpToken::End => return Ok((class, entries)),
+                PhpToken::Integer(key) => key.to_string(),
+                PhpToken::String(key) => key.to_property().to_str()?.to_string(),
+                token => {
+                    let kind = token.kind();
+                    return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+                }
+            };
+            entries.push((name, self.read_value()?));
+        }
+    }
+
+    /// Read an array or object header and call `f` with each key, leaving it
+    /// to consume the value that follows.
+    fn visit_entries(
+        &mut self,
+        mut f: impl FnMut(&mut Self, PhpKey) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let (position, found) = self.upcoming();
+        match self.read_token()? {
+            PhpToken::Array { .. } | PhpToken::Object { .. } => {}
+            _ => return Err(Error::from(ErrorKind::UnexpectedByte { found, position })),
+        }
+
+        loop {
+            let (position, _) = self.upcoming();
+            let key = match self.read_token()? {
+                PhpToken::End => return Ok(()),
+                PhpToken::Integer(key) => PhpKey::Int(key),
+                PhpToken::String(key) => PhpKey::from_bytes(key.as_bytes()),
+                token => {
+                    let kind = token.kind();
+                    return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+                }
+            };
+            f(self, key)?;
+        }
+    }
+
+    /// Read the next complete value like [`PhpParser::read_value`], but keep
+    /// going past malformed input and collect every error along the way.
+    ///
+    /// Recovery is a best-effort heuristic meant for cleaning up corrupted
+    /// data. A malformed scalar, like a string whose declared length is
+    /// wrong, is replaced by null and parsing resumes after the next `;`
+    /// that looks like the end of a token. Entries with a malformed key, or
+    /// a key that can't be one like `d:1.5;`, are dropped, stray bytes
+    /// between tokens are skipped, and references and custom serialized
+    /// objects are reported and replaced by null. A top level scalar that is
+    /// only missing its final `;`, like `i:42`, is unambiguous and kept, with
+    /// an [`ErrorKind::MissingTerminator`] reported as a warning. Any other
+    /// error, like a malformed array header or truncated input, ends parsing
+    /// and the containers still open are returned with the entries read so
+    /// far.
+    ///
+    /// ```rust
+    /// use phpserz::{PhpKey, PhpParser, PhpValue};
+    /// let mut parser = PhpParser::new(b"a:3:{i:0;s:5:\"abc\";i:1;i:x;i:2;b:1;}");
+    /// let (value, errors) = parser.parse_lenient();
+    /// assert_eq!(errors.len(), 2);
+    /// assert_eq!(
+    ///     value,
+    ///     PhpValue::Array(vec![
+    ///         (PhpKey::Int(0), PhpValue::Null),
+    ///         (PhpKey::Int(1), PhpValue::Null),
+    ///         (PhpKey::Int(2), PhpValue::Bool(true)),
+    ///     ])
+    /// );
+    /// ```
+    pub fn parse_lenient(&mut self) -> (PhpValue, Vec<Error>) {
+        let mut errors = Vec::new();
+        let mut abandoned = false;
+        self.allow_unterminated(true);
+        let value = read_value(|| {
+            if !abandoned {
+                match self.read_token_recovering(&mut errors) {
+                    Ok((position, PhpToken::Reference { id, .. })) => {
+                        errors.push(Error::from(ErrorKind::UnsupportedReference {
+                            index: id.index(),
+                            position,
+                        }));
+                        return Ok((position, PhpToken::Null));
+                    }
+                    Ok((position, PhpToken::CustomObject { .. })) => {
+                        errors.push(Error::from(ErrorKind::Deserialize {
+                            message: "Custom serialized objects cannot be represented as a value"
+                                .to_string(),
+                            position: Some(position),
+                        }));
+                        return Ok((position, PhpToken::Null));
+                    }
+                    Ok(token) => return Ok(token),
+                    Err(error) => {
+                        errors.push(error);
+                        abandoned = true;
+                    }
+                }
+            }
+
+            Ok(self.abandon())
+        });
+
+        self.allow_unterminated(false);
+        let value = value.unwrap_or_else(|error| {
+            errors.push(error);
+            PhpValue::Null
+        });
+        (value, errors)
+    }
+}
+
+/// Compare two payloads by the values they hold rather than their bytes.
+///
+/// The order of entries doesn't matter, only which keys map to which values,
+/// and the same goes for object properties. Keys are compared as PHP stores
+/// them, so `s:1:"5";` and `i:5;` are the same key. Values are compared
+/// strictly, unlike PHP's `==`: `i:1;` and `s:1:"1";` differ, and as with
+/// `f64`, `d:NAN;` is never equal to itself. Each input must hold a single
+/// value, which can't contain references or custom serialized objects.
+///
+/// ```rust
+/// use phpserz::structurally_equal;
+/// let a = b"a:2:{i:1;s:1:\"b\";i:0;s:1:\"a\";}";
+/// let b = b"a:2:{i:0;s:1:\"a\";i:1;s:1:\"b\";}";
+/// assert!(structurally_equal(a, b).unwrap());
+/// assert!(!structurally_equal(a, b"a:0:{}").unwrap());
+/// ```
+pub fn structurally_equal(a: &[u8], b: &[u8]) -> Result<bool, Error> {
+    let mut a = read_single_value(a)?;
+    let mut b = read_single_value(b)?;
+    a.sort_entries();
+    b.sort_entries();
+    Ok(a == b)
+}
+
+/// Read double serialized data: a PHP string whose contents are themselves
+/// PHP serialized data, as WordPress stores some values.
+///
+/// Error positions, including those of errors in the inner data, are
+/// relative to the start of `data`. Contents that don't start with a value
+/// fail with [`ErrorKind::NotSerialized`].
+///
+/// ```rust
+/// use phpserz::{PhpKey, PhpValue, unwrap_double_serialized};
+/// let data = b"s:14:\"a:1:{i:0;b:1;}\";";
+/// assert_eq!(
+///     unwrap_double_serialized(data).unwrap(),
+///     PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Bool(true))])
+/// );
+/// ```
+pub fn unwrap_double_serialized(data: &[u8]) -> Result<PhpValue, Error> {
+    let mut parser = PhpParser::new(data);
+    let (position, found) = parser.upcoming();
+    let PhpToken::String(contents) = parser.read_token()? else {
+        return Err(Error::from(ErrorKind::UnexpectedByte { found, position }));
+    };
+
+    let (position, _) = parser.upcoming();
+    if position < data.len() {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+
+    // The contents are a slice of the input, so the inner data is read in
+    // place to keep error positions relative to the input.
+    let end = position - 2;
+    let start = end - contents.as_bytes().len();
+    let mut inner = PhpParser::new(&data[..end]).at(start);
+    if !matches!(inner.peek_token(), Ok(Some(kind)) if kind != PhpTokenKind::End) {
+        return Err(Error::from(ErrorKind::NotSerialized { position: start }));
+    }
+
+    let value = inner.read_value()?;
+    let (position, _) = inner.upcoming();
+    if position < end {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+    Ok(value)
+}
+
+fn read_single_value(data: &[u8]) -> Result<PhpValue, Error> {
+    let mut parser = PhpParser::new(data);
+    let value = parser.read_value()?;
+    let (position, _) = parser.upcoming();
+    if position < data.len() {
+        return Err(Error::from(ErrorKind::TrailingData { position }));
+    }
+    Ok(value)
+}
+
+impl PhpValue {
+    /// Order the entries of arrays and objects by key, recursively.
+    fn sort_entries(&mut self) {
+        match self {
+            PhpValue::Array(entries) => {
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (_, value) in entries {
+                    value.sort_entries();
+                }
+            }
+            PhpValue::Object { properties, .. } => {
+                properties.sort_by(|(a, _), (b, _)| a.cmp(b));
+                for (_, value) in properties {
+                    value.sort_entries();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Build a value from a stream of tokens, each paired with its position.
+///
+/// Values are built without recursion, but dropping, comparing, and sorting
+/// them recurses, so nesting is capped like [`crate::validate`] caps it.
+pub(crate) fn read_value<'a>(
+    mut next_token: impl FnMut() -> Result<(usize, PhpToken<'a>), Error>,
+) -> Result<PhpValue, Error> {
+    struct Frame {
+        class: Option<Vec<u8>>,
+        entries: Vec<(PhpKey, PhpValue)>,
+        key: Option<PhpKey>,
+    }
+
+    let mut stack: Vec<Frame> = Vec::new();
+    loop {
+        let (position, token) = next_token()?;
+        let expect_key = stack.last().is_some_and(|frame| frame.key.is_none());
+        if expect_key
+            && !matches!(
+                token,
+                PhpToken::Integer(_) | PhpToken::String(_) | PhpToken::End
+            )
+        {
+            let kind = token.kind();
+            return Err(Error::from(ErrorKind::InvalidKey { kind, position }));
+        }
+
+        let value = match token {
+            PhpToken::End => {
+                let frame = stack.pop().ok_or_else(|| {
+                    Error::from(ErrorKind::Deserialize {
+                        message: "Expected a value, found the end of a container".to_string(),
+                        position: Some(position),
+                    })
+                })?;
+                match frame.class {
+                    None => PhpValue::Array(frame.entries),
+                    Some(class) => PhpValue::Object {
+                        class,
+                        properties: frame
+                            .entries
+                            .into_iter()
+                            .map(|(key, value)| match key {
+                                PhpKey::Int(i) => (i.to_string().into_bytes(), value),
+                                PhpKey::Str(name) => (name, value),
+                            })
+                            .collect(),
+                    },
+                }
+            }
+            token @ (PhpToken::Integer(_) | PhpToken::String(_)) if expect_key => {
+                let key = match token {
+                    PhpToken::Integer(i) => PhpKey::Int(i),
+                    PhpToken::String(s) => PhpKey::from_bytes(s.as_bytes()),
+                    _ => unreachable!(),
+                };
+                if let Some(frame) = stack.last_mut() {
+                    frame.key = Some(key);
+                }
+                continue;
+            }
+            PhpToken::Array { .. } => {
+                if stack.len() >= DEFAULT_MAX_DEPTH {
+                    return Err(Error::from(ErrorKind::DepthLimitExceeded { position }));
+                }
+                stack.push(Frame {
+                    class: None,
+                    entries: Vec::new(),
+                    key: None,
+                });
+                continue;
+            }
+            PhpToken::Object { class, .. } => {
+                if stack.len() >= DEFAULT_MAX_DEPTH {
+                    return Err(Error::from(ErrorKind::DepthLimitExceeded { position }));
+                }
+                stack.push(Frame {
+                    class: Some(class.as_bytes().to_vec()),
+                    entries: Vec::new(),
+                    key: None,
+                });
+                continue;
+            }
+            PhpToken::Null => PhpValue::Null,
+            PhpToken::Boolean(b) => PhpValue::Bool(b),
+            PhpToken::Integer(i) => PhpValue::Int(i),
+            PhpToken::Float(f) => PhpValue::Float(f),
+            PhpToken::String(s) => PhpValue::String(s.as_bytes().to_vec()),
+            PhpToken::CustomObject { .. } => {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: "Custom serialized objects cannot be represented as a value"
+                        .to_string(),
+                    position: Some(position),
+                }));
+            }
+            PhpToken::Enum { class, case } => PhpValue::Enum {
+                class: class.as_bytes().to_vec(),
+                case: case.as_bytes().to_vec(),
+            },
+            PhpToken::Reference { id, .. } => {
+                return Err(Error::from(ErrorKind::UnsupportedReference {
+                    index: id.index(),
+                    position,
+                }));
+            }
+        };
+
+        let Some(frame) = stack.last_mut() else {
+            return Ok(value);
+        };
+
+        let Some(key) = frame.key.take() else {
+            unreachable!("values in containers follow a key");
+        };
+        frame.entries.push((key, value));
+    }
+}
+
+fn merge_arrays(entries: &mut Vec<(PhpKey, PhpValue)>, others: Vec<(PhpKey, PhpValue)>) {
+    let mut next_index = 0;
+    for (key, _) in entries.iter_mut() {
+        if let PhpKey::Int(index) = key {
+            *index = next_index;
+            next_index += 1;
+        }
+    }
+
+    // Where each string key first appears, as integer keys are renumbered
+    // and never overwrite.
+    let mut positions: HashMap<PhpKey, usize> = HashMap::with_capacity(entries.len());
+    for (position, (key, _)) in entries.iter().enumerate() {
+        if matches!(key, PhpKey::Str(_)) {
+            positions.entry(key.clone()).or_insert(position);
+        }
+    }
+
+    entries.reserve(others.len());
+    for (key, value) in others {
+        match key {
+            PhpKey::Int(_) => {
+                entries.push((PhpKey::Int(next_index), value));
+                next_index += 1;
+            }
+            PhpKey::Str(_) => match positions.get(&key) {
+                Some(&position) => entries[position].1 = value,
+                None => {
+                    positions.insert(key.clone(), entries.len());
+                    entries.push((key, value));
+                }
+            },
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod de {
+    use super::{PhpKey, PhpValue};
+    use crate::errors::{Error, ErrorKind};
+    use crate::parser::PhpBstr;
+    use serde::de::value::{
+        BorrowedStrDeserializer, MapAccessDeserializer, MapDeserializer, SeqDeserializer,
+    };
+    use serde::de::{
+        self, Deserialize, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor,
+    };
+    use std::fmt;
+
+    impl<'de> Deserialize<'de> for PhpKey {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct KeyVisitor;
+
+            impl Visitor<'_> for KeyVisitor {
+                type Value = PhpKey;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("an integer or string key")
+                }
+
+                fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                    Ok(PhpKey::Int(v))
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                    i64::try_from(v)
+                        .map(PhpKey::Int)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                    Ok(PhpKey::from_bytes(v.as_bytes()))
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                    Ok(PhpKey::from_bytes(v))
+                }
+            }
+
+            deserializer.deserialize_any(KeyVisitor)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for PhpValue {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct ValueVisitor;
+
+            impl<'de> Visitor<'de> for ValueVisitor {
+                type Value = PhpValue;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                    formatter.write_str("any PHP value")
+                }
+
+                fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                    Ok(PhpValue::Null)
+                }
+
+                fn visit_none<E>(self) -> Result<Self::Value, E> {
+                    Ok(PhpValue::Null)
+                }
+
+                fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    PhpValue::deserialize(deserializer)
+                }
+
+                fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+                    Ok(PhpValue::Bool(v))
+                }
+
+                fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+                    Ok(PhpValue::Int(v))
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                    i64::try_from(v)
+                        .map(PhpValue::Int)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+                }
+
+                fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+                    Ok(PhpValue::Float(v))
+                }
+
+                fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+                    Ok(PhpValue::String(v.as_bytes().to_vec()))
+                }
+
+                fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E> {
+                    Ok(PhpValue::String(v.to_vec()))
+                }
+
+                fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+                where
+                    A: SeqAccess<'de>,
+                {
+                    let mut entries = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                    let mut index = 0;
+                    while let Some(value) = seq.next_element()? {
+                        entries.push((PhpKey::Int(index), value));
+                        index += 1;
+                    }
+                    Ok(PhpValue::Array(entries))
+                }
+
+                fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+                where
+                    A: MapAccess<'de>,
+                {
+                    let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                    while let Some(entry) = map.next_entry()? {
+                        entries.push(entry);
+                    }
+                    Ok(PhpValue::Array(entries))
+                }
+            }
+
+            deserializer.deserialize_any(ValueVisitor)
+        }
+    }
+
+    /// An array key or a demangled property name, as seen by a visitor.
+    #[derive(Clone, Copy)]
+    enum EntryKey<'de> {
+        Int(i64),
+        Name(&'de [u8]),
+    }
+
+    impl<'de> Deserializer<'de> for EntryKey<'de> {
+        type Error = Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                EntryKey::Int(i) => visitor.visit_i64(i),
+                EntryKey::Name(name) => visitor.visit_borrowed_bytes(name),
+            }
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+            bytes byte_buf option unit unit_struct newtype_struct seq tuple
+            tuple_struct map struct enum identifier ignored_any
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for EntryKey<'de> {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self::Deserializer {
+            self
+        }
+    }
+
+    impl<'de> IntoDeserializer<'de, Error> for &'de PhpValue {
+        type Deserializer = Self;
+
+        fn into_deserializer(self) -> Self::Deserializer {
+            self
+        }
+    }
+
+    fn entries<'de>(
+        entries: &'de [(PhpKey, PhpValue)],
+    ) -> MapDeserializer<'de, impl Iterator<Item = (EntryKey<'de>, &'de PhpValue)>, Error> {
+        MapDeserializer::new(entries.iter().map(|(key, value)| {
+            let key = match key {
+                PhpKey::Int(i) => EntryKey::Int(*i),
+                PhpKey::Str(s) => EntryKey::Name(s),
+            };
+            (key, value)
+        }))
+    }
+
+    fn properties<'de>(
+        properties: &'de [(Vec<u8>, PhpValue)],
+    ) -> MapDeserializer<'de, impl Iterator<Item = (EntryKey<'de>, &'de PhpValue)>, Error> {
+        MapDeserializer::new(properties.iter().map(|(name, value)| {
+            let name = PhpBstr::new(name).to_property().as_bytes();
+            (EntryKey::Name(name), value)
+        }))
+    }
+
+    /// Deserialize a user type from an already parsed value, like
+    /// `serde_json::from_value`.
+    ///
+    /// Values are interpreted as [`PhpDeserializer`](crate::PhpDeserializer)
+    /// interprets the serialized data with its default options: object
+    /// property names are demangled, arrays with the keys `0..n` in order are
+    /// sequences, and enum cases and strings are unit variants.
+    impl<'de> Deserializer<'de> for &'de PhpValue {
+        type Error = Error;
+
+        fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                PhpValue::Null => visitor.visit_unit(),
+                PhpValue::Bool(b) => visitor.visit_bool(*b),
+                PhpValue::Int(i) => visitor.visit_i64(*i),
+                PhpValue::Float(f) => visitor.visit_f64(*f),
+                PhpValue::String(s) => visitor.visit_borrowed_bytes(s),
+                PhpValue::Array(entries) => {
+                    let mut map = self::entries(entries);
+                    let value = visitor.visit_map(&mut map)?;
+                    map.end()?;
+                    Ok(value)
+                }
+                PhpValue::Object { properties, .. } => {
+                    let mut map = self::properties(properties);
+                    let value = visitor.visit_map(&mut map)?;
+                    map.end()?;
+                    Ok(value)
+                }
+                PhpValue::Enum { case, .. } => visitor.visit_borrowed_bytes(case),
+            }
+        }
+
+        fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                PhpValue::String(s) => visitor.visit_borrowed_str(PhpBstr::new(s).to_str()?),
+                _ => self.deserialize_any(visitor),
+            }
+        }
+
+        fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.deserialize_str(visitor)
+        }
+
+        fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.deserialize_str(visitor)
+        }
+
+        fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                PhpValue::Null => visitor.visit_none(),
+                _ => visitor.visit_some(self),
+            }
+        }
+
+        fn deserialize_newtype_struct<V>(
+            self,
+            _name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_newtype_struct(self)
+        }
+
+        fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            let PhpValue::Array(entries) = self else {
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message: "Expected array".to_string(),
+                    position: None,
+                }));
+            };
+
+            for (expected_index, (key, _)) in (0..).zip(entries) {
+                let message = match key {
+                    PhpKey::Int(index) if *index == expected_index => continue,
+                    PhpKey::Int(index) => format!(
+                        "Expected sequence index {expected_index}, found integer key {index}"
+                    ),
+                    PhpKey::Str(_) => {
+                        format!("Expected sequence index {expected_index}, found non-integer key")
+                    }
+                };
+
+                return Err(Error::from(ErrorKind::Deserialize {
+                    message,
+                    position: None,
+                }));
+            }
+
+            let mut seq = SeqDeserializer::new(entries.iter().map(|(_, value)| value));
+            let value = visitor.visit_seq(&mut seq)?;
+            seq.end()?;
+            Ok(value)
+        }
+
+        fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.deserialize_seq(visitor)
+        }
+
+        fn deserialize_tuple_struct<V>(
+            self,
+            _name: &'static str,
+            _len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            self.deserialize_seq(visitor)
+        }
+
+        fn deserialize_enum<V>(
+            self,
+            _name: &'static str,
+            _variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            match self {
+                PhpValue::String(case) | PhpValue::Enum { case, .. } => {
+                    let case = PhpBstr::new(case).to_str()?;
+                    visitor.visit_enum(BorrowedStrDeserializer::new(case))
+                }
+                PhpValue::Array(entries) if entries.len() == 1 => {
+                    visitor.visit_enum(MapAccessDeserializer::new(self::entries(entries)))
+                }
+                _ => Err(Error::from(ErrorKind::Deserialize {
+                    message: "Expected string or single entry array for enum".to_string(),
+                    position: None,
+                })),
+            }
+        }
+
+        fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+        where
+            V: Visitor<'de>,
+        {
+            visitor.visit_unit()
+        }
+
+        serde::forward_to_deserialize_any! {
+            bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 bytes byte_buf
+            unit unit_struct map struct identifier
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(b"0", PhpKey::Int(0))]
+    #[case(b"5", PhpKey::Int(5))]
+    #[case(b"-12", PhpKey::Int(-12))]
+    #[case(b"9223372036854775807", PhpKey::Int(i64::MAX))]
+    #[case(b"-9223372036854775808", PhpKey::Int(i64::MIN))]
+    #[case(b"9223372036854775808", PhpKey::Str(b"9223372036854775808".to_vec()))]
+    #[case(b"-0", PhpKey::Str(b"-0".to_vec()))]
+    #[case(b"05", PhpKey::Str(b"05".to_vec()))]
+    #[case(b"+5", PhpKey::Str(b"+5".to_vec()))]
+    #[case(b"5.0", PhpKey::Str(b"5.0".to_vec()))]
+    #[case(b" 5", PhpKey::Str(b" 5".to_vec()))]
+    #[case(b"-", PhpKey::Str(b"-".to_vec()))]
+    #[case(b"", PhpKey::Str(Vec::new()))]
+    fn test_key_from_bytes(#[case] input: &[u8], #[case] expected: PhpKey) {
+        assert_eq!(PhpKey::from_bytes(input), expected);
+    }
+
+    fn str_key(name: &str) -> PhpKey {
+        PhpKey::Str(name.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn test_merge_mixed_keys() {
+        let mut base = PhpValue::Array(vec![
+            (PhpKey::Int(3), PhpValue::Int(1)),
+            (str_key("a"), PhpValue::Int(2)),
+            (PhpKey::Int(7), PhpValue::Int(3)),
+            (str_key("b"), PhpValue::Int(4)),
+        ]);
+        let patch = PhpValue::Array(vec![
+            (str_key("b"), PhpValue::Int(40)),
+            (PhpKey::Int(0), PhpValue::Int(5)),
+            (str_key("c"), PhpValue::Int(6)),
+            (PhpKey::Int(3), PhpValue::Int(7)),
+        ]);
+
+        base.merge(patch);
+        assert_eq!(
+            base,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Int(1)),
+                (str_key("a"), PhpValue::Int(2)),
+                (PhpKey::Int(1), PhpValue::Int(3)),
+                (str_key("b"), PhpValue::Int(40)),
+                (PhpKey::Int(2), PhpValue::Int(5)),
+                (str_key("c"), PhpValue::Int(6)),
+                (PhpKey::Int(3), PhpValue::Int(7)),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_is_list() {
+        let list = PhpValue::Array(vec![
+            (PhpKey::Int(0), PhpValue::Int(1)),
+            (PhpKey::Int(1), PhpValue::Int(2)),
+        ]);
+        assert!(list.is_list());
+        assert!(PhpValue::Array(Vec::new()).is_list());
+
+        let reordered = PhpValue::Array(vec![
+            (PhpKey::Int(1), PhpValue::Int(1)),
+            (PhpKey::Int(0), PhpValue::Int(2)),
+        ]);
+        assert!(!reordered.is_list());
+
+        let map = PhpValue::Array(vec![(str_key("0"), PhpValue::Int(1))]);
+        assert!(!map.is_list());
+    }
+
+    #[test]
+    fn test_canonicalize_shuffled_array() {
+        let mut value = PhpValue::Array(vec![
+            (PhpKey::Int(2), PhpValue::Int(1)),
+            (PhpKey::Int(0), PhpValue::Int(2)),
+            (PhpKey::Int(1), PhpValue::Int(3)),
+        ]);
+        value.canonicalize();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Int(2)),
+                (PhpKey::Int(1), PhpValue::Int(3)),
+                (PhpKey::Int(2), PhpValue::Int(1)),
+            ])
+        );
+    }
+
+    #[rstest]
+    #[case(&[2, 0, 9])]
+    #[case(&[1, 1])]
+    #[case(&[-1, 0])]
+    fn test_canonicalize_keeps_keys(#[case] keys: &[i64]) {
+        let array = PhpValue::Array(
+            keys.iter()
+                .map(|&key| (PhpKey::Int(key), PhpValue::Int(key)))
+                .collect(),
+        );
+        let mut value = array.clone();
+        value.canonicalize();
+        assert_eq!(value, array);
+    }
+
+    #[test]
+    fn test_canonicalize_nested() {
+        let list = PhpValue::Array(vec![
+            (PhpKey::Int(0), PhpValue::Int(1)),
+            (PhpKey::Int(1), PhpValue::Int(2)),
+        ]);
+        let gapped = PhpValue::Array(vec![
+            (PhpKey::Int(3), PhpValue::Int(1)),
+            (PhpKey::Int(5), PhpValue::Int(2)),
+        ]);
+        let mut value = PhpValue::Array(vec![
+            (str_key("list"), list.clone()),
+            (str_key("gapped"), gapped.clone()),
+            (
+                str_key("object"),
+                PhpValue::Object {
+                    class: b"Foo".to_vec(),
+                    properties: vec![(
+                        b"items".to_vec(),
+                        PhpValue::Array(vec![
+                            (PhpKey::Int(1), PhpValue::Int(2)),
+                            (PhpKey::Int(0), PhpValue::Int(1)),
+                        ]),
+                    )],
+                },
+            ),
+        ]);
+        value.canonicalize();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (str_key("list"), list.clone()),
+                (str_key("gapped"), gapped),
+                (
+                    str_key("object"),
+                    PhpValue::Object {
+                        class: b"Foo".to_vec(),
+                        properties: vec![(b"items".to_vec(), list)],
+                    },
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_canonicalize_deep() {
+        let mut value = PhpValue::Null;
+        for _ in 0..100_000 {
+            value = PhpValue::Array(vec![(PhpKey::Int(0), value)]);
+        }
+        value.canonicalize();
+
+        // Dropping is recursive, so unwind the nesting by hand.
+        let mut pending = vec![value];
+        while let Some(value) = pending.pop() {
+            if let PhpValue::Array(entries) = value {
+                pending.extend(entries.into_iter().map(|(_, value)| value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_read_value() {
+        let input = b"a:3:{i:0;O:3:\"Foo\":1:{s:6:\"\0*\0bar\";d:1.5;}s:1:\"5\";E:11:\"Suit:Hearts\";s:1:\"c\";a:0:{}}";
+        let value = PhpParser::new(input).read_value().unwrap();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (
+                    PhpKey::Int(0),
+                    PhpValue::Object {
+                        class: b"Foo".to_vec(),
+                        properties: vec![(b"\0*\0bar".to_vec(), PhpValue::Float(1.5))],
+                    }
+                ),
+                (
+                    PhpKey::Int(5),
+                    PhpValue::Enum {
+                        class: b"Suit".to_vec(),
+                        case: b"Hearts".to_vec(),
+                    }
+                ),
+                (str_key("c"), PhpValue::Array(Vec::new())),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_value_depth_limit() {
+        let depth = 1_000_000;
+        let mut input = "a:1:{i:0;".repeat(depth).into_bytes();
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        let error = PhpParser::new(&input).read_value().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::DepthLimitExceeded { position } if *position == 128 * 9
+        ));
+
+        let depth = DEFAULT_MAX_DEPTH;
+        let mut input = "O:8:\"stdClass\":1:{s:1:\"a\";".repeat(depth).into_bytes();
+        input.extend_from_slice(b"N;");
+        input.extend(std::iter::repeat_n(b'}', depth));
+        PhpParser::new(&input).read_value().unwrap();
+    }
+
+    #[rstest]
+    #[case(|parser: &mut PhpParser| parser.read_value().map(drop))]
+    #[case(|parser: &mut PhpParser| parser.collect_keys().map(drop))]
+    #[case(|parser: &mut PhpParser| parser.read_object_entries().map(drop))]
+    fn test_invalid_key(#[case] read: fn(&mut PhpParser) -> Result<(), Error>) {
+        let mut parser = PhpParser::new(b"O:3:\"Foo\":1:{a:0:{}N;}");
+        let error = read(&mut parser).unwrap_err();
+        assert!(
+            matches!(
+                error.kind(),
+                ErrorKind::InvalidKey {
+                    kind: PhpTokenKind::Array,
+                    position: 13
+                }
+            ),
+            "{error:?}"
+        );
+    }
+
+    #[test]
+    fn test_read_value_reference() {
+        let error = PhpParser::new(b"a:2:{i:0;i:1;i:1;R:2;}")
+            .read_value()
+            .unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnsupportedReference {
+                index: 2,
+                position: 17
+            }
+        ));
+    }
+
+    #[test]
+    fn test_collect_keys_and_values() {
+        let input = b"a:4:{i:0;s:1:\"a\";s:3:\"foo\";a:1:{i:0;i:1;}s:2:\"10\";d:1.5;i:-2;O:3:\"Foo\":0:{}}i:9;";
+        let mut parser = PhpParser::new(input);
+        assert_eq!(
+            parser.collect_keys().unwrap(),
+            vec![
+                PhpKey::Int(0),
+                str_key("foo"),
+                PhpKey::Int(10),
+                PhpKey::Int(-2)
+            ]
+        );
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(9));
+
+        let mut parser = PhpParser::new(input);
+        assert_eq!(
+            parser.collect_values().unwrap(),
+            vec![
+                PhpValue::String(b"a".to_vec()),
+                PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Int(1))]),
+                PhpValue::Float(1.5),
+                PhpValue::Object {
+                    class: b"Foo".to_vec(),
+                    properties: Vec::new(),
+                },
+            ]
+        );
+        assert_eq!(parser.read_token().unwrap(), PhpToken::Integer(9));
+    }
+
+    #[test]
+    fn test_unwrap_double_serialized() {
+        let inner = b"a:2:{s:4:\"name\";s:5:\"Alice\";s:5:\"roles\";a:1:{i:0;s:5:\"admin\";}}";
+        let mut data = format!("s:{}:\"", inner.len()).into_bytes();
+        data.extend_from_slice(inner);
+        data.extend_from_slice(b"\";");
+
+        let value = unwrap_double_serialized(&data).unwrap();
+        assert_eq!(value, PhpParser::new(inner).read_value().unwrap());
+    }
+
+    #[rstest]
+    #[case(b"s:5:\"hello\";", ErrorKind::NotSerialized { position: 5 })]
+    #[case(b"s:0:\"\";", ErrorKind::NotSerialized { position: 5 })]
+    #[case(b"s:1:\"}\";", ErrorKind::NotSerialized { position: 5 })]
+    #[case(b"a:0:{}", ErrorKind::UnexpectedByte { found: b'a', position: 0 })]
+    #[case(b"s:4:\"i:1;\";i:2;", ErrorKind::TrailingData { position: 11 })]
+    #[case(b"s:8:\"i:1;i:2;\";", ErrorKind::TrailingData { position: 9 })]
+    #[case(b"s:3:\"i:1\";", ErrorKind::Eof)]
+    #[case(b"s:4:\"i:x;\";", ErrorKind::InvalidNumber { position: 7 })]
+    fn test_unwrap_double_serialized_errors(#[case] data: &[u8], #[case] expected: ErrorKind) {
+        let error = unwrap_double_serialized(data).unwrap_err();
+        assert_eq!(error.to_string(), Error::from(expected).to_string());
+    }
+
+    #[test]
+    fn test_read_value_with_span() {
+        let input = b"a:2:{s:4:\"user\";a:2:{s:4:\"name\";s:3:\"Bob\";s:3:\"age\";i:30;}s:4:\"tags\";a:0:{}}";
+        let mut parser = PhpParser::new(input);
+        parser.read_token().unwrap();
+        parser.read_token().unwrap();
+
+        let (value, span) = parser.read_value_with_span().unwrap();
+        assert_eq!(span, 16..58);
+        assert_eq!(
+            PhpParser::new(&input[span.clone()]).read_value().unwrap(),
+            value
+        );
+
+        // Splice a modified value back in place of the original.
+        let PhpValue::Array(mut entries) = value else {
+            panic!("expected an array");
+        };
+        entries[1].1 = PhpValue::Int(31);
+        let replacement = b"a:2:{s:4:\"name\";s:3:\"Bob\";s:3:\"age\";i:31;}";
+        let mut edited = input.to_vec();
+        edited.splice(span, replacement.iter().copied());
+        assert_eq!(
+            PhpParser::new(&edited).read_value().unwrap(),
+            PhpValue::Array(vec![
+                (PhpKey::Str(b"user".to_vec()), PhpValue::Array(entries)),
+                (PhpKey::Str(b"tags".to_vec()), PhpValue::Array(Vec::new())),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_value_with_span_skips_line_breaks() {
+        let input = b"\ni:1;\r\nb:0;";
+        let mut parser = PhpParser::new(input).skip_line_breaks(true);
+        assert_eq!(
+            parser.read_value_with_span().unwrap(),
+            (PhpValue::Int(1), 1..5)
+        );
+        assert_eq!(
+            parser.read_value_with_span().unwrap(),
+            (PhpValue::Bool(false), 7..11)
+        );
+    }
+
+    #[test]
+    fn test_read_object_entries() {
+        let input = b"O:7:\"Example\":5:{s:4:\"name\";s:8:\"John Doe\";s:12:\"\0Example\0age\";i:42;s:11:\"\0*\0isActive\";b:1;s:6:\"scores\";a:3:{i:0;d:95.5;i:1;d:88.0;i:2;d:92.3;}s:8:\"metadata\";a:2:{s:2:\"id\";i:12345;s:4:\"tags\";a:3:{i:0;s:3:\"php\";i:1;s:4:\"rust\";i:2;s:13:\"serialization\";}}}";
+        let mut parser = PhpParser::new(input);
+        let (class, properties) = parser.read_object_entries().unwrap();
+        assert_eq!(class, PhpBstr::new(b"Example"));
+
+        let names: Vec<_> = properties.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["name", "age", "isActive", "scores", "metadata"]);
+        assert_eq!(properties[0].1, PhpValue::String(b"John Doe".to_vec()));
+        assert_eq!(properties[1].1, PhpValue::Int(42));
+        assert_eq!(properties[2].1, PhpValue::Bool(true));
+        assert_eq!(
+            properties[3].1,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Float(95.5)),
+                (PhpKey::Int(1), PhpValue::Float(88.0)),
+                (PhpKey::Int(2), PhpValue::Float(92.3)),
+            ])
+        );
+        assert!(matches!(properties[4].1, PhpValue::Array(ref entries) if entries.len() == 2));
+        assert_eq!(parser.next_token().unwrap(), None);
+    }
+
+    #[test]
+    fn test_read_object_entries_requires_object() {
+        let error = PhpParser::new(b"a:0:{}").read_object_entries().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'a',
+                position: 0
+            }
+        ));
+
+        let mut parser = PhpParser::new(b"O:8:\"stdClass\":1:{i:0;b:0;}");
+        let (_, properties) = parser.read_object_entries().unwrap();
+        assert_eq!(properties, vec![(String::from("0"), PhpValue::Bool(false))]);
+    }
+
+    #[test]
+    fn test_collect_keys_requires_container() {
+        let error = PhpParser::new(b"i:1;").collect_keys().unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'i',
+                position: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_lenient_two_errors() {
+        let input = b"a:3:{i:0;s:5:\"abc\";i:1;i:x;i:2;b:1;}";
+        let (value, errors) = PhpParser::new(input).parse_lenient();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Null),
+                (PhpKey::Int(1), PhpValue::Null),
+                (PhpKey::Int(2), PhpValue::Bool(true)),
+            ])
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[0].kind(),
+            ErrorKind::MissingQuotes { position: 11 }
+        ));
+        assert!(matches!(
+            errors[1].kind(),
+            ErrorKind::InvalidNumber { position: 25 }
+        ));
+    }
+
+    #[test]
+    fn test_parse_lenient_valid() {
+        let input = b"a:2:{i:0;i:1;s:1:\"a\";a:0:{}}";
+        let (value, errors) = PhpParser::new(input).parse_lenient();
+        assert!(errors.is_empty());
+        assert_eq!(value, PhpParser::new(input).read_value().unwrap());
+    }
+
+    #[test]
+    fn test_parse_lenient_drops_bad_keys_and_stray_bytes() {
+        let input = b"a:3:{s:9:\"a\";i:1;x;i:2;b:0;i:3;b:1;}";
+        let (value, errors) = PhpParser::new(input).parse_lenient();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (PhpKey::Int(2), PhpValue::Bool(false)),
+                (PhpKey::Int(3), PhpValue::Bool(true)),
+            ])
+        );
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(
+            errors[1].kind(),
+            ErrorKind::UnexpectedByte {
+                found: b'x',
+                position: 17
+            }
+        ));
+    }
+
+    #[rstest]
+    #[case(b"a:3:{i:0;i:1;d:1.5;i:2;i:2;i:3;}", 13, PhpTokenKind::Float)]
+    #[case(b"a:3:{i:0;i:1;N;i:2;i:2;i:3;}", 13, PhpTokenKind::Null)]
+    #[case(b"a:3:{i:0;i:1;b:1;a:1:{i:0;N;}i:2;i:3;}", 13, PhpTokenKind::Boolean)]
+    #[case(b"a:3:{i:0;i:1;a:1:{i:0;N;}i:5;i:2;i:3;}", 13, PhpTokenKind::Array)]
+    fn test_parse_lenient_invalid_key(
+        #[case] input: &[u8],
+        #[case] position: usize,
+        #[case] kind: PhpTokenKind,
+    ) {
+        let (value, errors) = PhpParser::new(input).parse_lenient();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Int(1)),
+                (PhpKey::Int(2), PhpValue::Int(3)),
+            ])
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].kind(),
+            ErrorKind::InvalidKey { kind: k, position: p } if *k == kind && *p == position
+        ));
+    }
+
+    #[test]
+    fn test_parse_lenient_truncated() {
+        let input = b"a:2:{i:0;a:2:{i:0;i:1;i:1;s:3:\"ab";
+        let (value, errors) = PhpParser::new(input).parse_lenient();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![(
+                PhpKey::Int(0),
+                PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Int(1))])
+            )])
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0].kind(),
+            ErrorKind::TruncatedString { declared: 3, .. }
+        ));
+    }
+
+    #[rstest]
+    #[case(b"i:42", PhpValue::Int(42))]
+    #[case(b"i:-7", PhpValue::Int(-7))]
+    #[case(b"N", PhpValue::Null)]
+    #[case(b"b:1", PhpValue::Bool(true))]
+    #[case(b"d:1.5", PhpValue::Float(1.5))]
+    #[case(b"s:3:\"abc\"", PhpValue::String(b"abc".to_vec()))]
+    fn test_parse_lenient_missing_semicolon(#[case] input: &[u8], #[case] expected: PhpValue) {
+        let error = PhpParser::new(input).read_value().unwrap_err();
+        assert!(matches!(error.kind(), ErrorKind::Eof), "{error:?}");
+
+        let mut parser = PhpParser::new(input);
+        let (value, errors) = parser.parse_lenient();
+        assert_eq!(value, expected);
+        assert!(matches!(
+            errors[..],
+            [ref error] if matches!(error.kind(), ErrorKind::MissingTerminator { position } if *position == input.len())
+        ));
+        assert_eq!(parser.position(), input.len());
+    }
+
+    #[test]
+    fn test_parse_lenient_missing_semicolon_nested() {
+        let (value, errors) = PhpParser::new(b"a:1:{i:0;i:42").parse_lenient();
+        assert_eq!(value, PhpValue::Array(Vec::new()));
+        assert!(matches!(
+            errors[..],
+            [ref error] if matches!(error.kind(), ErrorKind::Eof)
+        ));
+    }
+
+    #[test]
+    fn test_parse_lenient_reference() {
+        let (value, errors) = PhpParser::new(b"a:2:{i:0;i:1;i:1;R:2;}").parse_lenient();
+        assert_eq!(
+            value,
+            PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Int(1)),
+                (PhpKey::Int(1), PhpValue::Null),
+            ])
+        );
+        assert!(matches!(
+            errors[..],
+            [ref error] if matches!(error.kind(), ErrorKind::UnsupportedReference { index: 2, .. })
+        ));
+    }
+
+    #[rstest]
+    #[case(b"a:2:{i:0;i:1;i:1;i:2;}", b"a:2:{i:1;i:2;i:0;i:1;}")]
+    #[case(b"a:1:{s:1:\"5\";N;}", b"a:1:{i:5;N;}")]
+    #[case(
+        b"O:3:\"Foo\":2:{s:1:\"a\";a:2:{s:1:\"x\";b:1;s:1:\"y\";b:0;}s:1:\"b\";d:0.5;}",
+        b"O:3:\"Foo\":2:{s:1:\"b\";d:0.5;s:1:\"a\";a:2:{s:1:\"y\";b:0;s:1:\"x\";b:1;}}"
+    )]
+    fn test_structurally_equal(#[case] a: &[u8], #[case] b: &[u8]) {
+        assert_ne!(a, b);
+        assert!(structurally_equal(a, b).unwrap());
+        assert!(structurally_equal(b, a).unwrap());
+    }
+
+    #[rstest]
+    #[case(b"a:1:{i:0;i:1;}", b"a:1:{i:1;i:1;}")]
+    #[case(b"a:1:{i:0;i:1;}", b"a:1:{i:0;s:1:\"1\";}")]
+    #[case(b"a:1:{s:2:\"05\";N;}", b"a:1:{i:5;N;}")]
+    #[case(b"O:3:\"Foo\":0:{}", b"O:3:\"Bar\":0:{}")]
+    #[case(b"O:3:\"Foo\":0:{}", b"a:0:{}")]
+    #[case(b"d:NAN;", b"d:NAN;")]
+    fn test_structurally_unequal(#[case] a: &[u8], #[case] b: &[u8]) {
+        assert!(!structurally_equal(a, b).unwrap());
+    }
+
+    #[test]
+    fn test_structurally_equal_trailing_data() {
+        let error = structurally_equal(b"i:1;", b"i:1;i:2;").unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            ErrorKind::TrailingData { position: 4 }
+        ));
+    }
+
+    #[test]
+    fn test_merge_replaces_non_arrays() {
+        let object = PhpValue::Object {
+            class: b"Foo".to_vec(),
+            properties: vec![(b"a".to_vec(), PhpValue::Int(1))],
+        };
+
+        let mut base = PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Null)]);
+        base.merge(object.clone());
+        assert_eq!(base, object);
+
+        let mut base = object;
+        base.merge(PhpValue::Array(Vec::new()));
+        assert_eq!(base, PhpValue::Array(Vec::new()));
+
+        let mut base = PhpValue::Int(1);
+        base.merge(PhpValue::String(b"x".to_vec()));
+        assert_eq!(base, PhpValue::String(b"x".to_vec()));
+    }
+
+    #[test]
+    fn test_entries() {
+        let value = PhpValue::Array(vec![
+            (PhpKey::Int(0), PhpValue::Int(1)),
+            (PhpKey::Str(b"b".to_vec()), PhpValue::Int(2)),
+            (PhpKey::Int(5), PhpValue::Int(3)),
+        ]);
+
+        let mut entries = value.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries.next(), Some((&PhpKey::Int(0), &PhpValue::Int(1))));
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            entries.next_back(),
+            Some((&PhpKey::Int(5), &PhpValue::Int(3)))
+        );
+        assert_eq!(entries.len(), 1);
+
+        let keys: Vec<_> = value.entries().rev().map(|(key, _)| key.clone()).collect();
+        assert_eq!(
+            keys,
+            vec![PhpKey::Int(5), PhpKey::Str(b"b".to_vec()), PhpKey::Int(0)]
+        );
+
+        assert_eq!(value.entries().size_hint(), (3, Some(3)));
+    }
+
+    #[rstest]
+    #[case(PhpValue::Null)]
+    #[case(PhpValue::String(b"abc".to_vec()))]
+    #[case(PhpValue::Object { class: b"Foo".to_vec(), properties: vec![(b"a".to_vec(), PhpValue::Null)] })]
+    fn test_entries_of_non_arrays(#[case] value: PhpValue) {
+        assert_eq!(value.entries().len(), 0);
+        assert_eq!(value.entries().next_back(), None);
+    }
+
+    #[cfg(feature = "serde")]
+    mod serde {
+        use super::*;
+        use crate::PhpDeserializer;
+        use ::serde::Deserialize;
+        use std::collections::BTreeMap;
+
+        #[test]
+        fn test_deserialize_btreemap_preserves_key_types() {
+            let input = b"a:4:{i:0;s:1:\"a\";s:3:\"foo\";i:1;s:1:\"5\";N;i:-3;b:0;}";
+            let mut deserializer = PhpDeserializer::new(input);
+            let map: BTreeMap<PhpKey, PhpValue> =
+                Deserialize::deserialize(&mut deserializer).unwrap();
+            assert_eq!(
+                map,
+                BTreeMap::from([
+                    (PhpKey::Int(0), PhpValue::String(b"a".to_vec())),
+                    (PhpKey::Str(b"foo".to_vec()), PhpValue::Int(1)),
+                    (PhpKey::Int(5), PhpValue::Null),
+                    (PhpKey::Int(-3), PhpValue::Bool(false)),
+                ])
+            );
+        }
+
+        #[test]
+        fn test_deserialize_numeric_string_key() {
+            let mut deserializer = PhpDeserializer::new(b"a:1:{s:1:\"5\";s:4:\"five\";}");
+            let map: BTreeMap<PhpKey, PhpValue> =
+                Deserialize::deserialize(&mut deserializer).unwrap();
+            assert_eq!(
+                map.into_iter().collect::<Vec<_>>(),
+                vec![(PhpKey::Int(5), PhpValue::String(b"five".to_vec()))]
+            );
+        }
+
+        #[test]
+        fn test_deserialize_value_field() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            struct Envelope {
+                kind: String,
+                payload: PhpValue,
+            }
+
+            let input = b"a:2:{s:4:\"kind\";s:5:\"event\";s:7:\"payload\";a:2:{s:2:\"id\";i:9;s:4:\"tags\";a:1:{i:0;s:1:\"x\";}}}";
+            let mut deserializer = PhpDeserializer::new(input);
+            let envelope = Envelope::deserialize(&mut deserializer).unwrap();
+            assert_eq!(
+                envelope,
+                Envelope {
+                    kind: "event".to_string(),
+                    payload: PhpValue::Array(vec![
+                        (PhpKey::Str(b"id".to_vec()), PhpValue::Int(9)),
+                        (
+                            PhpKey::Str(b"tags".to_vec()),
+                            PhpValue::Array(vec![(
+                                PhpKey::Int(0),
+                                PhpValue::String(b"x".to_vec())
+                            )])
+                        ),
+                    ]),
+                }
+            );
+        }
+
+        #[test]
+        fn test_deserialize_nested_value() {
+            let input = b"a:2:{i:0;a:1:{i:0;d:1.5;}i:1;O:3:\"Foo\":1:{s:6:\"\0*\0bar\";i:7;}}";
+            let mut deserializer = PhpDeserializer::new(input);
+            let value = PhpValue::deserialize(&mut deserializer).unwrap();
+            assert_eq!(
+                value,
+                PhpValue::Array(vec![
+                    (
+                        PhpKey::Int(0),
+                        PhpValue::Array(vec![(PhpKey::Int(0), PhpValue::Float(1.5))])
+                    ),
+                    (
+                        PhpKey::Int(1),
+                        PhpValue::Array(vec![(
+                            PhpKey::Str(b"\0*\0bar".to_vec()),
+                            PhpValue::Int(7)
+                        )])
+                    ),
+                ])
+            );
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Example {
+            name: String,
+            age: i32,
+            #[serde(rename = "isActive")]
+            is_active: bool,
+            scores: BTreeMap<u32, f64>,
+            metadata: Metadata,
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Metadata {
+            id: i32,
+            tags: Vec<String>,
+        }
+
+        #[test]
+        fn test_deserialize_from_value() {
+            let value = PhpValue::Object {
+                class: b"Example".to_vec(),
+                properties: vec![
+                    (b"name".to_vec(), PhpValue::String(b"John Doe".to_vec())),
+                    (b"\0Example\0age".to_vec(), PhpValue::Int(42)),
+                    (b"\0*\0isActive".to_vec(), PhpValue::Bool(true)),
+                    (
+                        b"scores".to_vec(),
+                        PhpValue::Array(vec![
+                            (PhpKey::Int(0), PhpValue::Float(95.5)),
+                            (PhpKey::Int(1), PhpValue::Float(88.0)),
+                            (PhpKey::Int(2), PhpValue::Float(92.3)),
+                        ]),
+                    ),
+                    (
+                        b"metadata".to_vec(),
+                        PhpValue::Array(vec![
+                            (PhpKey::Str(b"id".to_vec()), PhpValue::Int(12345)),
+                            (
+                                PhpKey::Str(b"tags".to_vec()),
+                                PhpValue::Array(vec![
+                                    (PhpKey::Int(0), PhpValue::String(b"php".to_vec())),
+                                    (PhpKey::Int(1), PhpValue::String(b"rust".to_vec())),
+                                ]),
+                            ),
+                        ]),
+                    ),
+                ],
+            };
+
+            let example = Example::deserialize(&value).unwrap();
+            assert_eq!(
+                example,
+                Example {
+                    name: "John Doe".to_string(),
+                    age: 42,
+                    is_active: true,
+                    scores: BTreeMap::from([(0, 95.5), (1, 88.0), (2, 92.3)]),
+                    metadata: Metadata {
+                        id: 12345,
+                        tags: vec!["php".to_string(), "rust".to_string()],
+                    },
+                }
+            );
+        }
+
+        #[test]
+        fn test_deserialize_from_parsed_value() {
+            let input = b"O:7:\"Example\":5:{s:4:\"name\";s:8:\"John Doe\";s:12:\"\0Example\0age\";i:42;s:11:\"\0*\0isActive\";b:1;s:6:\"scores\";a:3:{i:0;d:95.5;i:1;d:88.0;i:2;d:92.3;}s:8:\"metadata\";a:2:{s:2:\"id\";i:12345;s:4:\"tags\";a:3:{i:0;s:3:\"php\";i:1;s:4:\"rust\";i:2;s:13:\"serialization\";}}}";
+            let value = PhpParser::new(input).read_value().unwrap();
+            let from_value = Example::deserialize(&value).unwrap();
+            let from_bytes = Example::deserialize(&mut PhpDeserializer::new(input)).unwrap();
+            assert_eq!(from_value, from_bytes);
+        }
+
+        #[test]
+        fn test_deserialize_from_value_shapes() {
+            #[derive(Debug, Deserialize, PartialEq)]
+            enum Suit {
+                Hearts,
+                Spades,
+            }
+
+            #[derive(Debug, Deserialize, PartialEq)]
+            enum Shape {
+                Circle { radius: f64 },
+            }
+
+            let value = PhpValue::Array(vec![
+                (
+                    PhpKey::Int(0),
+                    PhpValue::Enum {
+                        class: b"Suit".to_vec(),
+                        case: b"Hearts".to_vec(),
+                    },
+                ),
+                (PhpKey::Int(1), PhpValue::String(b"Spades".to_vec())),
+            ]);
+            let suits = Vec::<Suit>::deserialize(&value).unwrap();
+            assert_eq!(suits, vec![Suit::Hearts, Suit::Spades]);
+
+            let value = PhpValue::Array(vec![(
+                PhpKey::Str(b"Circle".to_vec()),
+                PhpValue::Array(vec![(
+                    PhpKey::Str(b"radius".to_vec()),
+                    PhpValue::Float(1.5),
+                )]),
+            )]);
+            let shape = Shape::deserialize(&value).unwrap();
+            assert_eq!(shape, Shape::Circle { radius: 1.5 });
+
+            let value = PhpValue::Array(vec![
+                (PhpKey::Int(0), PhpValue::Null),
+                (PhpKey::Int(1), PhpValue::Int(3)),
+            ]);
+            let options = <(Option<i64>, Option<i64>)>::deserialize(&value).unwrap();
+            assert_eq!(options, (None, Some(3)));
+
+            let value = PhpValue::Array(vec![(PhpKey::Int(1), PhpValue::Int(3))]);
+            let error = Vec::<i64>::deserialize(&value).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                "Deserialization error: Expected sequence index 0, found integer key 1"
+            );
+
+            let value = PhpValue::String(b"\xff".to_vec());
+            assert!(String::deserialize(&value).is_err());
+        }
+    }
+}